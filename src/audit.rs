@@ -0,0 +1,943 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Component, Path as StdPath, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::memory::{symlink_loop, MAX_SYMLINKS};
+use crate::{
+    DiskUsage, FileSystem, FileType, Metadata, OpenFile, OpenOptions, RawDirEntry, ReadDir,
+};
+
+/// A mutating operation recorded by an [AuditFileSystem].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AuditEvent {
+    /// A directory is created.
+    CreateDir {
+        /// The path of the directory.
+        path: String,
+        /// The requested permission bits.
+        mode: u32,
+    },
+    /// A file is opened for writing, which may create or truncate it.
+    Write {
+        /// The path of the file.
+        path: String,
+        /// The options it is opened with.
+        options: OpenOptions,
+    },
+    /// A file is renamed.
+    Rename {
+        /// The old path.
+        from: String,
+        /// The new path.
+        to: String,
+    },
+    /// A file or symlink is removed.
+    RemoveFile {
+        /// The path of the file.
+        path: String,
+    },
+    /// An empty directory is removed.
+    RemoveDir {
+        /// The path of the directory.
+        path: String,
+    },
+    /// A symlink is created.
+    Symlink {
+        /// The target of the symlink.
+        target: String,
+        /// The path of the symlink.
+        link: String,
+    },
+    /// A hard link is created.
+    HardLink {
+        /// The path of the existing file.
+        original: String,
+        /// The path of the new link.
+        link: String,
+    },
+    /// The permission bits of a file are changed.
+    SetPermissions {
+        /// The path of the file.
+        path: String,
+        /// The new permission bits.
+        mode: u32,
+    },
+    /// The contents of a file are copied to another one, which may create or truncate it.
+    Copy {
+        /// The path of the source file.
+        from: String,
+        /// The path of the target file.
+        to: String,
+    },
+    /// The owner or group of a file is changed.
+    SetOwner {
+        /// The path of the file.
+        path: String,
+        /// The new user ID, if it changes.
+        uid: Option<u32>,
+        /// The new group ID, if it changes.
+        gid: Option<u32>,
+        /// Whether a symlink at the path is followed.
+        follow: bool,
+    },
+    /// The access or modification time of a file is changed.
+    SetTimes {
+        /// The path of the file.
+        path: String,
+        /// The new access time, if it changes.
+        atime: Option<SystemTime>,
+        /// The new modification time, if it changes.
+        mtime: Option<SystemTime>,
+        /// Whether a symlink at the path is followed.
+        follow: bool,
+    },
+    /// An extended attribute of a file is set.
+    SetXattr {
+        /// The path of the file.
+        path: String,
+        /// The name of the attribute.
+        name: String,
+        /// The new value.
+        value: Vec<u8>,
+        /// Whether a symlink at the path is followed.
+        follow: bool,
+    },
+    /// An extended attribute of a file is removed.
+    RemoveXattr {
+        /// The path of the file.
+        path: String,
+        /// The name of the attribute.
+        name: String,
+        /// Whether a symlink at the path is followed.
+        follow: bool,
+    },
+}
+
+impl Display for AuditEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateDir { path, mode } => write!(f, "mkdir -m {mode:o} {}", Quoted(path)),
+            Self::Write { path, options } if options.append => write!(f, "append {}", Quoted(path)),
+            Self::Write { path, .. } => write!(f, "write {}", Quoted(path)),
+            Self::Rename { from, to } => write!(f, "mv {} {}", Quoted(from), Quoted(to)),
+            Self::RemoveFile { path } => write!(f, "rm {}", Quoted(path)),
+            Self::RemoveDir { path } => write!(f, "rmdir {}", Quoted(path)),
+            Self::Symlink { target, link } => {
+                write!(f, "ln -s {} {}", Quoted(target), Quoted(link))
+            }
+            Self::HardLink { original, link } => {
+                write!(f, "ln {} {}", Quoted(original), Quoted(link))
+            }
+            Self::SetPermissions { path, mode } => write!(f, "chmod {mode:o} {}", Quoted(path)),
+            Self::Copy { from, to } => write!(f, "cp {} {}", Quoted(from), Quoted(to)),
+            Self::SetOwner {
+                path,
+                uid,
+                gid,
+                follow,
+            } => {
+                let id = |id: &Option<u32>| id.map(|id| id.to_string()).unwrap_or_default();
+                let flag = if *follow { "" } else { " -h" };
+                write!(f, "chown{flag} {}:{} {}", id(uid), id(gid), Quoted(path))
+            }
+            Self::SetTimes {
+                path,
+                atime,
+                mtime,
+                follow,
+            } => {
+                let flag = if *follow { "" } else { " -h" };
+                let path = Quoted(path);
+                match (atime, mtime) {
+                    (Some(atime), Some(mtime)) if atime == mtime => {
+                        write!(f, "touch{flag} -d {} {path}", Timestamp(*atime))
+                    }
+                    (Some(atime), Some(mtime)) => write!(
+                        f,
+                        "touch{flag} -a -d {} {path}; touch{flag} -m -d {} {path}",
+                        Timestamp(*atime),
+                        Timestamp(*mtime),
+                    ),
+                    (Some(atime), None) => {
+                        write!(f, "touch{flag} -a -d {} {path}", Timestamp(*atime))
+                    }
+                    (None, Some(mtime)) => {
+                        write!(f, "touch{flag} -m -d {} {path}", Timestamp(*mtime))
+                    }
+                    (None, None) => write!(f, "touch{flag} -c -r {path} {path}"),
+                }
+            }
+            Self::SetXattr {
+                path,
+                name,
+                value,
+                follow,
+            } => {
+                let flag = if *follow { "" } else { " -h" };
+                write!(f, "setfattr{flag} -n {} -v 0x", Quoted(name))?;
+                for byte in value {
+                    write!(f, "{byte:02x}")?;
+                }
+                write!(f, " {}", Quoted(path))
+            }
+            Self::RemoveXattr { path, name, follow } => {
+                let flag = if *follow { "" } else { " -h" };
+                write!(f, "setfattr{flag} -x {} {}", Quoted(name), Quoted(path))
+            }
+        }
+    }
+}
+
+/// A string quoted for a POSIX shell if it has characters other than those that are safe
+/// unquoted, as by Python's `shlex.quote`.
+struct Quoted<'a>(&'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+        if !self.0.is_empty() && self.0.chars().all(safe) {
+            return f.write_str(self.0);
+        }
+        write!(f, "'{}'", self.0.replace('\'', "'\"'\"'"))
+    }
+}
+
+/// A time formatted as seconds since the Unix epoch for `touch -d`, such as `@1.5`.
+struct Timestamp(SystemTime);
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0.duration_since(UNIX_EPOCH) {
+            Ok(since) => write!(f, "@{}.{:09}", since.as_secs(), since.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                write!(f, "@-{}.{:09}", before.as_secs(), before.subsec_nanos())
+            }
+        }
+    }
+}
+
+/// A sink that [AuditEvent]s are reported to.
+type Sink = Box<dyn FnMut(&AuditEvent)>;
+
+/// What a dry run did to a path, which the wrapped filesystem does not reflect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Shadow {
+    /// A directory was created with these permission bits.
+    Dir(u32),
+    /// A file was created with these permission bits.
+    File(u32),
+    /// A symlink to this target was created.
+    Symlink(String),
+    /// The file at this absolute path of the wrapped filesystem was moved or linked here,
+    /// with its contents if it is a directory.
+    Inner(String),
+    /// The file was removed.
+    Removed,
+}
+
+impl Shadow {
+    /// Returns the metadata of a file created by a skipped operation, which is not one
+    /// moved from the wrapped filesystem or removed.
+    fn metadata(&self) -> Metadata {
+        let (file_type, mode, size) = match self {
+            Self::Dir(mode) => (FileType::Dir, *mode, 0),
+            Self::Symlink(target) => (FileType::Symlink, 0o777, target.len() as u64),
+            Self::File(mode) => (FileType::File, *mode, 0),
+            Self::Inner(_) | Self::Removed => (FileType::File, 0, 0),
+        };
+        Metadata {
+            file_type,
+            mode,
+            size,
+            nlink: 1,
+            ..Metadata::default()
+        }
+    }
+}
+
+/// Where a path leads in a dry run.
+#[derive(Debug)]
+enum Location {
+    /// The file is in the wrapped filesystem, at this path.
+    Inner(String),
+    /// The file was created by a skipped operation.
+    Created(Shadow),
+    /// The file does not exist.
+    Missing,
+}
+
+/// A backend that reports the mutating operations on another one to a sink, and can skip
+/// them for a dry run.
+///
+/// Events are reported once the operation succeeds. In a dry run, the wrapped filesystem
+/// is only read, and the backend keeps track of the files that the skipped operations
+/// would have created, moved and removed, so that reads and later operations see them and
+/// fail or succeed as they would have. Files opened for writing discard what is written to
+/// them and read as empty, and metadata other than the file type and permission bits of
+/// the files created is not tracked.
+pub struct AuditFileSystem<F> {
+    inner: F,
+    sink: RefCell<Sink>,
+    dry_run: bool,
+    /// The paths changed by the operations skipped in a dry run, as absolute paths without
+    /// `.` and `..` components.
+    shadow: RefCell<BTreeMap<String, Shadow>>,
+}
+
+impl<F: FileSystem> AuditFileSystem<F> {
+    /// Wraps `inner`, reporting events to `sink`.
+    pub fn new(inner: F, sink: impl FnMut(&AuditEvent) + 'static) -> Self {
+        Self {
+            inner,
+            sink: RefCell::new(Box::new(sink)),
+            dry_run: false,
+            shadow: RefCell::default(),
+        }
+    }
+
+    /// Sets whether mutating operations are skipped, which is disabled by default.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Returns the wrapped filesystem.
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// Reports `event` to the sink.
+    fn report(&self, event: &AuditEvent) {
+        (self.sink.borrow_mut())(event);
+    }
+
+    /// Runs the mutating operation `f`, or `simulate` instead in a dry run, and reports
+    /// `event` if it succeeds.
+    fn audit(
+        &self,
+        event: AuditEvent,
+        f: impl FnOnce(&F) -> Result<()>,
+        simulate: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        match self.dry_run {
+            true => simulate()?,
+            false => f(&self.inner)?,
+        }
+        self.report(&event);
+        Ok(())
+    }
+
+    /// Returns the key of `path` in the shadow map: the absolute path, with `.` components
+    /// and trailing separators removed and `..` components applied.
+    fn key(&self, path: &str) -> String {
+        let mut key = PathBuf::new();
+        if StdPath::new(path).is_relative() {
+            if let Ok(current_dir) = self.inner.current_dir() {
+                key.push(current_dir);
+            }
+        }
+        for component in StdPath::new(path).components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    let _ = key.pop();
+                }
+                component => key.push(component),
+            }
+        }
+        key.into_os_string().into_string().unwrap_or_default()
+    }
+
+    /// Returns where `path` leads after the operations skipped so far, with its key, following
+    /// the symlinks they created in all components but the last, and in the last one as well
+    /// if `follow` is true.
+    fn locate(&self, path: &str, follow: bool) -> Result<(String, Location)> {
+        let mut key = self.key(path);
+        let mut redirected = false;
+        for _ in 0..=MAX_SYMLINKS {
+            let shadow = self.shadow.borrow();
+            let found = StdPath::new(&key).ancestors().find_map(|ancestor| {
+                let entry = shadow.get(ancestor.to_str()?)?;
+                Some((ancestor, entry))
+            });
+            let Some((ancestor, entry)) = found else {
+                let path = match redirected {
+                    true => key.clone(),
+                    false => path.to_owned(),
+                };
+                return Ok((key, Location::Inner(path)));
+            };
+            let rest = StdPath::new(&key)
+                .strip_prefix(ancestor)
+                .unwrap_or(StdPath::new(""));
+            let location = match entry {
+                Shadow::Removed => Location::Missing,
+                Shadow::Inner(inner) => Location::Inner(join(inner, rest)),
+                Shadow::Symlink(target) if follow || !rest.as_os_str().is_empty() => {
+                    let dir = ancestor.parent().unwrap_or(ancestor);
+                    let target = join(&join(&dir.to_string_lossy(), StdPath::new(target)), rest);
+                    drop(shadow);
+                    key = self.key(&target);
+                    redirected = true;
+                    continue;
+                }
+                entry if rest.as_os_str().is_empty() => Location::Created(entry.clone()),
+                Shadow::Dir(_) => Location::Missing,
+                _ => return Err(ErrorKind::NotADirectory.into()),
+            };
+            return Ok((key, location));
+        }
+        Err(symlink_loop())
+    }
+
+    /// Returns the metadata of the file at `path` as changed by the operations skipped so
+    /// far.
+    fn dry_metadata(&self, path: &str, follow: bool) -> Result<Metadata> {
+        match self.locate(path, follow)?.1 {
+            Location::Inner(path) if follow => self.inner.metadata(&path),
+            Location::Inner(path) => self.inner.symlink_metadata(&path),
+            Location::Created(shadow) => Ok(shadow.metadata()),
+            Location::Missing => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    /// Returns the entries of the directory at `path` as changed by the operations skipped
+    /// so far.
+    fn dry_read_dir(&self, path: &str) -> Result<Vec<RawDirEntry>> {
+        let (key, location) = self.locate(path, true)?;
+        let mut entries = match location {
+            Location::Inner(path) => self.inner.read_dir(&path)?.collect::<Result<Vec<_>>>()?,
+            Location::Created(Shadow::Dir(_)) => Vec::new(),
+            Location::Created(_) => return Err(ErrorKind::NotADirectory.into()),
+            Location::Missing => return Err(ErrorKind::NotFound.into()),
+        };
+        let shadow = self.shadow.borrow();
+        entries.retain(|entry| !shadow.contains_key(&join(&key, StdPath::new(&entry.name))));
+        for (child, entry) in shadow.iter() {
+            let Some(name) = StdPath::new(child)
+                .strip_prefix(&key)
+                .ok()
+                .filter(|name| name.components().count() == 1)
+            else {
+                continue;
+            };
+            let file_type = match entry {
+                Shadow::Removed => continue,
+                Shadow::Inner(inner) => self.inner.symlink_metadata(inner)?.file_type,
+                entry => entry.metadata().file_type,
+            };
+            entries.push(RawDirEntry {
+                name: name.as_os_str().to_owned(),
+                file_type,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Returns the file type of `path` as changed by the operations skipped so far, or
+    /// `None` if it does not exist.
+    fn file_type(&self, path: &str, follow: bool) -> Option<FileType> {
+        self.dry_metadata(path, follow)
+            .ok()
+            .map(|metadata| metadata.file_type)
+    }
+
+    /// Records that a skipped operation changed `path`, which replaces whatever was there.
+    fn record(&self, path: &str, shadow: Shadow) {
+        let key = self.key(path);
+        let mut map = self.shadow.borrow_mut();
+        map.retain(|other, _| !is_below(other, &key));
+        let _ = map.insert(key, shadow);
+    }
+
+    /// Returns the permission bits of a file created with `mode`.
+    fn masked(&self, mode: u32) -> u32 {
+        mode & !self.inner.umask().unwrap_or(0)
+    }
+
+    /// Checks that `path` exists, and returns its file type.
+    fn check_exists(&self, path: &str, follow: bool) -> Result<FileType> {
+        Ok(self.dry_metadata(path, follow)?.file_type)
+    }
+
+    /// Checks that the parent of `path` is a directory, so that `path` can be created.
+    fn check_parent(&self, path: &str) -> Result<()> {
+        let key = self.key(path);
+        let Some(parent) = StdPath::new(&key).parent().and_then(StdPath::to_str) else {
+            return Ok(());
+        };
+        match self.file_type(parent, true) {
+            Some(file_type) if file_type.is_dir() => Ok(()),
+            Some(_) => Err(ErrorKind::NotADirectory.into()),
+            None => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    /// Checks that `path` does not exist and that its parent is a directory.
+    fn check_creatable(&self, path: &str) -> Result<()> {
+        if self.file_type(path, false).is_some() {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        self.check_parent(path)
+    }
+
+    /// Returns what to record for a file created as a copy or link of `path`.
+    fn shadow_of(&self, path: &str) -> Result<Shadow> {
+        match self.locate(path, false)?.1 {
+            Location::Inner(path) => Ok(Shadow::Inner(self.key(&path))),
+            Location::Created(shadow) => Ok(shadow),
+            Location::Missing => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    /// Simulates renaming `from` to `to`.
+    fn dry_rename(&self, from: &str, to: &str) -> Result<()> {
+        let file_type = self.check_exists(from, false)?;
+        self.check_parent(to)?;
+        let (from_key, to_key) = (self.key(from), self.key(to));
+        if is_below(&to_key, &from_key) {
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        if from_key == to_key {
+            return Ok(());
+        }
+        match self.file_type(to, false) {
+            Some(target) if target.is_dir() && !file_type.is_dir() => {
+                return Err(ErrorKind::IsADirectory.into());
+            }
+            Some(target) if file_type.is_dir() && !target.is_dir() => {
+                return Err(ErrorKind::NotADirectory.into());
+            }
+            Some(target) if target.is_dir() && !self.dry_read_dir(to)?.is_empty() => {
+                return Err(ErrorKind::DirectoryNotEmpty.into());
+            }
+            _ => {}
+        }
+        let shadow = self.shadow_of(from)?;
+        let moved = self
+            .shadow
+            .borrow()
+            .iter()
+            .filter_map(|(key, entry)| {
+                let rest = StdPath::new(key).strip_prefix(&from_key).ok()?;
+                (!rest.as_os_str().is_empty()).then(|| (join(&to_key, rest), entry.clone()))
+            })
+            .collect::<Vec<_>>();
+        self.record(from, Shadow::Removed);
+        self.record(to, shadow);
+        self.shadow.borrow_mut().extend(moved);
+        Ok(())
+    }
+}
+
+/// Returns whether `path` is strictly below the directory `dir`.
+fn is_below(path: &str, dir: &str) -> bool {
+    StdPath::new(path)
+        .strip_prefix(dir)
+        .is_ok_and(|rest| !rest.as_os_str().is_empty())
+}
+
+/// Joins `path` and `rest`, which may be empty.
+fn join(path: &str, rest: &StdPath) -> String {
+    match rest.as_os_str().is_empty() {
+        true => path.to_owned(),
+        false => StdPath::new(path).join(rest).to_string_lossy().into_owned(),
+    }
+}
+
+impl<F: Debug> Debug for AuditFileSystem<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditFileSystem")
+            .field("inner", &self.inner)
+            .field("dry_run", &self.dry_run)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: FileSystem> FileSystem for AuditFileSystem<F> {
+    fn current_dir(&self) -> Result<String> {
+        self.inner.current_dir()
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        match self.dry_run {
+            true => self.dry_metadata(path, true),
+            false => self.inner.metadata(path),
+        }
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        match self.dry_run {
+            true => self.dry_metadata(path, false),
+            false => self.inner.symlink_metadata(path),
+        }
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String> {
+        if !self.dry_run {
+            return self.inner.canonicalize(path);
+        }
+        match self.locate(path, true)? {
+            (_, Location::Inner(inner)) if inner == path => self.inner.canonicalize(path),
+            (key, Location::Inner(inner)) => self.inner.metadata(&inner).map(|_| key),
+            (key, Location::Created(_)) => Ok(key),
+            (_, Location::Missing) => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn read_dir(&self, path: &str) -> Result<ReadDir> {
+        match self.dry_run {
+            true => Ok(Box::new(self.dry_read_dir(path)?.into_iter().map(Ok))),
+            false => self.inner.read_dir(path),
+        }
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        if !self.dry_run {
+            return self.inner.read_link(path);
+        }
+        match self.locate(path, false)?.1 {
+            Location::Inner(path) => self.inner.read_link(&path),
+            Location::Created(Shadow::Symlink(target)) => Ok(target),
+            Location::Created(_) => Err(ErrorKind::InvalidInput.into()),
+            Location::Missing => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        if !(options.writes() || options.create || options.create_new) {
+            if !self.dry_run {
+                return self.inner.open(path, options);
+            }
+            return match self.locate(path, true)?.1 {
+                Location::Inner(path) => self.inner.open(&path, options),
+                Location::Created(shadow) => Ok(Box::new(Discarded {
+                    metadata: shadow.metadata(),
+                })),
+                Location::Missing => Err(ErrorKind::NotFound.into()),
+            };
+        }
+        let event = AuditEvent::Write {
+            path: path.to_owned(),
+            options: *options,
+        };
+        let file: Box<dyn OpenFile> = match self.dry_run {
+            true => {
+                match self.file_type(path, true) {
+                    Some(file_type) if file_type.is_dir() => {
+                        return Err(ErrorKind::IsADirectory.into());
+                    }
+                    Some(_) if options.create_new => return Err(ErrorKind::AlreadyExists.into()),
+                    Some(_) => {}
+                    None if options.create || options.create_new => {
+                        self.check_parent(path)?;
+                        self.record(path, Shadow::File(self.masked(options.mode)));
+                    }
+                    None => return Err(ErrorKind::NotFound.into()),
+                }
+                Box::new(Discarded {
+                    metadata: self.dry_metadata(path, true)?,
+                })
+            }
+            false => self.inner.open(path, options)?,
+        };
+        self.report(&event);
+        Ok(file)
+    }
+
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+        let event = AuditEvent::CreateDir {
+            path: path.to_owned(),
+            mode,
+        };
+        self.audit(
+            event,
+            |fs| fs.create_dir(path, mode),
+            || {
+                self.check_creatable(path)?;
+                self.record(path, Shadow::Dir(self.masked(mode)));
+                Ok(())
+            },
+        )
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        let event = AuditEvent::RemoveFile {
+            path: path.to_owned(),
+        };
+        self.audit(
+            event,
+            |fs| fs.remove_file(path),
+            || {
+                if self.check_exists(path, false)?.is_dir() {
+                    return Err(ErrorKind::IsADirectory.into());
+                }
+                self.record(path, Shadow::Removed);
+                Ok(())
+            },
+        )
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        let event = AuditEvent::RemoveDir {
+            path: path.to_owned(),
+        };
+        self.audit(
+            event,
+            |fs| fs.remove_dir(path),
+            || {
+                if !self.check_exists(path, false)?.is_dir() {
+                    return Err(ErrorKind::NotADirectory.into());
+                }
+                if !self.dry_read_dir(path)?.is_empty() {
+                    return Err(ErrorKind::DirectoryNotEmpty.into());
+                }
+                self.record(path, Shadow::Removed);
+                Ok(())
+            },
+        )
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let event = AuditEvent::Rename {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        };
+        self.audit(
+            event,
+            |fs| fs.rename(from, to),
+            || self.dry_rename(from, to),
+        )
+    }
+
+    fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        let event = AuditEvent::Symlink {
+            target: target.to_owned(),
+            link: link.to_owned(),
+        };
+        self.audit(
+            event,
+            |fs| fs.symlink(target, link),
+            || {
+                self.check_creatable(link)?;
+                self.record(link, Shadow::Symlink(target.to_owned()));
+                Ok(())
+            },
+        )
+    }
+
+    fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+        let event = AuditEvent::HardLink {
+            original: original.to_owned(),
+            link: link.to_owned(),
+        };
+        self.audit(
+            event,
+            |fs| fs.hard_link(original, link),
+            || {
+                if self.check_exists(original, false)?.is_dir() {
+                    return Err(ErrorKind::PermissionDenied.into());
+                }
+                self.check_creatable(link)?;
+                self.record(link, self.shadow_of(original)?);
+                Ok(())
+            },
+        )
+    }
+
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+        let event = AuditEvent::SetPermissions {
+            path: path.to_owned(),
+            mode,
+        };
+        self.audit(
+            event,
+            |fs| fs.set_permissions(path, mode),
+            || {
+                let (key, location) = self.locate(path, true)?;
+                if let Location::Missing = location {
+                    return Err(ErrorKind::NotFound.into());
+                }
+                if let Some(Shadow::Dir(old) | Shadow::File(old)) =
+                    self.shadow.borrow_mut().get_mut(&key)
+                {
+                    *old = mode;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        let event = AuditEvent::SetOwner {
+            path: path.to_owned(),
+            uid,
+            gid,
+            follow,
+        };
+        self.audit(
+            event,
+            |fs| fs.set_owner(path, uid, gid, follow),
+            || self.check_exists(path, follow).map(drop),
+        )
+    }
+
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        let event = AuditEvent::SetTimes {
+            path: path.to_owned(),
+            atime,
+            mtime,
+            follow,
+        };
+        self.audit(
+            event,
+            |fs| fs.set_times(path, atime, mtime, follow),
+            || self.check_exists(path, follow).map(drop),
+        )
+    }
+
+    fn umask(&self) -> Result<u32> {
+        self.inner.umask()
+    }
+
+    fn disk_usage(&self, path: &str) -> Result<DiskUsage> {
+        self.inner.disk_usage(path)
+    }
+
+    fn copy_file(&self, from: &str, to: &str, progress: &mut dyn FnMut(u64)) -> Result<u64> {
+        let event = AuditEvent::Copy {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        };
+        let mut copied = 0;
+        self.audit(
+            event,
+            |fs| {
+                copied = fs.copy_file(from, to, progress)?;
+                Ok(())
+            },
+            || {
+                let source = self.dry_metadata(from, true)?;
+                if source.is_dir() {
+                    return Err(ErrorKind::IsADirectory.into());
+                }
+                match self.file_type(to, true) {
+                    Some(file_type) if file_type.is_dir() => Err(ErrorKind::IsADirectory.into()),
+                    Some(_) => Ok(()),
+                    None => {
+                        self.check_parent(to)?;
+                        self.record(to, Shadow::File(source.mode));
+                        Ok(())
+                    }
+                }
+            },
+        )?;
+        Ok(copied)
+    }
+
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        if !self.dry_run {
+            return self.inner.list_xattrs(path, follow);
+        }
+        match self.locate(path, follow)?.1 {
+            Location::Inner(path) => self.inner.list_xattrs(&path, follow),
+            Location::Created(_) => Ok(Vec::new()),
+            Location::Missing => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        if !self.dry_run {
+            return self.inner.get_xattr(path, name, follow);
+        }
+        match self.locate(path, follow)?.1 {
+            Location::Inner(path) => self.inner.get_xattr(&path, name, follow),
+            Location::Created(_) => Ok(None),
+            Location::Missing => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        let event = AuditEvent::SetXattr {
+            path: path.to_owned(),
+            name: name.to_owned(),
+            value: value.to_vec(),
+            follow,
+        };
+        self.audit(
+            event,
+            |fs| fs.set_xattr(path, name, value, follow),
+            || self.check_exists(path, follow).map(drop),
+        )
+    }
+
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        let event = AuditEvent::RemoveXattr {
+            path: path.to_owned(),
+            name: name.to_owned(),
+            follow,
+        };
+        self.audit(
+            event,
+            |fs| fs.remove_xattr(path, name, follow),
+            || self.check_exists(path, follow).map(drop),
+        )
+    }
+}
+
+/// A file opened for writing in a dry run, which discards what is written to it.
+struct Discarded {
+    metadata: Metadata,
+}
+
+impl Read for Discarded {
+    fn read(&mut self, _: &mut [u8]) -> Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for Discarded {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for Discarded {
+    fn seek(&mut self, _: SeekFrom) -> Result<u64> {
+        Ok(0)
+    }
+}
+
+impl OpenFile for Discarded {
+    fn metadata(&self) -> Result<Metadata> {
+        Ok(self.metadata)
+    }
+
+    fn set_len(&mut self, _: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_modified(&mut self, _: SystemTime) -> Result<()> {
+        Ok(())
+    }
+}
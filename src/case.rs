@@ -0,0 +1,233 @@
+use std::io::Result;
+use std::time::SystemTime;
+
+use crate::{DiskUsage, FileSystem, Metadata, OpenFile, OpenOptions, ReadDir, Windows};
+
+/// A backend that makes another one case-insensitive and case-preserving, as on Windows
+/// and macOS.
+///
+/// Each component of a path refers to the entry of its directory whose name folds to the
+/// same characters, using the case folding of NTFS that [Windows] globs match with, and an
+/// exact match is preferred. New files keep the spelling they are created with, and
+/// renaming a file to another spelling of its own name changes the spelling. Results such as
+/// [`canonicalize`](FileSystem::canonicalize) and [`read_dir`](FileSystem::read_dir) come
+/// from the wrapped filesystem, so they have the spelling on disk.
+///
+/// Symlink targets are resolved by the wrapped filesystem, so their spelling must match.
+#[derive(Debug, Clone, Default)]
+pub struct CaseInsensitiveFileSystem<F> {
+    inner: F,
+    #[cfg(feature = "normalization")]
+    normalization_insensitive: bool,
+}
+
+impl<F: FileSystem> CaseInsensitiveFileSystem<F> {
+    /// Wraps `inner`.
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            #[cfg(feature = "normalization")]
+            normalization_insensitive: false,
+        }
+    }
+
+    /// Sets whether names are also compared regardless of their Unicode normalization, as
+    /// on APFS. This is disabled by default.
+    #[cfg(feature = "normalization")]
+    pub fn normalization_insensitive(mut self, normalization_insensitive: bool) -> Self {
+        self.normalization_insensitive = normalization_insensitive;
+        self
+    }
+
+    /// Returns the wrapped filesystem.
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// Returns the key that names are compared by.
+    fn key(&self, name: &str) -> String {
+        #[cfg(feature = "normalization")]
+        if self.normalization_insensitive {
+            use unicode_normalization::UnicodeNormalization;
+            return name.nfd().map(Windows::fold_case).nfd().collect();
+        }
+        name.chars().map(Windows::fold_case).collect()
+    }
+
+    /// Returns the spelling on disk of `name`, an entry of the directory `dir`.
+    fn find(&self, dir: &str, name: &str) -> Option<String> {
+        let key = self.key(name);
+        let mut found = None;
+        for entry in self.inner.read_dir(dir).ok()? {
+            let Ok(entry) = entry else { continue };
+            let Some(entry) = entry.name.to_str() else {
+                continue;
+            };
+            if entry == name {
+                return Some(entry.to_owned());
+            }
+            if found.is_none() && self.key(entry) == key {
+                found = Some(entry.to_owned());
+            }
+        }
+        found
+    }
+
+    /// Returns `path` with the spelling on disk of each component that exists.
+    fn on_disk(&self, path: &str) -> String {
+        let mut resolved = String::with_capacity(path.len());
+        let mut rest = path;
+        let mut first = true;
+        while !rest.is_empty() {
+            let end = rest.find(std::path::is_separator).unwrap_or(rest.len());
+            let (name, tail) = rest.split_at(end);
+            let special = matches!(name, "" | "." | "..") || (first && name.ends_with(':'));
+            let found = match special {
+                true => None,
+                false => {
+                    let dir = match resolved.as_str() {
+                        "" => ".",
+                        dir => dir,
+                    };
+                    self.find(dir, name)
+                }
+            };
+            match found {
+                Some(found) => resolved.push_str(&found),
+                None if special => resolved.push_str(name),
+                None => {
+                    // Nothing below a missing file exists either.
+                    resolved.push_str(rest);
+                    break;
+                }
+            }
+            let separator = tail.chars().next().map_or(0, char::len_utf8);
+            resolved.push_str(&tail[..separator]);
+            rest = &tail[separator..];
+            first = false;
+        }
+        resolved
+    }
+}
+
+impl<F: FileSystem> FileSystem for CaseInsensitiveFileSystem<F> {
+    fn current_dir(&self) -> Result<String> {
+        self.inner.current_dir()
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        self.inner.metadata(&self.on_disk(path))
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        self.inner.symlink_metadata(&self.on_disk(path))
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String> {
+        self.inner.canonicalize(&self.on_disk(path))
+    }
+
+    fn read_dir(&self, path: &str) -> Result<ReadDir> {
+        self.inner.read_dir(&self.on_disk(path))
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        self.inner.read_link(&self.on_disk(path))
+    }
+
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        self.inner.open(&self.on_disk(path), options)
+    }
+
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+        self.inner.create_dir(&self.on_disk(path), mode)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        self.inner.remove_file(&self.on_disk(path))
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        self.inner.remove_dir(&self.on_disk(path))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let from = self.on_disk(from);
+        let mut target = self.on_disk(to);
+        if target == from {
+            // Only the spelling of the name changes.
+            let name = |path: &str| {
+                let path = path.trim_end_matches(std::path::is_separator);
+                path.rfind(std::path::is_separator).map_or(0, |i| i + 1)
+            };
+            target = format!("{}{}", &from[..name(&from)], &to[name(to)..])
+                .trim_end_matches(std::path::is_separator)
+                .to_owned();
+        }
+        self.inner.rename(&from, &target)
+    }
+
+    fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        self.inner.symlink(target, &self.on_disk(link))
+    }
+
+    fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+        self.inner
+            .hard_link(&self.on_disk(original), &self.on_disk(link))
+    }
+
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+        self.inner.set_permissions(&self.on_disk(path), mode)
+    }
+
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        self.inner.set_owner(&self.on_disk(path), uid, gid, follow)
+    }
+
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        self.inner
+            .set_times(&self.on_disk(path), atime, mtime, follow)
+    }
+
+    fn umask(&self) -> Result<u32> {
+        self.inner.umask()
+    }
+
+    fn disk_usage(&self, path: &str) -> Result<DiskUsage> {
+        self.inner.disk_usage(&self.on_disk(path))
+    }
+
+    fn copy_file(&self, from: &str, to: &str, progress: &mut dyn FnMut(u64)) -> Result<u64> {
+        self.inner
+            .copy_file(&self.on_disk(from), &self.on_disk(to), progress)
+    }
+
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        self.inner.list_xattrs(&self.on_disk(path), follow)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        self.inner.get_xattr(&self.on_disk(path), name, follow)
+    }
+
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        self.inner
+            .set_xattr(&self.on_disk(path), name, value, follow)
+    }
+
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        self.inner.remove_xattr(&self.on_disk(path), name, follow)
+    }
+}
@@ -0,0 +1,364 @@
+use std::convert::Infallible;
+use std::io::{Error, ErrorKind, Result};
+use std::rc::Rc;
+
+use crate::error::at;
+use crate::glob::Pattern;
+use crate::{fs, FileSystem, Metadata, Path, PurePath, Timestamp};
+
+/// What [`Path::copy_to`] and [`Path::copy_tree`] do with a file that exists at the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OverwritePolicy {
+    /// Replace the file.
+    #[default]
+    Replace,
+    /// Keep the file, and skip the copy silently.
+    Skip,
+    /// Fail with an error of kind [`ErrorKind::AlreadyExists`].
+    Error,
+    /// Replace the file only if it was modified before the source, as `cp --update` does.
+    Update,
+}
+
+/// The progress of a copy, reported to the callback of [`CopyOptions::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CopyProgress<'a> {
+    /// The path of the file being copied.
+    pub from: &'a str,
+    /// The path it is copied to.
+    pub to: &'a str,
+    /// The number of bytes of the file copied so far.
+    pub copied: u64,
+    /// The size of the file when the copy started.
+    pub size: u64,
+    /// The number of bytes copied so far by the whole operation, including this file.
+    pub total: u64,
+}
+
+/// A callback that the progress of a copy is reported to.
+type Progress = Box<dyn FnMut(&CopyProgress<'_>)>;
+
+/// Options for [`Path::copy_to`] and [`Path::copy_tree`].
+///
+/// By default, the permission bits are preserved and the other metadata is not, as by
+/// `shutil.copy`. Symlinks are followed, files at the target are replaced, and the target
+/// of a tree must not exist.
+pub struct CopyOptions {
+    mode: bool,
+    times: bool,
+    ownership: bool,
+    xattrs: bool,
+    follow_symlinks: bool,
+    overwrite: OverwritePolicy,
+    dirs_exist_ok: bool,
+    ignore: Vec<Pattern>,
+    progress: Option<Progress>,
+}
+
+impl CopyOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self {
+            mode: true,
+            times: false,
+            ownership: false,
+            xattrs: false,
+            follow_symlinks: true,
+            overwrite: OverwritePolicy::default(),
+            dirs_exist_ok: false,
+            ignore: Vec::new(),
+            progress: None,
+        }
+    }
+
+    /// Sets whether the permission bits are preserved, which is the default.
+    pub fn mode(mut self, mode: bool) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets whether the access and modification times are preserved, as by `shutil.copy2`.
+    pub fn times(mut self, times: bool) -> Self {
+        self.times = times;
+        self
+    }
+
+    /// Sets whether the owner and group are preserved, which usually needs privileges.
+    ///
+    /// They are set before the extended attributes and permission bits, since changing them
+    /// clears the set-user-ID bit and file capabilities.
+    pub fn ownership(mut self, ownership: bool) -> Self {
+        self.ownership = ownership;
+        self
+    }
+
+    /// Sets whether the extended attributes are preserved, such as `user.*` and
+    /// `security.capability`.
+    ///
+    /// Sources on filesystems without extended attributes have none to copy. As by
+    /// `shutil.copystat`, attributes that the target does not support or does not allow to
+    /// be set, such as `security.*` ones without privileges, are skipped.
+    pub fn xattrs(mut self, xattrs: bool) -> Self {
+        self.xattrs = xattrs;
+        self
+    }
+
+    /// Sets whether symlinks are followed, which is the default, or copied as symlinks.
+    ///
+    /// The root of a tree is always followed.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets what happens to files that exist at the target.
+    ///
+    /// A symlink at the target is replaced rather than written through, and a directory is
+    /// never replaced by a file.
+    pub fn overwrite(mut self, overwrite: OverwritePolicy) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Sets whether directories of a tree may exist at the target already, in which case
+    /// the files are copied into them.
+    pub fn dirs_exist_ok(mut self, dirs_exist_ok: bool) -> Self {
+        self.dirs_exist_ok = dirs_exist_ok;
+        self
+    }
+
+    /// Skips the entries of a tree whose names match `pattern`, in addition to those already
+    /// skipped.
+    ///
+    /// `?`, `*` and `[..]` are wildcards as in [Glob](crate::Glob), matched against whole
+    /// names, as by `shutil.ignore_patterns`.
+    pub fn ignore(mut self, pattern: &str) -> Self {
+        self.ignore.push(Pattern::new(pattern));
+        self
+    }
+
+    /// Sets a callback that is called with the progress of each file as it is copied.
+    pub fn progress(mut self, progress: impl FnMut(&CopyProgress<'_>) + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copies the file at `from` to `to`, as described by [`Path::copy_to`].
+pub(crate) fn copy<P>(from: &P, to: &P, options: &mut CopyOptions) -> Result<u64>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    let to = match (to.is_dir(), from.file_name()) {
+        (true, Some(name)) => to.join(&P::from(name)),
+        _ => to.clone(),
+    };
+    let mut copier = Copier {
+        fs: fs::current(),
+        options,
+        total: 0,
+    };
+    copier.copy_file(from, &to)?;
+    Ok(copier.total)
+}
+
+/// Copies the directory tree at `from` to `to`, as described by [`Path::copy_tree`].
+pub(crate) fn copy_tree<P>(from: &P, to: &P, options: &mut CopyOptions) -> Result<u64>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    let mut copier = Copier {
+        fs: fs::current(),
+        options,
+        total: 0,
+    };
+    copier.copy_dir(from, to)?;
+    Ok(copier.total)
+}
+
+/// The state of a copy.
+struct Copier<'a> {
+    fs: Rc<dyn FileSystem>,
+    options: &'a mut CopyOptions,
+    /// The number of bytes copied so far.
+    total: u64,
+}
+
+impl Copier<'_> {
+    /// Copies the directory at `from` to `to` with its contents.
+    fn copy_dir<P>(&mut self, from: &P, to: &P) -> Result<()>
+    where
+        P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+    {
+        let metadata = from.metadata()?;
+        if !metadata.is_dir() {
+            return Err(at(from.as_ref())(ErrorKind::NotADirectory.into()));
+        }
+        // The entries are listed before the target is created, so that a target inside the
+        // tree is not copied into itself.
+        let entries = from.scandir()?.collect::<Result<Vec<_>>>()?;
+        to.mkdir(0o777, true, self.options.dirs_exist_ok)?;
+        for entry in entries {
+            let name = entry.file_name();
+            if self
+                .options
+                .ignore
+                .iter()
+                .any(|pattern| pattern.matches(name))
+            {
+                continue;
+            }
+            let (from, to) = (entry.path(), to.join(&P::from(name)));
+            let is_dir = match self.options.follow_symlinks {
+                true => from.is_dir(),
+                false => entry.is_dir(),
+            };
+            match is_dir {
+                true => self.copy_dir(&from, &to)?,
+                false => self.copy_file(&from, &to)?,
+            }
+        }
+        self.preserve(from, to, &metadata)
+    }
+
+    /// Copies the file or symlink at `from` to `to`.
+    fn copy_file<P>(&mut self, from: &P, to: &P) -> Result<()>
+    where
+        P: Path + PurePath<Error = Infallible> + AsRef<str> + for<'a> From<&'a str>,
+    {
+        let metadata = match self.options.follow_symlinks {
+            true => from.metadata()?,
+            false => from.symlink_metadata()?,
+        };
+        if metadata.is_dir() {
+            return Err(at(from.as_ref())(ErrorKind::IsADirectory.into()));
+        }
+        if metadata.is_fifo() {
+            let error = Error::new(ErrorKind::InvalidInput, "Cannot copy a named pipe");
+            return Err(at(from.as_ref())(error));
+        }
+        if !self.prepare_target(from, to, &metadata)? {
+            return Ok(());
+        }
+
+        if metadata.is_symlink() {
+            to.symlink_to(&from.read_link()?)?;
+        } else {
+            let (from_str, to_str) = (from.as_ref(), to.as_ref());
+            let (size, total) = (metadata.size, self.total);
+            let mut progress = |copied| {
+                if let Some(progress) = &mut self.options.progress {
+                    progress(&CopyProgress {
+                        from: from_str,
+                        to: to_str,
+                        copied,
+                        size,
+                        total: total + copied,
+                    });
+                }
+            };
+            let copied = self
+                .fs
+                .copy_file(from_str, to_str, &mut progress)
+                .map_err(at(from_str))?;
+            self.total += copied;
+        }
+        self.preserve(from, to, &metadata)
+    }
+
+    /// Applies the overwrite policy to the file at `to`, and returns whether the copy goes
+    /// ahead.
+    fn prepare_target<P>(&self, from: &P, to: &P, metadata: &Metadata) -> Result<bool>
+    where
+        P: Path + AsRef<str>,
+    {
+        let existing = match to.symlink_metadata() {
+            Ok(existing) => existing,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(true),
+            Err(err) => return Err(err),
+        };
+        if !existing.is_symlink() && !metadata.is_symlink() && from.samefile(to)? {
+            let error = Error::new(
+                ErrorKind::InvalidInput,
+                "Source and target are the same file",
+            );
+            return Err(at(to.as_ref())(error));
+        }
+        match self.options.overwrite {
+            OverwritePolicy::Replace => {}
+            OverwritePolicy::Skip => return Ok(false),
+            OverwritePolicy::Error => {
+                return Err(at(to.as_ref())(ErrorKind::AlreadyExists.into()));
+            }
+            OverwritePolicy::Update if existing.mtime >= metadata.mtime => return Ok(false),
+            OverwritePolicy::Update => {}
+        }
+        if existing.is_dir() {
+            return Err(at(to.as_ref())(ErrorKind::IsADirectory.into()));
+        }
+        if existing.is_symlink() || metadata.is_symlink() {
+            to.unlink(false)?;
+        }
+        Ok(true)
+    }
+
+    /// Copies the metadata of `from` that the options preserve to `to`.
+    fn preserve<P: AsRef<str>>(&self, from: &P, to: &P, metadata: &Metadata) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        if self.options.ownership {
+            self.fs
+                .set_owner(to, Some(metadata.uid), Some(metadata.gid), false)
+                .map_err(at(to))?;
+        }
+        if self.options.xattrs {
+            self.copy_xattrs(from, to).map_err(at(to))?;
+        }
+        if self.options.mode && !metadata.is_symlink() {
+            self.fs.set_permissions(to, metadata.mode).map_err(at(to))?;
+        }
+        if self.options.times {
+            let atime = metadata.atime.and_then(Timestamp::to_system_time);
+            let mtime = metadata.mtime.and_then(Timestamp::to_system_time);
+            self.fs.set_times(to, atime, mtime, false).map_err(at(to))?;
+        }
+        Ok(())
+    }
+
+    /// Copies the extended attributes of `from` to `to`.
+    fn copy_xattrs(&self, from: &str, to: &str) -> Result<()> {
+        let follow = self.options.follow_symlinks;
+        let names = match self.fs.list_xattrs(from, follow) {
+            Err(err) if err.kind() == ErrorKind::Unsupported => return Ok(()),
+            names => names?,
+        };
+        for name in names {
+            let result = match self.fs.get_xattr(from, &name, follow) {
+                Ok(Some(value)) => self.fs.set_xattr(to, &name, &value, false),
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            };
+            match result {
+                Err(err) if is_skipped(&err) => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `error`, from copying an extended attribute, is skipped.
+///
+/// These are `EPERM`, `EACCES`, `ENOTSUP` and `EINVAL`, which `shutil` skips as well. An
+/// attribute removed in the meantime reads as `None` rather than failing with `ENODATA`.
+fn is_skipped(error: &Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::PermissionDenied | ErrorKind::Unsupported | ErrorKind::InvalidInput
+    )
+}
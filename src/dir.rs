@@ -0,0 +1,178 @@
+use std::convert::Infallible;
+use std::fmt::{self, Debug, Formatter};
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path as StdPath;
+use std::rc::Rc;
+
+use crate::error::at;
+use crate::{fs, FileSystem, FileType, Metadata, NonUtf8Error, PurePath, ReadDir};
+
+/// What to do with directory entries whose names are not valid UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NonUtf8Policy {
+    /// Yield an error wrapping a [NonUtf8Error].
+    #[default]
+    Error,
+    /// Skip the entry silently.
+    Skip,
+}
+
+/// An entry of a directory, returned by [`Path::scandir`](crate::Path::scandir).
+///
+/// The file type is read while iterating, so querying it does not touch the filesystem again.
+#[derive(Clone)]
+pub struct DirEntry<P> {
+    path: P,
+    file_name: String,
+    file_type: FileType,
+    /// The backend the entry was read from.
+    fs: Rc<dyn FileSystem>,
+}
+
+impl<P: Debug> Debug for DirEntry<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DirEntry")
+            .field("path", &self.path)
+            .field("file_name", &self.file_name)
+            .field("file_type", &self.file_type)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> DirEntry<P> {
+    /// Returns the full path of the entry.
+    pub fn path(&self) -> P
+    where
+        P: Clone,
+    {
+        self.path.clone()
+    }
+
+    /// Consumes the entry and returns its full path.
+    pub fn into_path(self) -> P {
+        self.path
+    }
+
+    /// Returns the file name of the entry.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Returns the file type of the entry, without following symlinks.
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns whether the entry is a directory, without following symlinks.
+    pub fn is_dir(&self) -> bool {
+        self.file_type.is_dir()
+    }
+
+    /// Returns whether the entry is a file, without following symlinks.
+    pub fn is_file(&self) -> bool {
+        self.file_type.is_file()
+    }
+
+    /// Returns whether the entry is a symlink.
+    pub fn is_symlink(&self) -> bool {
+        self.file_type.is_symlink()
+    }
+}
+
+impl<P: AsRef<str>> DirEntry<P> {
+    /// Returns the metadata of the entry, without following symlinks.
+    pub fn metadata(&self) -> Result<Metadata> {
+        self.fs
+            .symlink_metadata(self.path.as_ref())
+            .map_err(at(self.path.as_ref()))
+    }
+}
+
+/// An iterator over the entries of a directory, returned by [`Path::scandir`](crate::Path::scandir).
+pub struct ScanDir<P> {
+    inner: ReadDir,
+    parent: P,
+    policy: NonUtf8Policy,
+    /// The backend the directory is read from.
+    fs: Rc<dyn FileSystem>,
+}
+
+impl<P: Debug> Debug for ScanDir<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScanDir")
+            .field("parent", &self.parent)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> ScanDir<P> {
+    /// Creates an iterator over `inner`, the entries of `parent` read from the current
+    /// backend.
+    pub(crate) fn new(inner: ReadDir, parent: P) -> Self {
+        Self {
+            inner,
+            parent,
+            policy: NonUtf8Policy::default(),
+            fs: fs::current(),
+        }
+    }
+
+    /// Sets what to do with entries whose names are not valid UTF-8.
+    pub fn non_utf8(mut self, policy: NonUtf8Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl<P: PurePath<Error = Infallible> + AsRef<str> + for<'a> From<&'a str>> Iterator for ScanDir<P> {
+    type Item = Result<DirEntry<P>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.inner.next()? {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(at(self.parent.as_ref())(err))),
+            };
+            let file_name = match entry.name.into_string() {
+                Ok(file_name) => file_name,
+                Err(_) if self.policy == NonUtf8Policy::Skip => continue,
+                Err(name) => {
+                    let error = NonUtf8Error::new(StdPath::new(self.parent.as_ref()).join(name));
+                    return Some(Err(Error::new(ErrorKind::InvalidData, error)));
+                }
+            };
+            let path = self.parent.join(&P::from(file_name.as_str()));
+            return Some(Ok(DirEntry {
+                path,
+                file_name,
+                file_type: entry.file_type,
+                fs: self.fs.clone(),
+            }));
+        }
+    }
+}
+
+/// An iterator over the paths in a directory, returned by [`Path::iterdir`](crate::Path::iterdir).
+#[derive(Debug)]
+pub struct IterDir<P>(ScanDir<P>);
+
+impl<P> IterDir<P> {
+    /// Creates an iterator over the paths of `scan`.
+    pub(crate) fn new(scan: ScanDir<P>) -> Self {
+        Self(scan)
+    }
+
+    /// Sets what to do with entries whose names are not valid UTF-8.
+    pub fn non_utf8(self, policy: NonUtf8Policy) -> Self {
+        Self(self.0.non_utf8(policy))
+    }
+}
+
+impl<P: PurePath<Error = Infallible> + AsRef<str> + for<'a> From<&'a str>> Iterator for IterDir<P> {
+    type Item = Result<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| entry.map(DirEntry::into_path))
+    }
+}
@@ -0,0 +1,327 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::{Path as StdPath, PathBuf};
+
+/// An I/O error together with the path it occurred on.
+///
+/// Methods of [Path](crate::Path) that report it still return [`io::Error`]s,
+/// which wrap it and can be downcast back with [`io::Error::get_ref`].
+/// The wrapping error keeps the [kind](io::Error::kind) but not the OS error code,
+/// which is returned by [`raw_os_error`](PathError::raw_os_error).
+#[derive(Debug)]
+pub struct PathError {
+    path: String,
+    error: io::Error,
+}
+
+impl PathError {
+    /// Creates an error for `path`.
+    pub fn new(path: impl Into<String>, error: io::Error) -> Self {
+        Self {
+            path: path.into(),
+            error,
+        }
+    }
+
+    /// Returns the path the error occurred on.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the underlying I/O error.
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns the OS error code of the underlying I/O error, if it has one.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.error.raw_os_error()
+    }
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+impl Error for PathError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<PathError> for io::Error {
+    fn from(error: PathError) -> Self {
+        io::Error::new(error.error.kind(), error)
+    }
+}
+
+/// The error for a path that is not valid UTF-8, and so cannot be represented by this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonUtf8Error {
+    path: PathBuf,
+}
+
+impl NonUtf8Error {
+    /// Creates an error for `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the path that is not valid UTF-8.
+    pub fn path(&self) -> &StdPath {
+        &self.path
+    }
+}
+
+impl Display for NonUtf8Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "path is not valid UTF-8: {}", self.path.display())
+    }
+}
+
+impl Error for NonUtf8Error {}
+
+/// The error for a `~user` whose home directory cannot be found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownUserError {
+    user: Option<String>,
+}
+
+impl UnknownUserError {
+    /// Creates an error for `user`, or for the current user if it is `None`.
+    pub fn new(user: Option<String>) -> Self {
+        Self { user }
+    }
+
+    /// Returns the name of the user, or `None` for the current user.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+}
+
+impl Display for UnknownUserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.user {
+            Some(user) => write!(f, "home directory of user {user} is unknown"),
+            None => f.write_str("home directory of the current user is unknown"),
+        }
+    }
+}
+
+impl Error for UnknownUserError {}
+
+impl From<UnknownUserError> for io::Error {
+    fn from(error: UnknownUserError) -> Self {
+        io::Error::new(io::ErrorKind::NotFound, error)
+    }
+}
+
+/// The error for a user or group that is not in the passwd or group database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownOwnerError {
+    name: String,
+    group: bool,
+}
+
+impl UnknownOwnerError {
+    /// Creates an error for the user `name`, which may be a user ID.
+    pub fn user(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            group: false,
+        }
+    }
+
+    /// Creates an error for the group `name`, which may be a group ID.
+    pub fn group(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            group: true,
+        }
+    }
+
+    /// Returns the name or ID of the user or group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns whether this is a group rather than a user.
+    pub fn is_group(&self) -> bool {
+        self.group
+    }
+}
+
+impl Display for UnknownOwnerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.group {
+            true => write!(f, "group {} is unknown", self.name),
+            false => write!(f, "user {} is unknown", self.name),
+        }
+    }
+}
+
+impl Error for UnknownOwnerError {}
+
+impl From<UnknownOwnerError> for io::Error {
+    fn from(error: UnknownOwnerError) -> Self {
+        io::Error::new(io::ErrorKind::NotFound, error)
+    }
+}
+
+/// The error for extended attributes that the filesystem or platform does not support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedXattrError {
+    name: Option<String>,
+}
+
+impl UnsupportedXattrError {
+    /// Creates an error for the attribute `name`, or for listing the attributes if `None`.
+    pub fn new(name: Option<String>) -> Self {
+        Self { name }
+    }
+
+    /// Returns the name of the attribute, or `None` if the attributes were listed.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl Display for UnsupportedXattrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "extended attribute {name} is not supported"),
+            None => write!(f, "extended attributes are not supported"),
+        }
+    }
+}
+
+impl Error for UnsupportedXattrError {}
+
+impl From<UnsupportedXattrError> for io::Error {
+    fn from(error: UnsupportedXattrError) -> Self {
+        io::Error::new(io::ErrorKind::Unsupported, error)
+    }
+}
+
+/// The error for an undefined environment variable in a strict expansion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndefinedVarError {
+    name: String,
+}
+
+impl UndefinedVarError {
+    /// Creates an error for the variable `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+
+    /// Returns the name of the variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Display for UndefinedVarError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "environment variable {} is undefined", self.name)
+    }
+}
+
+impl Error for UndefinedVarError {}
+
+impl From<UndefinedVarError> for io::Error {
+    fn from(error: UndefinedVarError) -> Self {
+        io::Error::new(io::ErrorKind::NotFound, error)
+    }
+}
+
+/// The error of a [Transaction](crate::Transaction) that failed and could not be rolled back.
+#[derive(Debug)]
+pub struct RollbackError {
+    error: io::Error,
+    rollback: Vec<io::Error>,
+}
+
+impl RollbackError {
+    /// Creates an error for an operation that failed with `error`, and whose rollback
+    /// failed with the errors in `rollback`.
+    pub fn new(error: io::Error, rollback: Vec<io::Error>) -> Self {
+        Self { error, rollback }
+    }
+
+    /// Returns the error of the operation that failed.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns the errors of the operations that could not be undone, in the order they
+    /// were undone.
+    pub fn rollback_errors(&self) -> &[io::Error] {
+        &self.rollback
+    }
+}
+
+impl Display for RollbackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, and rolling back failed: ", self.error)?;
+        for (index, error) in self.rollback.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RollbackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<RollbackError> for io::Error {
+    fn from(error: RollbackError) -> Self {
+        io::Error::new(error.error.kind(), error)
+    }
+}
+
+/// The error for a mode that [`ModeChange::parse`](crate::ModeChange::parse) cannot parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseModeError {
+    mode: String,
+}
+
+impl ParseModeError {
+    /// Creates an error for `mode`.
+    pub fn new(mode: impl Into<String>) -> Self {
+        Self { mode: mode.into() }
+    }
+
+    /// Returns the mode that could not be parsed.
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+}
+
+impl Display for ParseModeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid mode: {:?}", self.mode)
+    }
+}
+
+impl Error for ParseModeError {}
+
+impl From<ParseModeError> for io::Error {
+    fn from(error: ParseModeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
+/// Returns a closure that attaches `path` to an [`io::Error`].
+pub(crate) fn at(path: &str) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |error| PathError::new(path, error).into()
+}
@@ -0,0 +1,160 @@
+use core::fmt::{self, Display, Formatter};
+
+use crate::PathStorage;
+
+/// The error returned by [`PurePath::expanduser_with`](crate::PurePath::expanduser_with).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExpandUserError<E> {
+    /// The home directory of the user is unknown.
+    UnknownUser,
+    /// The expanded path does not fit into the capacity of the path.
+    Capacity(E),
+}
+
+impl<E: Display> Display for ExpandUserError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownUser => f.write_str("home directory of the user is unknown"),
+            Self::Capacity(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error> std::error::Error for ExpandUserError<E> {}
+
+/// The syntaxes recognized by [`PurePath::expand_vars`](crate::PurePath::expand_vars).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct VarSyntax {
+    /// Whether `$VAR` is expanded, where `VAR` consists of ASCII letters, digits and `_`.
+    pub dollar: bool,
+    /// Whether `${VAR}` is expanded.
+    pub braces: bool,
+    /// Whether `%VAR%` is expanded, with `%%` standing for a literal `%`.
+    pub percent: bool,
+}
+
+impl VarSyntax {
+    /// `$VAR` and `${VAR}`, as in Posix shells.
+    pub const POSIX: Self = Self {
+        dollar: true,
+        braces: true,
+        percent: false,
+    };
+    /// `%VAR%`, as in the Windows command prompt.
+    pub const WINDOWS: Self = Self {
+        dollar: false,
+        braces: false,
+        percent: true,
+    };
+    /// All of the syntaxes.
+    pub const ALL: Self = Self {
+        dollar: true,
+        braces: true,
+        percent: true,
+    };
+}
+
+/// Options for [`PurePath::expand_vars_with`](crate::PurePath::expand_vars_with).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct VarOptions {
+    /// The syntaxes to expand, or `None` for those of the flavor.
+    pub syntax: Option<VarSyntax>,
+    /// Whether undefined variables are errors, rather than left as they are.
+    pub strict: bool,
+}
+
+/// The error returned by [`PurePath::expand_vars`](crate::PurePath::expand_vars).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExpandVarsError<'a, E> {
+    /// The variable with this name is undefined, and the expansion is strict.
+    Undefined(&'a str),
+    /// The expanded path does not fit into the capacity of the path.
+    Capacity(E),
+}
+
+impl<E: Display> Display for ExpandVarsError<'_, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined(name) => write!(f, "variable {name} is undefined"),
+            Self::Capacity(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error> std::error::Error for ExpandVarsError<'_, E> {}
+
+/// A reference to a variable at the start of a string.
+enum Reference<'a> {
+    /// A variable with its name, spanning the given number of bytes.
+    Var(&'a str, usize),
+    /// `%%`, which stands for a literal `%`.
+    Percent,
+}
+
+/// Parses the reference at the start of `s`, which starts with `$` or `%`.
+fn parse_reference(s: &str, syntax: VarSyntax) -> Option<Reference<'_>> {
+    if let Some(rest) = s.strip_prefix('%').filter(|_| syntax.percent) {
+        if rest.starts_with('%') {
+            return Some(Reference::Percent);
+        }
+        let end = rest.find('%')?;
+        return Some(Reference::Var(&rest[..end], end + 2));
+    }
+    let rest = s.strip_prefix('$')?;
+    if let Some(rest) = rest.strip_prefix('{').filter(|_| syntax.braces) {
+        let end = rest.find('}').filter(|&end| end > 0)?;
+        return Some(Reference::Var(&rest[..end], end + 3));
+    }
+    if !syntax.dollar {
+        return None;
+    }
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    (end > 0).then(|| Reference::Var(&rest[..end], end + 1))
+}
+
+/// Expands the variables in `path` that `lookup` defines.
+pub(crate) fn expand_vars<'a, S: PathStorage, V: AsRef<str>>(
+    path: &'a str,
+    syntax: VarSyntax,
+    strict: bool,
+    mut lookup: impl FnMut(&str) -> Option<V>,
+) -> Result<S, ExpandVarsError<'a, S::Error>> {
+    let mut expanded = S::default();
+    let mut push = |part: &str| {
+        expanded
+            .try_extend(&[part])
+            .map_err(ExpandVarsError::Capacity)
+    };
+    let mut rest = path;
+    let starts =
+        |c: char| (c == '%' && syntax.percent) || (c == '$' && (syntax.dollar || syntax.braces));
+    while let Some(start) = rest.find(starts) {
+        push(&rest[..start])?;
+        rest = &rest[start..];
+        let len = match parse_reference(rest, syntax) {
+            Some(Reference::Var(name, len)) => {
+                match lookup(name) {
+                    Some(value) => push(value.as_ref())?,
+                    None if strict => return Err(ExpandVarsError::Undefined(name)),
+                    None => push(&rest[..len])?,
+                }
+                len
+            }
+            Some(Reference::Percent) => {
+                push("%")?;
+                2
+            }
+            None => {
+                push(&rest[..1])?;
+                1
+            }
+        };
+        rest = &rest[len..];
+    }
+    push(rest)?;
+    Ok(expanded)
+}
@@ -0,0 +1,466 @@
+use std::cell::RefCell;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::glob::Pattern;
+use crate::{DiskUsage, FileSystem, Metadata, OpenFile, OpenOptions, ReadDir};
+
+/// An operation that a [FaultRule] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// [`FileSystem::current_dir`].
+    CurrentDir,
+    /// [`FileSystem::metadata`].
+    Metadata,
+    /// [`FileSystem::symlink_metadata`].
+    SymlinkMetadata,
+    /// [`FileSystem::canonicalize`].
+    Canonicalize,
+    /// [`FileSystem::read_dir`].
+    ReadDir,
+    /// [`FileSystem::read_link`].
+    ReadLink,
+    /// [`FileSystem::open`].
+    Open,
+    /// [`FileSystem::create_dir`].
+    CreateDir,
+    /// [`FileSystem::remove_file`].
+    RemoveFile,
+    /// [`FileSystem::remove_dir`].
+    RemoveDir,
+    /// [`FileSystem::rename`].
+    Rename,
+    /// [`FileSystem::symlink`].
+    Symlink,
+    /// [`FileSystem::hard_link`].
+    HardLink,
+    /// [`FileSystem::set_permissions`].
+    SetPermissions,
+    /// [`FileSystem::set_owner`].
+    SetOwner,
+    /// [`FileSystem::set_times`].
+    SetTimes,
+    /// [`FileSystem::umask`].
+    Umask,
+    /// [`FileSystem::disk_usage`].
+    DiskUsage,
+    /// [`FileSystem::copy_file`].
+    CopyFile,
+    /// [`FileSystem::list_xattrs`].
+    ListXattrs,
+    /// [`FileSystem::get_xattr`].
+    GetXattr,
+    /// [`FileSystem::set_xattr`].
+    SetXattr,
+    /// [`FileSystem::remove_xattr`].
+    RemoveXattr,
+    /// A read from an opened file.
+    Read,
+    /// A write to an opened file.
+    Write,
+}
+
+/// A fault that a [FaultyFileSystem] injects into an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// The operation fails with an error of this kind, such as [`ErrorKind::StorageFull`]
+    /// for `ENOSPC`, [`ErrorKind::PermissionDenied`] for `EACCES`, or
+    /// [`ErrorKind::NotFound`] for a file removed concurrently.
+    Error(ErrorKind),
+    /// The operation fails with this raw OS error code, such as `EIO`.
+    Os(i32),
+    /// Writes to an opened file write at most this many bytes, so `0` makes them fail
+    /// with [`ErrorKind::WriteZero`] from [`Write::write_all`]. Other operations are
+    /// unaffected.
+    PartialWrite(usize),
+    /// The operation runs after this delay.
+    Delay(Duration),
+}
+
+impl Fault {
+    /// Returns whether the fault has an effect on `operation`.
+    fn affects(self, operation: Operation) -> bool {
+        !matches!(self, Self::PartialWrite(_)) || operation == Operation::Write
+    }
+
+    /// Returns whether the fault makes the operation fail.
+    fn fails(self) -> bool {
+        matches!(self, Self::Error(_) | Self::Os(_))
+    }
+}
+
+/// When and where a [FaultyFileSystem] injects a [Fault].
+///
+/// By default, the fault is injected into every operation it has an effect on. Only those
+/// operations count towards [`skip`](Self::skip) and [`times`](Self::times), and not those
+/// that an earlier rule already makes fail.
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    fault: Fault,
+    operations: Vec<Operation>,
+    pattern: Option<Pattern>,
+    skip: u64,
+    times: Option<u64>,
+    probability: Option<f64>,
+}
+
+impl FaultRule {
+    /// Creates a rule that injects `fault`.
+    pub fn new(fault: Fault) -> Self {
+        Self {
+            fault,
+            operations: Vec::new(),
+            pattern: None,
+            skip: 0,
+            times: None,
+            probability: None,
+        }
+    }
+
+    /// Restricts the rule to `operation`, in addition to the operations it is already
+    /// restricted to.
+    pub fn operation(mut self, operation: Operation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Restricts the rule to paths matching `pattern` as they are passed to the backend.
+    ///
+    /// `?`, `*` and `[..]` are wildcards as in [Glob](crate::Glob), except that `*` matches
+    /// separators too. Operations on two paths match if either of them does.
+    pub fn path(mut self, pattern: &str) -> Self {
+        self.pattern = Some(Pattern::new(pattern));
+        self
+    }
+
+    /// Lets the first `skip` matching operations run unaffected.
+    pub fn skip(mut self, skip: u64) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    /// Injects the fault at most `times` times.
+    pub fn times(mut self, times: u64) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Injects the fault into each matching operation with probability `probability`,
+    /// drawn from the seed of the backend.
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = Some(probability);
+        self
+    }
+
+    /// Returns whether the rule applies to `operation` on `paths`.
+    fn applies(&self, operation: Operation, paths: &[&str]) -> bool {
+        self.fault.affects(operation)
+            && (self.operations.is_empty() || self.operations.contains(&operation))
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| paths.iter().any(|path| pattern.matches(path)))
+    }
+}
+
+/// The rules of a [FaultyFileSystem], shared with its open files.
+#[derive(Debug)]
+struct Injector {
+    /// The rules, with the number of operations they applied to and of faults injected.
+    rules: Vec<(FaultRule, u64, u64)>,
+    /// The state of the random number generator.
+    state: u64,
+}
+
+impl Injector {
+    /// Returns a random number in `[0, 1)`, using SplitMix64.
+    fn random(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns the faults to inject into `operation` on `paths`, up to the first one that
+    /// makes it fail.
+    fn faults(&mut self, operation: Operation, paths: &[&str]) -> Vec<Fault> {
+        let mut faults = Vec::new();
+        for index in 0..self.rules.len() {
+            let (rule, matched, injected) = &mut self.rules[index];
+            if !rule.applies(operation, paths) {
+                continue;
+            }
+            *matched += 1;
+            if *matched <= rule.skip || rule.times.is_some_and(|times| *injected >= times) {
+                continue;
+            }
+            let probability = rule.probability;
+            if probability.is_some_and(|probability| self.random() >= probability) {
+                continue;
+            }
+            let (rule, _, injected) = &mut self.rules[index];
+            *injected += 1;
+            faults.push(rule.fault);
+            if rule.fault.fails() {
+                break;
+            }
+        }
+        faults
+    }
+
+    /// Injects the faults into `operation` on `paths`, and returns the maximum number of
+    /// bytes to write.
+    fn inject(
+        injector: &RefCell<Self>,
+        operation: Operation,
+        paths: &[&str],
+    ) -> Result<Option<usize>> {
+        let faults = injector.borrow_mut().faults(operation, paths);
+        let mut limit = None::<usize>;
+        for fault in faults {
+            match fault {
+                Fault::Error(kind) => return Err(Error::new(kind, "Injected fault")),
+                Fault::Os(code) => return Err(Error::from_raw_os_error(code)),
+                Fault::PartialWrite(max) => limit = Some(limit.map_or(max, |limit| limit.min(max))),
+                Fault::Delay(delay) => thread::sleep(delay),
+            }
+        }
+        Ok(limit)
+    }
+}
+
+/// A backend that injects faults into the operations of another one, for testing error
+/// handling.
+///
+/// Faults are described by [FaultRule]s, which are checked in the order they were added.
+/// The first error among the faults to inject is returned, and the operation is not run.
+/// Random faults are drawn from a generator seeded with [`seed`](Self::seed), so a test
+/// fails the same way every time.
+#[derive(Debug)]
+pub struct FaultyFileSystem<F> {
+    inner: F,
+    injector: Rc<RefCell<Injector>>,
+}
+
+impl<F: FileSystem> FaultyFileSystem<F> {
+    /// Wraps `inner`, without any rules and with a seed of `0`.
+    pub fn new(inner: F) -> Self {
+        let injector = Injector {
+            rules: Vec::new(),
+            state: 0,
+        };
+        Self {
+            inner,
+            injector: Rc::new(RefCell::new(injector)),
+        }
+    }
+
+    /// Sets the seed of random faults.
+    pub fn seed(self, seed: u64) -> Self {
+        self.injector.borrow_mut().state = seed;
+        self
+    }
+
+    /// Adds `rule` after the existing rules.
+    pub fn inject(&self, rule: FaultRule) {
+        self.injector.borrow_mut().rules.push((rule, 0, 0));
+    }
+
+    /// Removes all of the rules.
+    pub fn clear(&self) {
+        self.injector.borrow_mut().rules.clear();
+    }
+
+    /// Returns the wrapped filesystem.
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// Injects the faults into `operation` on `paths`.
+    fn check(&self, operation: Operation, paths: &[&str]) -> Result<()> {
+        Injector::inject(&self.injector, operation, paths).map(|_| ())
+    }
+}
+
+impl<F: FileSystem> FileSystem for FaultyFileSystem<F> {
+    fn current_dir(&self) -> Result<String> {
+        self.check(Operation::CurrentDir, &[])?;
+        self.inner.current_dir()
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        self.check(Operation::Metadata, &[path])?;
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        self.check(Operation::SymlinkMetadata, &[path])?;
+        self.inner.symlink_metadata(path)
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String> {
+        self.check(Operation::Canonicalize, &[path])?;
+        self.inner.canonicalize(path)
+    }
+
+    fn read_dir(&self, path: &str) -> Result<ReadDir> {
+        self.check(Operation::ReadDir, &[path])?;
+        self.inner.read_dir(path)
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        self.check(Operation::ReadLink, &[path])?;
+        self.inner.read_link(path)
+    }
+
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        self.check(Operation::Open, &[path])?;
+        Ok(Box::new(FaultyFile {
+            file: self.inner.open(path, options)?,
+            path: path.to_owned(),
+            injector: self.injector.clone(),
+        }))
+    }
+
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+        self.check(Operation::CreateDir, &[path])?;
+        self.inner.create_dir(path, mode)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        self.check(Operation::RemoveFile, &[path])?;
+        self.inner.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        self.check(Operation::RemoveDir, &[path])?;
+        self.inner.remove_dir(path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.check(Operation::Rename, &[from, to])?;
+        self.inner.rename(from, to)
+    }
+
+    fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        self.check(Operation::Symlink, &[link])?;
+        self.inner.symlink(target, link)
+    }
+
+    fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+        self.check(Operation::HardLink, &[original, link])?;
+        self.inner.hard_link(original, link)
+    }
+
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+        self.check(Operation::SetPermissions, &[path])?;
+        self.inner.set_permissions(path, mode)
+    }
+
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        self.check(Operation::SetOwner, &[path])?;
+        self.inner.set_owner(path, uid, gid, follow)
+    }
+
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        self.check(Operation::SetTimes, &[path])?;
+        self.inner.set_times(path, atime, mtime, follow)
+    }
+
+    fn umask(&self) -> Result<u32> {
+        self.check(Operation::Umask, &[])?;
+        self.inner.umask()
+    }
+
+    fn disk_usage(&self, path: &str) -> Result<DiskUsage> {
+        self.check(Operation::DiskUsage, &[path])?;
+        self.inner.disk_usage(path)
+    }
+
+    fn copy_file(&self, from: &str, to: &str, progress: &mut dyn FnMut(u64)) -> Result<u64> {
+        self.check(Operation::CopyFile, &[from, to])?;
+        self.inner.copy_file(from, to, progress)
+    }
+
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        self.check(Operation::ListXattrs, &[path])?;
+        self.inner.list_xattrs(path, follow)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        self.check(Operation::GetXattr, &[path])?;
+        self.inner.get_xattr(path, name, follow)
+    }
+
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        self.check(Operation::SetXattr, &[path])?;
+        self.inner.set_xattr(path, name, value, follow)
+    }
+
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        self.check(Operation::RemoveXattr, &[path])?;
+        self.inner.remove_xattr(path, name, follow)
+    }
+}
+
+/// A file opened by a [FaultyFileSystem].
+struct FaultyFile {
+    file: Box<dyn OpenFile>,
+    path: String,
+    injector: Rc<RefCell<Injector>>,
+}
+
+impl Read for FaultyFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let _ = Injector::inject(&self.injector, Operation::Read, &[&self.path])?;
+        self.file.read(buf)
+    }
+}
+
+impl Write for FaultyFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let limit = Injector::inject(&self.injector, Operation::Write, &[&self.path])?;
+        let len = limit.map_or(buf.len(), |limit| limit.min(buf.len()));
+        self.file.write(&buf[..len])
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for FaultyFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl OpenFile for FaultyFile {
+    fn metadata(&self) -> Result<Metadata> {
+        self.file.metadata()
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<()> {
+        self.file.set_len(size)
+    }
+
+    fn set_modified(&mut self, time: SystemTime) -> Result<()> {
+        self.file.set_modified(time)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::VarSyntax;
+
+/// The syntax of paths on a family of systems.
+pub trait Flavor {
+    /// The primary component separator.
+    ///
+    /// For example, `'/'` on Posix systems and `'\\'` on Windows.
+    const PRIMARY_COMPONENT_SEPARATOR: char;
+    /// The secondary component separator.
+    ///
+    /// For example, `None` on Posix systems and `Some('/')` on Windows.
+    const SECONDARY_COMPONENT_SEPARATOR: Option<char>;
+    /// The component separators.
+    ///
+    /// For example, `&['/', '\\']` on Windows.
+    const COMPONENT_SEPARATORS: &'static [char] = match Self::SECONDARY_COMPONENT_SEPARATOR {
+        Some(c) => &[Self::PRIMARY_COMPONENT_SEPARATOR, c],
+        None => &[Self::PRIMARY_COMPONENT_SEPARATOR],
+    };
+    /// The extension separator.
+    const EXTENSION_SEPARATOR: char;
+    /// The drive separator.
+    const DRIVE_SEPARATOR: Option<char>;
+    /// The current directory.
+    const CURRENT_DIR: &'static str;
+    /// The parent directory.
+    const PARENT_DIR: &'static str;
+    /// The syntaxes of environment variables that [`PurePath::expand_vars`] expands.
+    ///
+    /// [`PurePath::expand_vars`]: crate::PurePath::expand_vars
+    const VAR_SYNTAX: VarSyntax;
+}
+
+/// The path syntax of Posix systems.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Posix;
+
+impl Flavor for Posix {
+    const PRIMARY_COMPONENT_SEPARATOR: char = '/';
+    const SECONDARY_COMPONENT_SEPARATOR: Option<char> = None;
+    const EXTENSION_SEPARATOR: char = '.';
+    const DRIVE_SEPARATOR: Option<char> = None;
+    const CURRENT_DIR: &'static str = ".";
+    const PARENT_DIR: &'static str = "..";
+    const VAR_SYNTAX: VarSyntax = VarSyntax::POSIX;
+}
+
+/// The path syntax of Windows systems.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Windows;
+
+impl Flavor for Windows {
+    const PRIMARY_COMPONENT_SEPARATOR: char = '\\';
+    const SECONDARY_COMPONENT_SEPARATOR: Option<char> = Some('/');
+    const EXTENSION_SEPARATOR: char = '.';
+    const DRIVE_SEPARATOR: Option<char> = Some(':');
+    const CURRENT_DIR: &'static str = ".";
+    const PARENT_DIR: &'static str = "..";
+    const VAR_SYNTAX: VarSyntax = VarSyntax::WINDOWS;
+}
+
+#[cfg(feature = "std")]
+impl Windows {
+    /// Folds the case of `c` as NTFS does, so that characters that Windows file names treat
+    /// as equal fold to the same character.
+    ///
+    /// This is the simple uppercase mapping: a character whose uppercase form is a single
+    /// character maps to it, and other characters, such as `'ß'`, are left as they are.
+    pub(crate) fn fold_case(c: char) -> char {
+        let mut upper = c.to_uppercase();
+        match (upper.next(), upper.next()) {
+            (Some(upper), None) => upper,
+            _ => c,
+        }
+    }
+}
+
+/// The platform-independent path syntax, with `/` as the only separator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Unified;
+
+impl Flavor for Unified {
+    const PRIMARY_COMPONENT_SEPARATOR: char = '/';
+    const SECONDARY_COMPONENT_SEPARATOR: Option<char> = None;
+    const EXTENSION_SEPARATOR: char = '.';
+    const DRIVE_SEPARATOR: Option<char> = Some(':');
+    const CURRENT_DIR: &'static str = ".";
+    const PARENT_DIR: &'static str = "..";
+    const VAR_SYNTAX: VarSyntax = VarSyntax::ALL;
+}
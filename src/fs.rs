@@ -0,0 +1,658 @@
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::fs::{self, DirBuilder, File};
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use crate::{sys, DiskUsage, FileType, Metadata, UnsupportedXattrError};
+
+/// Options for opening files with [`Path::open`](crate::Path::open).
+///
+/// Unlike [`std::fs::OpenOptions`], the options can be read back by any [FileSystem].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpenOptions {
+    /// Whether the file is opened for reading.
+    pub read: bool,
+    /// Whether the file is opened for writing.
+    pub write: bool,
+    /// Whether writes append to the file, which implies `write`.
+    pub append: bool,
+    /// Whether the file is truncated to zero bytes when opened.
+    pub truncate: bool,
+    /// Whether the file is created if it does not exist.
+    pub create: bool,
+    /// Whether the file is created, and it is an error if it exists.
+    pub create_new: bool,
+    /// The permission bits of a created file, combined with the process umask on Unix.
+    pub mode: u32,
+}
+
+impl OpenOptions {
+    /// Creates options with everything disabled, and a mode of `0o666`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the file is opened for reading.
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Sets whether the file is opened for writing.
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Sets whether writes append to the file.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Sets whether the file is truncated when opened.
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Sets whether the file is created if it does not exist.
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Sets whether the file is created, failing if it exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Sets the permission bits of a created file.
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns whether the options allow writing to the file.
+    pub fn writes(&self) -> bool {
+        self.write || self.append
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            mode: 0o666,
+        }
+    }
+}
+
+/// A file opened by a [FileSystem].
+pub trait OpenFile: Read + Write + Seek {
+    /// Returns the metadata of the file.
+    fn metadata(&self) -> Result<Metadata>;
+
+    /// Truncates or extends the file to `size` bytes.
+    fn set_len(&mut self, size: u64) -> Result<()>;
+
+    /// Sets the modification time of the file.
+    fn set_modified(&mut self, time: SystemTime) -> Result<()>;
+}
+
+impl OpenFile for File {
+    fn metadata(&self) -> Result<Metadata> {
+        File::metadata(self).map(Metadata::from)
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<()> {
+        File::set_len(self, size)
+    }
+
+    fn set_modified(&mut self, time: SystemTime) -> Result<()> {
+        File::set_modified(self, time)
+    }
+}
+
+/// An entry of a directory, as listed by a [FileSystem].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawDirEntry {
+    /// The file name of the entry, which may not be valid UTF-8.
+    pub name: OsString,
+    /// The file type of the entry, without following symlinks.
+    pub file_type: FileType,
+}
+
+/// An iterator over the entries of a directory, returned by [`FileSystem::read_dir`].
+pub type ReadDir = Box<dyn Iterator<Item = Result<RawDirEntry>>>;
+
+/// A backend that [Path](crate::Path) operations run against.
+///
+/// Paths are passed as they are written, relative paths being relative to
+/// [`current_dir`](FileSystem::current_dir). Errors do not need to mention the path, as the
+/// callers attach it.
+pub trait FileSystem {
+    /// Returns the current directory.
+    fn current_dir(&self) -> Result<String>;
+
+    /// Returns the metadata of the file at `path`, following symlinks.
+    fn metadata(&self, path: &str) -> Result<Metadata>;
+
+    /// Returns the metadata of the file at `path`, without following symlinks.
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata>;
+
+    /// Returns the absolute path of `path` with all symlinks resolved.
+    fn canonicalize(&self, path: &str) -> Result<String>;
+
+    /// Returns the entries of the directory at `path`, without `.` and `..`.
+    fn read_dir(&self, path: &str) -> Result<ReadDir>;
+
+    /// Returns the target of the symlink at `path`.
+    fn read_link(&self, path: &str) -> Result<String>;
+
+    /// Opens the file at `path`.
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>>;
+
+    /// Creates a directory at `path`, whose parent must exist.
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()>;
+
+    /// Removes the file or symlink at `path`.
+    fn remove_file(&self, path: &str) -> Result<()>;
+
+    /// Removes the empty directory at `path`.
+    fn remove_dir(&self, path: &str) -> Result<()>;
+
+    /// Renames `from` to `to`, replacing any file at `to`.
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Creates a symlink at `link` pointing to `target`.
+    fn symlink(&self, target: &str, link: &str) -> Result<()>;
+
+    /// Creates a hard link at `link` to the file at `original`.
+    fn hard_link(&self, original: &str, link: &str) -> Result<()>;
+
+    /// Sets the permission bits of the file at `path`, following symlinks.
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()>;
+
+    /// Sets the owner and group of the file at `path`, leaving those that are `None`.
+    ///
+    /// Symlinks are followed if `follow` is true. This fails with
+    /// [`ErrorKind::Unsupported`] by default.
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        let _ = (path, uid, gid, follow);
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "Ownership is not supported by this filesystem",
+        ))
+    }
+
+    /// Sets the access and modification times of the file at `path`, leaving those that
+    /// are `None`.
+    ///
+    /// Symlinks are followed if `follow` is true. This fails with
+    /// [`ErrorKind::Unsupported`] by default.
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        let _ = (path, atime, mtime, follow);
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "Setting times is not supported by this filesystem",
+        ))
+    }
+
+    /// Returns the umask that the permission bits of created files are masked with.
+    ///
+    /// This fails with [`ErrorKind::Unsupported`] by default.
+    fn umask(&self) -> Result<u32> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "The umask is not supported by this filesystem",
+        ))
+    }
+
+    /// Returns the space of the filesystem that holds the file at `path`.
+    ///
+    /// This fails with [`ErrorKind::Unsupported`] by default.
+    fn disk_usage(&self, path: &str) -> Result<DiskUsage> {
+        let _ = path;
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "Disk usage is not supported by this filesystem",
+        ))
+    }
+
+    /// Copies the contents of the file at `from` to `to`, which is created with mode `0o666`
+    /// or truncated, and returns the number of bytes copied.
+    ///
+    /// `progress` is called with the number of bytes copied so far as the copy proceeds. By
+    /// default, the file is read and written in chunks.
+    fn copy_file(&self, from: &str, to: &str, progress: &mut dyn FnMut(u64)) -> Result<u64> {
+        let mut source = self.open(from, OpenOptions::new().read(true))?;
+        let options = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .to_owned();
+        let mut target = self.open(to, &options)?;
+        copy_chunks(&mut source, &mut target, progress)
+    }
+
+    /// Returns the names of the extended attributes of the file at `path`.
+    ///
+    /// Symlinks are followed if `follow` is true. This fails with an
+    /// [`UnsupportedXattrError`] by default.
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        let _ = (path, follow);
+        Err(UnsupportedXattrError::new(None).into())
+    }
+
+    /// Returns the value of the extended attribute `name` of the file at `path`, or
+    /// `None` if it is not set.
+    ///
+    /// Symlinks are followed if `follow` is true. This fails with an
+    /// [`UnsupportedXattrError`] by default.
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        let _ = (path, follow);
+        Err(UnsupportedXattrError::new(Some(name.to_owned())).into())
+    }
+
+    /// Sets the extended attribute `name` of the file at `path` to `value`.
+    ///
+    /// Symlinks are followed if `follow` is true. This fails with an
+    /// [`UnsupportedXattrError`] by default.
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        let _ = (path, value, follow);
+        Err(UnsupportedXattrError::new(Some(name.to_owned())).into())
+    }
+
+    /// Removes the extended attribute `name` of the file at `path`, failing with
+    /// [`ErrorKind::NotFound`] if it is not set.
+    ///
+    /// Symlinks are followed if `follow` is true. This fails with an
+    /// [`UnsupportedXattrError`] by default.
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        let _ = (path, follow);
+        Err(UnsupportedXattrError::new(Some(name.to_owned())).into())
+    }
+}
+
+impl<T: FileSystem + ?Sized> FileSystem for Rc<T> {
+    fn current_dir(&self) -> Result<String> {
+        (**self).current_dir()
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        (**self).metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        (**self).symlink_metadata(path)
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String> {
+        (**self).canonicalize(path)
+    }
+
+    fn read_dir(&self, path: &str) -> Result<ReadDir> {
+        (**self).read_dir(path)
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        (**self).read_link(path)
+    }
+
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        (**self).open(path, options)
+    }
+
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+        (**self).create_dir(path, mode)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        (**self).remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        (**self).remove_dir(path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        (**self).rename(from, to)
+    }
+
+    fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        (**self).symlink(target, link)
+    }
+
+    fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+        (**self).hard_link(original, link)
+    }
+
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+        (**self).set_permissions(path, mode)
+    }
+
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        (**self).set_owner(path, uid, gid, follow)
+    }
+
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        (**self).set_times(path, atime, mtime, follow)
+    }
+
+    fn umask(&self) -> Result<u32> {
+        (**self).umask()
+    }
+
+    fn disk_usage(&self, path: &str) -> Result<DiskUsage> {
+        (**self).disk_usage(path)
+    }
+
+    fn copy_file(&self, from: &str, to: &str, progress: &mut dyn FnMut(u64)) -> Result<u64> {
+        (**self).copy_file(from, to, progress)
+    }
+
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        (**self).list_xattrs(path, follow)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        (**self).get_xattr(path, name, follow)
+    }
+
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        (**self).set_xattr(path, name, value, follow)
+    }
+
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        (**self).remove_xattr(path, name, follow)
+    }
+}
+
+/// The size of the chunks that files are copied in.
+const CHUNK_SIZE: usize = 128 * 1024;
+
+/// Copies `reader` to `writer` in chunks, calling `progress` with the number of bytes
+/// copied so far after each one, and returns that number.
+pub(crate) fn copy_chunks(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    progress: &mut dyn FnMut(u64),
+) -> Result<u64> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut copied = 0;
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(len) => len,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buf[..len])?;
+        copied += len as u64;
+        progress(copied);
+    }
+}
+
+/// Converts a path returned by [std] into a string.
+fn into_string(path: PathBuf) -> Result<String> {
+    path.into_os_string()
+        .into_string()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Failed to convert path to string"))
+}
+
+/// The backend of the host filesystem, through [std::fs].
+///
+/// This is the backend that [Path](crate::Path) operations run against by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn current_dir(&self) -> Result<String> {
+        into_string(std::env::current_dir()?)
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        fs::metadata(path).map(Metadata::from)
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        let metadata = Metadata::from(fs::symlink_metadata(path)?);
+        #[cfg(windows)]
+        let metadata = match metadata.is_symlink() && sys::is_junction(path)? {
+            true => Metadata {
+                file_type: FileType::Junction,
+                ..metadata
+            },
+            false => metadata,
+        };
+        Ok(metadata)
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String> {
+        into_string(fs::canonicalize(path)?)
+    }
+
+    fn read_dir(&self, path: &str) -> Result<ReadDir> {
+        let entries = fs::read_dir(path)?.map(|entry| {
+            let entry = entry?;
+            let file_type = FileType::from(entry.file_type()?);
+            #[cfg(windows)]
+            let file_type =
+                match file_type.is_symlink() && sys::is_junction(&into_string(entry.path())?)? {
+                    true => FileType::Junction,
+                    false => file_type,
+                };
+            Ok(RawDirEntry {
+                name: entry.file_name(),
+                file_type,
+            })
+        });
+        Ok(Box::new(entries))
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        into_string(fs::read_link(path)?)
+    }
+
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        let mut std_options = fs::OpenOptions::new();
+        let _ = std_options
+            .read(options.read)
+            .write(options.write)
+            .append(options.append)
+            .truncate(options.truncate)
+            .create(options.create)
+            .create_new(options.create_new);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            let _ = std_options.mode(options.mode);
+        }
+        Ok(Box::new(std_options.open(path)?))
+    }
+
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+        let mut builder = DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            let _ = builder.mode(mode);
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        builder.create(path)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                std::os::unix::fs::symlink(target, link)
+            } else if #[cfg(windows)] {
+                let link = std::path::Path::new(link);
+                let resolved = link
+                    .parent()
+                    .map_or(PathBuf::from(target), |parent| parent.join(target));
+                match resolved.is_dir() {
+                    true => std::os::windows::fs::symlink_dir(target, link),
+                    false => std::os::windows::fs::symlink_file(target, link),
+                }
+            } else {
+                let _ = (target, link);
+                Err(Error::new(ErrorKind::Unsupported, "Symlinks are not supported"))
+            }
+        }
+    }
+
+    fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+        fs::hard_link(original, link)
+    }
+
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+        let mut permissions = fs::metadata(path)?.permissions();
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                use std::os::unix::fs::PermissionsExt;
+                permissions.set_mode(mode);
+            } else {
+                permissions.set_readonly(mode & 0o222 == 0);
+            }
+        }
+        fs::set_permissions(path, permissions)
+    }
+
+    #[cfg(unix)]
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        match follow {
+            true => std::os::unix::fs::chown(path, uid, gid),
+            false => std::os::unix::fs::lchown(path, uid, gid),
+        }
+    }
+
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        sys::set_times(path, atime, mtime, follow)
+    }
+
+    fn umask(&self) -> Result<u32> {
+        sys::umask()
+    }
+
+    fn disk_usage(&self, path: &str) -> Result<DiskUsage> {
+        sys::disk_usage(path)
+    }
+
+    fn copy_file(&self, from: &str, to: &str, progress: &mut dyn FnMut(u64)) -> Result<u64> {
+        sys::copy_file(from, to, progress)
+    }
+
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        sys::list_xattrs(path, follow)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        sys::get_xattr(path, name, follow)
+    }
+
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        sys::set_xattr(path, name, value, follow)
+    }
+
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        sys::remove_xattr(path, name, follow)
+    }
+}
+
+thread_local! {
+    /// The backend that [Path](crate::Path) operations on this thread run against.
+    static CURRENT: RefCell<Rc<dyn FileSystem>> = RefCell::new(Rc::new(StdFileSystem));
+}
+
+/// Returns the backend that [Path](crate::Path) operations on this thread run against.
+pub(crate) fn current() -> Rc<dyn FileSystem> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Restores the previous backend of this thread when dropped.
+struct Restore(Option<Rc<dyn FileSystem>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            let _ = CURRENT.try_with(|current| current.replace(previous));
+        }
+    }
+}
+
+/// Runs `f` with the [Path](crate::Path) operations on this thread running against `fs`.
+///
+/// The previous backend is restored afterwards, even if `f` panics. Iterators such as
+/// [Walk](crate::Walk) keep using the backend that was current when they were created,
+/// even if they are advanced after `f` returns.
+/// Pass an [`Rc`] to keep access to the backend after `f` returns.
+pub fn with_file_system<R>(fs: impl FileSystem + 'static, f: impl FnOnce() -> R) -> R {
+    enter(Rc::new(fs), f)
+}
+
+/// Runs `f` with `fs` as the backend of this thread, as [with_file_system] does.
+pub(crate) fn enter<R>(fs: Rc<dyn FileSystem>, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT.with(|current| current.replace(fs));
+    let _restore = Restore(Some(previous));
+    f()
+}
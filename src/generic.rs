@@ -0,0 +1,281 @@
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::Div;
+
+#[cfg(all(feature = "alloc", not(feature = "std"), target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{borrow::Cow, boxed::Box, rc::Rc};
+#[cfg(feature = "std")]
+use std::{borrow::Cow, boxed::Box, rc::Rc, sync::Arc};
+
+use crate::expand::expand_vars;
+use crate::pure::ParsablePath;
+use crate::{
+    ArrayString, CapacityError, Component, Components, ExpandUserError, ExpandVarsError, Flavor,
+    PathStorage, PurePath, VarOptions,
+};
+#[cfg(feature = "alloc")]
+use crate::{SmallString, String};
+
+/// A path with the syntax of flavor `F`, stored in `S`.
+///
+/// All path types of this crate are aliases of this type, so they share one [PurePath]
+/// implementation regardless of how they are stored.
+pub struct GenericPath<F, S> {
+    path: S,
+    flavor: PhantomData<F>,
+}
+
+/// A path stored inline in a buffer of `N` bytes.
+///
+/// It never allocates, so it can be used on targets without a heap.
+/// Operations that would grow the path beyond `N` bytes fail with [CapacityError]
+/// and leave the path untouched.
+pub type ArrayPath<F, const N: usize> = GenericPath<F, ArrayString<N>>;
+
+/// An immutable path in a compact boxed form.
+#[cfg(feature = "alloc")]
+pub type BoxPath<F> = GenericPath<F, Box<str>>;
+
+/// A reference-counted path that is cheap to clone.
+#[cfg(feature = "alloc")]
+pub type RcPath<F> = GenericPath<F, Rc<str>>;
+
+/// An atomically reference-counted path that is cheap to clone and share between threads.
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub type ArcPath<F> = GenericPath<F, Arc<str>>;
+
+/// A path that borrows the string it was parsed from until it is modified.
+#[cfg(feature = "alloc")]
+pub type CowPath<'a, F> = GenericPath<F, Cow<'a, str>>;
+
+/// A path stored inline while it fits into `N` bytes, and on the heap otherwise.
+#[cfg(feature = "alloc")]
+pub type SmallPath<F, const N: usize = 23> = GenericPath<F, SmallString<N>>;
+
+impl<F, S> GenericPath<F, S> {
+    /// Creates a path from its storage.
+    pub const fn from_storage(path: S) -> Self {
+        Self {
+            path,
+            flavor: PhantomData,
+        }
+    }
+
+    /// Returns the storage of the path.
+    pub const fn storage(&self) -> &S {
+        &self.path
+    }
+
+    /// Consumes the path and returns its storage.
+    pub fn into_storage(self) -> S {
+        self.path
+    }
+}
+
+impl<F, S: PathStorage> GenericPath<F, S> {
+    /// Creates an empty path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the path as a string slice.
+    pub fn as_str(&self) -> &str {
+        self.path.as_str()
+    }
+
+    /// Returns the length of the path in bytes.
+    pub fn len(&self) -> usize {
+        self.as_str().len()
+    }
+
+    /// Returns whether the path is empty.
+    pub fn is_empty(&self) -> bool {
+        self.as_str().is_empty()
+    }
+
+    /// Copies the path into another storage.
+    pub fn to_storage<T: PathStorage>(&self) -> Result<GenericPath<F, T>, T::Error> {
+        T::try_from_str(self.as_str()).map(GenericPath::from_storage)
+    }
+}
+
+impl<F: Flavor, S: PathStorage> GenericPath<F, S> {
+    /// Extends the path with the given path.
+    ///
+    /// If `path` is absolute, it replaces the current path.
+    pub fn try_push(&mut self, path: &str) -> Result<(), S::Error> {
+        F::join_in_place(&mut self.path, path)
+    }
+}
+
+impl<F, const N: usize> ArrayPath<F, N> {
+    /// Returns the capacity of the path in bytes.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<F, S: Default> Default for GenericPath<F, S> {
+    fn default() -> Self {
+        Self::from_storage(S::default())
+    }
+}
+
+impl<F, S: Clone> Clone for GenericPath<F, S> {
+    fn clone(&self) -> Self {
+        Self::from_storage(self.path.clone())
+    }
+}
+
+impl<F, S: Copy> Copy for GenericPath<F, S> {}
+
+impl<F, S: PathStorage> Debug for GenericPath<F, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<F, S: PathStorage, T: PathStorage> PartialEq<GenericPath<F, T>> for GenericPath<F, S> {
+    fn eq(&self, other: &GenericPath<F, T>) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<F, S: PathStorage> Eq for GenericPath<F, S> {}
+
+impl<F, S: PathStorage> PartialOrd for GenericPath<F, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F, S: PathStorage> Ord for GenericPath<F, S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl<F, S: PathStorage> Hash for GenericPath<F, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl<'a, F, S: From<&'a str>> From<&'a str> for GenericPath<F, S> {
+    fn from(path: &'a str) -> Self {
+        Self::from_storage(S::from(path))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F, S: From<String>> From<String> for GenericPath<F, S> {
+    fn from(path: String) -> Self {
+        Self::from_storage(S::from(path))
+    }
+}
+
+impl<'a, F, const N: usize> TryFrom<&'a str> for ArrayPath<F, N> {
+    type Error = CapacityError;
+
+    fn try_from(path: &'a str) -> Result<Self, Self::Error> {
+        ArrayString::try_from_str(path).map(Self::from_storage)
+    }
+}
+
+impl<F, S: PathStorage> AsRef<str> for GenericPath<F, S> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<F: Flavor, S: PathStorage<Error = Infallible>> Div for GenericPath<F, S> {
+    type Output = Self;
+
+    fn div(mut self, rhs: Self) -> Self::Output {
+        <Self as PurePath>::join_in_place(&mut self, &rhs);
+        self
+    }
+}
+
+impl<F: Flavor, S: PathStorage<Error = Infallible>> Div for &GenericPath<F, S> {
+    type Output = GenericPath<F, S>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        <GenericPath<F, S> as PurePath>::join(self, rhs)
+    }
+}
+
+impl<F: Flavor, S: PathStorage> PurePath for GenericPath<F, S> {
+    type Error = S::Error;
+
+    fn parent(&self) -> Option<Self> {
+        // The parent is a prefix of the path, so it always fits.
+        let parent = F::parent(self.as_str())?;
+        S::try_from_str(parent).ok().map(Self::from_storage)
+    }
+
+    fn file_name(&self) -> Option<&str> {
+        F::file_name(self.as_str())
+    }
+
+    fn try_join_in_place(&mut self, path: &Self) -> Result<(), S::Error> {
+        F::join_in_place(&mut self.path, path.as_str())
+    }
+
+    fn try_join(&self, path: &Self) -> Result<Self, S::Error> {
+        F::join(self.as_str(), path.as_str()).map(Self::from_storage)
+    }
+
+    fn file_stem(&self) -> Option<&str> {
+        F::file_stem(self.as_str())
+    }
+
+    fn extension(&self) -> Option<&str> {
+        F::extension(self.as_str())
+    }
+
+    fn try_with_extension(&self, ext: &str) -> Result<Self, S::Error> {
+        F::with_extension(self.as_str(), ext).map(Self::from_storage)
+    }
+
+    fn is_absolute(&self) -> bool {
+        F::is_absolute(self.as_str())
+    }
+
+    fn components(&self) -> impl DoubleEndedIterator<Item = Component<'_>> {
+        <Components<'_, F>>::new(self.as_str())
+    }
+
+    fn expanduser_with<H: AsRef<str>>(
+        &self,
+        home: impl FnOnce(Option<&str>) -> Option<H>,
+    ) -> Result<Self, ExpandUserError<S::Error>> {
+        let expanded = match F::split_user(self.as_str()) {
+            Some((user, rest)) => {
+                let home = home(user).ok_or(ExpandUserError::UnknownUser)?;
+                match rest.is_empty() {
+                    true => S::try_from_str(home.as_ref()),
+                    false => F::join(home.as_ref(), rest),
+                }
+            }
+            None => S::try_from_str(self.as_str()),
+        };
+        expanded
+            .map(Self::from_storage)
+            .map_err(ExpandUserError::Capacity)
+    }
+
+    fn expand_vars_with<V: AsRef<str>>(
+        &self,
+        options: &VarOptions,
+        lookup: impl FnMut(&str) -> Option<V>,
+    ) -> Result<Self, ExpandVarsError<'_, S::Error>> {
+        let syntax = options.syntax.unwrap_or(F::VAR_SYNTAX);
+        expand_vars(self.as_str(), syntax, options.strict, lookup).map(Self::from_storage)
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::io::{Error, ErrorKind, Result};
+use std::rc::Rc;
+
+use crate::{fs, Component, FileSystem, Path, PurePath, Walk, Windows};
+
+/// A single character matcher of a wildcard segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A literal character.
+    Char(char),
+    /// `?`, which matches any character.
+    Any,
+    /// `*`, which matches any run of characters.
+    Star,
+    /// `[..]`, which matches any character in (or with `[!..]`, not in) the ranges.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+/// Returns whether `a` and `b` are the same character.
+fn eq_char(a: char, b: char, case_sensitive: bool) -> bool {
+    a == b || (!case_sensitive && Windows::fold_case(a) == Windows::fold_case(b))
+}
+
+impl Token {
+    /// Returns whether the token matches `c`, which must not be matched by [`Token::Star`].
+    fn matches(&self, c: char, case_sensitive: bool) -> bool {
+        match self {
+            Token::Char(expected) => eq_char(*expected, c, case_sensitive),
+            Token::Any => true,
+            Token::Star => false,
+            Token::Class { negated, ranges } => {
+                let in_range = |c: char, fold: fn(char) -> char| {
+                    ranges
+                        .iter()
+                        .any(|&(lo, hi)| fold(lo) <= c && c <= fold(hi))
+                };
+                let found = in_range(c, |c| c)
+                    || (!case_sensitive && in_range(Windows::fold_case(c), Windows::fold_case));
+                found != *negated
+            }
+        }
+    }
+}
+
+/// Parses the wildcard segment `s`.
+///
+/// A `[` without a closing `]` is matched literally.
+fn parse_tokens(s: &str) -> Vec<Token> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        i += 1;
+        match c {
+            '*' if tokens.last() == Some(&Token::Star) => {}
+            '*' => tokens.push(Token::Star),
+            '?' => tokens.push(Token::Any),
+            '[' => match parse_class(&chars[i..]) {
+                Some((class, len)) => {
+                    tokens.push(class);
+                    i += len;
+                }
+                None => tokens.push(Token::Char('[')),
+            },
+            c => tokens.push(Token::Char(c)),
+        }
+    }
+    tokens
+}
+
+/// Parses the class that follows a `[`, and returns it with the number of characters it spans.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let start = i;
+    loop {
+        let c = *chars.get(i)?;
+        if c == ']' && i > start {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some('-'), Some(&hi)) if hi != ']' => {
+                ranges.push((c, hi));
+                i += 3;
+            }
+            _ => {
+                ranges.push((c, c));
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Returns whether `tokens` match all of `name`.
+fn matches(tokens: &[Token], name: &str, case_sensitive: bool) -> bool {
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut t, mut n) = (0, 0);
+    // The last star seen, and the position in `name` it currently extends to.
+    let mut backtrack = None;
+    while n < name.len() {
+        match tokens.get(t) {
+            Some(Token::Star) => {
+                backtrack = Some((t, n));
+                t += 1;
+                continue;
+            }
+            Some(token) if token.matches(name[n], case_sensitive) => {
+                t += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((star, end)) => {
+                backtrack = Some((star, end + 1));
+                t = star + 1;
+                n = end + 1;
+            }
+            None => return false,
+        }
+    }
+    tokens[t..].iter().all(|token| *token == Token::Star)
+}
+
+/// A wildcard pattern matched against whole strings, in which `*` also matches separators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pattern(Vec<Token>);
+
+impl Pattern {
+    /// Parses `pattern`.
+    pub(crate) fn new(pattern: &str) -> Self {
+        Self(parse_tokens(pattern))
+    }
+
+    /// Returns whether the pattern matches all of `s`, case-sensitively.
+    pub(crate) fn matches(&self, s: &str) -> bool {
+        matches(&self.0, s, true)
+    }
+}
+
+/// A segment of a glob pattern, between two separators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// A name without wildcards.
+    Literal(String),
+    /// A name with wildcards.
+    Wildcard(Vec<Token>),
+    /// `**`, which matches this directory and all directories below it.
+    Recursive,
+}
+
+impl Segment {
+    /// Parses the segment `s`.
+    fn parse(s: &str) -> Self {
+        match s {
+            "**" => Segment::Recursive,
+            s if s.contains(['*', '?', '[']) => Segment::Wildcard(parse_tokens(s)),
+            s => Segment::Literal(s.to_owned()),
+        }
+    }
+}
+
+/// The work left to do for a glob.
+enum Work<P> {
+    /// Matching the segment at the index against the entries of a directory.
+    Dir(P, usize),
+    /// Matching the segment at the index against the directories of a walk.
+    Walk(Box<Walk<P>>, usize),
+    /// Yielding a match.
+    Match(P),
+}
+
+/// An iterator over the paths that match a glob pattern, returned by [`Path::glob`] and
+/// [`Path::rglob`].
+///
+/// Patterns are split into segments with the separators of the path's flavor, so the same
+/// pattern matches the same paths on every system. Each segment may use `*`, `?` and `[..]`,
+/// and a segment of `**` matches the directory and all directories below it.
+/// Directories that cannot be read are skipped. They are read from the backend that was
+/// current when the glob was created.
+pub struct Glob<P> {
+    segments: Vec<Segment>,
+    case_sensitive: bool,
+    follow_symlinks: bool,
+    include_hidden: bool,
+    root: Option<P>,
+    /// The work still to do, with the next one last.
+    todo: Vec<Work<P>>,
+    /// The matches yielded so far, kept only if a path can be matched more than once.
+    seen: Option<HashSet<String>>,
+    /// The backend the directories are read from.
+    fs: Rc<dyn FileSystem>,
+}
+
+impl<P> Glob<P> {
+    /// Sets whether names are matched case-sensitively, which is the default.
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Sets whether `**` walks into symlinks to directories.
+    ///
+    /// It does not by default. Symlinks are always followed when matching other segments.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets whether wildcards match names starting with a `.`.
+    ///
+    /// They do not by default, so hidden files are only matched by segments that start with
+    /// a literal `.`, and `**` does not walk into hidden directories.
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Returns whether wildcards in `segment` may match `name`.
+    fn is_visible(&self, segment: &[Token], name: &str) -> bool {
+        self.include_hidden || !name.starts_with('.') || segment.first() == Some(&Token::Char('.'))
+    }
+}
+
+impl<P: PurePath> Glob<P> {
+    /// Creates a glob matching `pattern` below `root`, or at any depth if `recursive` is true.
+    pub(crate) fn new(root: P, pattern: &P, recursive: bool) -> Result<Self> {
+        let mut segments = Vec::new();
+        if recursive {
+            segments.push(Segment::Recursive);
+        }
+        for component in pattern.components() {
+            let segment = match component {
+                Component::Prefix(_) | Component::Root => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "Non-relative patterns are unsupported",
+                    ));
+                }
+                Component::CurDir => continue,
+                Component::ParentDir => Segment::Literal("..".to_owned()),
+                Component::Normal(s) => Segment::parse(s),
+            };
+            if !(segment == Segment::Recursive && segments.last() == Some(&Segment::Recursive)) {
+                segments.push(segment);
+            }
+        }
+        if segments.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Empty glob pattern"));
+        }
+        let recursive = segments.iter().filter(|s| **s == Segment::Recursive);
+        let seen = (recursive.count() > 1).then(HashSet::new);
+        Ok(Self {
+            segments,
+            case_sensitive: true,
+            follow_symlinks: false,
+            include_hidden: false,
+            root: Some(root),
+            todo: Vec::new(),
+            seen,
+            fs: fs::current(),
+        })
+    }
+}
+
+impl<P> Glob<P>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    /// Matches the segment at `index` against the entries of `dir`.
+    fn match_dir(&mut self, dir: P, index: usize) {
+        let last = index + 1 == self.segments.len();
+        let tokens = match &self.segments[index] {
+            Segment::Literal(name) if self.case_sensitive => {
+                let child = dir.join(&P::from(name.as_str()));
+                match last {
+                    true if child.symlink_metadata().is_ok() => self.todo.push(Work::Match(child)),
+                    false if child.is_dir() => self.todo.push(Work::Dir(child, index + 1)),
+                    _ => {}
+                }
+                return;
+            }
+            Segment::Literal(name) => name.chars().map(Token::Char).collect(),
+            Segment::Wildcard(tokens) => tokens.clone(),
+            Segment::Recursive => {
+                let walk = dir.walk().follow_symlinks(self.follow_symlinks);
+                self.todo.push(Work::Walk(Box::new(walk), index + 1));
+                return;
+            }
+        };
+        let Ok(entries) = dir.scandir() else {
+            return;
+        };
+        let mut work = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if !self.is_visible(&tokens, name) || !matches(&tokens, name, self.case_sensitive) {
+                continue;
+            }
+            if last {
+                work.push(Work::Match(entry.into_path()));
+            } else if entry.is_dir() || (entry.is_symlink() && entry.path().is_dir()) {
+                work.push(Work::Dir(entry.into_path(), index + 1));
+            }
+        }
+        self.todo.extend(work.into_iter().rev());
+    }
+
+    /// Matches the segment at `index` against the next directory of `walk`.
+    fn match_walk(&mut self, mut walk: Box<Walk<P>>, index: usize) {
+        let Some((dir, _, files)) = walk.next() else {
+            return;
+        };
+        if !self.include_hidden {
+            walk.prune(|name| !name.starts_with('.'));
+        }
+        self.todo.push(Work::Walk(walk, index));
+        if index < self.segments.len() {
+            self.todo.push(Work::Dir(dir, index));
+            return;
+        }
+        // A trailing `**` matches the files below the directory as well.
+        for file in files.iter().rev() {
+            if self.include_hidden || !file.starts_with('.') {
+                self.todo
+                    .push(Work::Match(dir.join(&P::from(file.as_str()))));
+            }
+        }
+        self.todo.push(Work::Match(dir));
+    }
+}
+
+impl<P> Iterator for Glob<P>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    type Item = P;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        fs::enter(self.fs.clone(), || self.advance())
+    }
+}
+
+impl<P> Glob<P>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    /// Returns the next match, with the backend of the glob current.
+    fn advance(&mut self) -> Option<P> {
+        if let Some(root) = self.root.take() {
+            self.todo.push(Work::Dir(root, 0));
+        }
+        loop {
+            match self.todo.pop()? {
+                Work::Dir(dir, index) => self.match_dir(dir, index),
+                Work::Walk(walk, index) => self.match_walk(walk, index),
+                Work::Match(path) => {
+                    if let Some(seen) = &mut self.seen {
+                        if !seen.insert(path.as_ref().to_owned()) {
+                            continue;
+                        }
+                    }
+                    return Some(path);
+                }
+            }
+        }
+    }
+}
@@ -29,26 +29,119 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 //! A simple path manipulation library.
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+mod audit;
+#[cfg(feature = "std")]
+mod case;
 mod comp;
+#[cfg(feature = "std")]
+mod copy;
+#[cfg(feature = "std")]
+mod dir;
+#[cfg(feature = "std")]
+mod error;
+mod expand;
+#[cfg(feature = "std")]
+mod fault;
+mod flavor;
+#[cfg(feature = "std")]
+mod fs;
+mod generic;
+#[cfg(feature = "std")]
+mod glob;
+#[cfg(feature = "std")]
+mod memory;
+mod metadata;
+#[cfg(feature = "std")]
+mod mode;
+#[cfg(feature = "std")]
+mod mount;
+#[cfg(feature = "alloc")]
 mod nt;
 #[cfg(feature = "std")]
+mod overlay;
+#[cfg(feature = "std")]
 mod path;
+#[cfg(feature = "alloc")]
 mod posix;
 mod pure;
+mod storage;
+#[cfg(feature = "std")]
+mod sys;
+#[cfg(feature = "std")]
+mod text;
+#[cfg(feature = "std")]
+mod transaction;
+#[cfg(feature = "alloc")]
 mod unified;
+#[cfg(feature = "std")]
+mod usage;
+#[cfg(feature = "std")]
+mod walk;
 
-#[cfg(not(feature = "std"))]
-use alloc::{borrow::ToOwned, string::String};
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::string::String;
 #[cfg(feature = "std")]
-use std::{borrow::ToOwned, string::String};
+use std::string::String;
 
+#[cfg(feature = "std")]
+pub use audit::{AuditEvent, AuditFileSystem};
+#[cfg(feature = "std")]
+pub use case::CaseInsensitiveFileSystem;
 pub use comp::{Component, Components};
+#[cfg(feature = "std")]
+pub use copy::{CopyOptions, CopyProgress, OverwritePolicy};
+#[cfg(feature = "std")]
+pub use dir::{DirEntry, IterDir, NonUtf8Policy, ScanDir};
+#[cfg(feature = "std")]
+pub use error::{
+    NonUtf8Error, ParseModeError, PathError, RollbackError, UndefinedVarError, UnknownOwnerError,
+    UnknownUserError, UnsupportedXattrError,
+};
+pub use expand::{ExpandUserError, ExpandVarsError, VarOptions, VarSyntax};
+#[cfg(feature = "std")]
+pub use fault::{Fault, FaultRule, FaultyFileSystem, Operation};
+pub use flavor::{Flavor, Posix, Unified, Windows};
+#[cfg(feature = "std")]
+pub use fs::{
+    with_file_system, FileSystem, OpenFile, OpenOptions, RawDirEntry, ReadDir, StdFileSystem,
+};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use generic::ArcPath;
+pub use generic::{ArrayPath, GenericPath};
+#[cfg(feature = "alloc")]
+pub use generic::{BoxPath, CowPath, RcPath, SmallPath};
+#[cfg(feature = "std")]
+pub use glob::Glob;
+#[cfg(feature = "std")]
+pub use memory::MemoryFileSystem;
+pub use metadata::{FileType, Metadata, Timestamp};
+#[cfg(feature = "std")]
+pub use mode::ModeChange;
+#[cfg(feature = "std")]
+pub use mount::{parse_mountinfo, MountInfo};
+#[cfg(feature = "alloc")]
 pub use nt::WindowsPath;
 #[cfg(feature = "std")]
+pub use overlay::{Change, ChangeKind, OverlayFileSystem};
+#[cfg(feature = "std")]
 pub use path::Path;
+#[cfg(feature = "alloc")]
 pub use posix::PosixPath;
 pub use pure::PurePath;
+#[cfg(feature = "alloc")]
+pub use storage::SmallString;
+pub use storage::{ArrayString, CapacityError, PathStorage};
+#[cfg(feature = "std")]
+pub use text::{Encoding, Newline, TextOptions};
+#[cfg(feature = "std")]
+pub use transaction::Transaction;
+#[cfg(feature = "alloc")]
 pub use unified::UnifiedPath;
+#[cfg(feature = "std")]
+pub use usage::{DiskUsage, SizeOptions, TreeSize};
+#[cfg(feature = "std")]
+pub use walk::Walk;
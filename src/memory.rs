@@ -0,0 +1,883 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use crate::{
+    FileSystem, FileType, Metadata, OpenFile, OpenOptions, RawDirEntry, ReadDir,
+    UnsupportedXattrError,
+};
+
+/// The inode number of the root directory.
+const ROOT: u64 = 1;
+/// The device number reported for every file.
+const DEV: u64 = 1;
+/// The size reported for directories.
+const DIR_SIZE: u64 = 4096;
+/// The unit that the space of files is allocated in.
+const BLOCK_SIZE: u64 = 4096;
+/// The maximum number of symlinks followed while resolving a path, as on Linux.
+pub(crate) const MAX_SYMLINKS: usize = 40;
+
+/// The namespaces that extended attribute names must start with, as on Linux.
+const XATTR_NAMESPACES: [&str; 4] = ["security.", "system.", "trusted.", "user."];
+
+/// The permission bit that allows reading.
+const READ: u32 = 0o400;
+/// The permission bit that allows writing.
+const WRITE: u32 = 0o200;
+/// The permission bit that allows searching a directory.
+const SEARCH: u32 = 0o100;
+
+/// The contents of an inode.
+#[derive(Debug, Clone)]
+enum Node {
+    /// A regular file.
+    File(Vec<u8>),
+    /// A directory with its parent and its entries in order of creation.
+    Dir {
+        parent: u64,
+        entries: Vec<(String, u64)>,
+    },
+    /// A symlink with its target.
+    Symlink(String),
+}
+
+/// A file of the filesystem, which may be linked into several directories.
+#[derive(Debug, Clone)]
+struct Inode {
+    node: Node,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    nlink: u64,
+    /// The number of open handles, which keep the inode alive after it is unlinked.
+    handles: usize,
+    atime: SystemTime,
+    mtime: SystemTime,
+    ctime: SystemTime,
+    btime: SystemTime,
+    xattrs: BTreeMap<String, Vec<u8>>,
+}
+
+impl Inode {
+    /// Creates an inode with all timestamps set to now.
+    fn new(node: Node, mode: u32) -> Self {
+        let now = SystemTime::now();
+        Self {
+            node,
+            mode,
+            uid: 0,
+            gid: 0,
+            nlink: 1,
+            handles: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            btime: now,
+            xattrs: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the file type of the inode.
+    fn file_type(&self) -> FileType {
+        match self.node {
+            Node::File(_) => FileType::File,
+            Node::Dir { .. } => FileType::Dir,
+            Node::Symlink(_) => FileType::Symlink,
+        }
+    }
+
+    /// Returns an error unless the owner has all of the permission bits in `mask`.
+    fn check(&self, mask: u32) -> Result<()> {
+        match self.mode & mask == mask {
+            true => Ok(()),
+            false => Err(ErrorKind::PermissionDenied.into()),
+        }
+    }
+
+    /// Marks the inode as modified now.
+    fn touch(&mut self) {
+        let now = SystemTime::now();
+        self.mtime = now;
+        self.ctime = now;
+    }
+}
+
+/// The result of resolving a path.
+struct Resolved {
+    /// The directory holding the last component.
+    parent: u64,
+    /// The last component, or `None` if the path ends in `.`, `..` or the root.
+    name: Option<String>,
+    /// The file the path refers to, if it exists.
+    ino: Option<u64>,
+}
+
+/// The state of a [MemoryFileSystem], shared with its open files.
+#[derive(Debug)]
+struct State {
+    inodes: HashMap<u64, Inode>,
+    next_ino: u64,
+    cwd: u64,
+    umask: u32,
+}
+
+impl State {
+    /// Returns the inode `ino`.
+    fn inode(&self, ino: u64) -> Result<&Inode> {
+        self.inodes
+            .get(&ino)
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    /// Returns the inode `ino` mutably.
+    fn inode_mut(&mut self, ino: u64) -> Result<&mut Inode> {
+        self.inodes
+            .get_mut(&ino)
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    /// Returns the parent and the entries of the directory `ino`.
+    fn dir(&self, ino: u64) -> Result<(u64, &[(String, u64)])> {
+        match &self.inode(ino)?.node {
+            Node::Dir { parent, entries } => Ok((*parent, entries)),
+            _ => Err(ErrorKind::NotADirectory.into()),
+        }
+    }
+
+    /// Returns the entries of the directory `ino` mutably.
+    fn entries_mut(&mut self, ino: u64) -> Result<&mut Vec<(String, u64)>> {
+        match &mut self.inode_mut(ino)?.node {
+            Node::Dir { entries, .. } => Ok(entries),
+            _ => Err(ErrorKind::NotADirectory.into()),
+        }
+    }
+
+    /// Returns the entry `name` of the directory `dir`.
+    fn lookup(&self, dir: u64, name: &str) -> Result<Option<u64>> {
+        let (_, entries) = self.dir(dir)?;
+        Ok(entries.iter().find(|(n, _)| n == name).map(|&(_, ino)| ino))
+    }
+
+    /// Resolves `path` the way Linux does, following symlinks in all components but the
+    /// last, and in the last one as well if `follow` is true.
+    fn resolve(&self, path: &str, follow: bool) -> Result<Resolved> {
+        if path.is_empty() {
+            return Err(ErrorKind::NotFound.into());
+        }
+        let must_be_dir = path.ends_with('/');
+        let follow = follow || must_be_dir;
+        let mut components = split(path);
+        let mut dir = match path.starts_with('/') {
+            true => ROOT,
+            false => self.cwd,
+        };
+        let mut symlinks = 0;
+        while let Some(name) = components.pop_front() {
+            let (parent, _) = self.dir(dir)?;
+            self.inode(dir)?.check(SEARCH)?;
+            let last = components.is_empty();
+            let ino = match name.as_str() {
+                "." => Some(dir),
+                ".." => Some(parent),
+                name => self.lookup(dir, name)?,
+            };
+            let Some(ino) = ino else {
+                return match last {
+                    true => Ok(Resolved {
+                        parent: dir,
+                        name: Some(name),
+                        ino: None,
+                    }),
+                    false => Err(ErrorKind::NotFound.into()),
+                };
+            };
+            let inode = self.inode(ino)?;
+            if let Node::Symlink(target) = &inode.node {
+                if !last || follow {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(symlink_loop());
+                    }
+                    let mut target_components = split(target);
+                    target_components.extend(components);
+                    components = target_components;
+                    if target.starts_with('/') {
+                        dir = ROOT;
+                    }
+                    if components.is_empty() {
+                        components.push_back(".".to_owned());
+                    }
+                    continue;
+                }
+            }
+            if last {
+                if must_be_dir && !matches!(inode.node, Node::Dir { .. }) {
+                    return Err(ErrorKind::NotADirectory.into());
+                }
+                return Ok(match name.as_str() {
+                    "." | ".." => Resolved {
+                        parent: self.dir(ino)?.0,
+                        name: None,
+                        ino: Some(ino),
+                    },
+                    _ => Resolved {
+                        parent: dir,
+                        name: Some(name),
+                        ino: Some(ino),
+                    },
+                });
+            }
+            dir = ino;
+        }
+        Ok(Resolved {
+            parent: self.dir(dir)?.0,
+            name: None,
+            ino: Some(dir),
+        })
+    }
+
+    /// Resolves `path` to an existing file.
+    fn resolve_existing(&self, path: &str, follow: bool) -> Result<u64> {
+        self.resolve(path, follow)?
+            .ino
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    /// Resolves `path` to a directory and a name that does not exist in it yet.
+    fn resolve_new(&self, path: &str) -> Result<(u64, String)> {
+        match self.resolve(path, false)? {
+            Resolved { ino: Some(_), .. } => Err(ErrorKind::AlreadyExists.into()),
+            Resolved {
+                parent,
+                name: Some(name),
+                ino: None,
+            } => Ok((parent, name)),
+            Resolved { name: None, .. } => Err(ErrorKind::AlreadyExists.into()),
+        }
+    }
+
+    /// Returns the absolute path of the directory `ino`.
+    fn dir_path(&self, mut ino: u64) -> Result<String> {
+        let mut names = Vec::new();
+        while ino != ROOT {
+            let (parent, _) = self.dir(ino)?;
+            let (_, entries) = self.dir(parent)?;
+            let (name, _) = entries
+                .iter()
+                .find(|&&(_, child)| child == ino)
+                .ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+            names.push(name.as_str());
+            ino = parent;
+        }
+        match names.is_empty() {
+            true => Ok("/".to_owned()),
+            false => Ok(names
+                .iter()
+                .rev()
+                .fold(String::new(), |path, name| path + "/" + name)),
+        }
+    }
+
+    /// Adds a new inode as the entry `name` of the directory `dir`.
+    fn link_new(&mut self, dir: u64, name: String, inode: Inode) -> Result<u64> {
+        self.inode(dir)?.check(WRITE | SEARCH)?;
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        let is_dir = matches!(inode.node, Node::Dir { .. });
+        let _ = self.inodes.insert(ino, inode);
+        self.entries_mut(dir)?.push((name, ino));
+        let parent = self.inode_mut(dir)?;
+        parent.touch();
+        if is_dir {
+            parent.nlink += 1;
+        }
+        Ok(ino)
+    }
+
+    /// Removes the entry `name` of the directory `dir`, and drops its inode once unused.
+    fn unlink(&mut self, dir: u64, name: &str) -> Result<()> {
+        self.inode(dir)?.check(WRITE | SEARCH)?;
+        let entries = self.entries_mut(dir)?;
+        let index = entries
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+        let (_, ino) = entries.remove(index);
+        let inode = self.inode_mut(ino)?;
+        let is_dir = matches!(inode.node, Node::Dir { .. });
+        inode.nlink -= match is_dir {
+            true => inode.nlink,
+            false => 1,
+        };
+        inode.ctime = SystemTime::now();
+        if inode.nlink == 0 && inode.handles == 0 {
+            let _ = self.inodes.remove(&ino);
+        }
+        let parent = self.inode_mut(dir)?;
+        parent.touch();
+        if is_dir {
+            parent.nlink -= 1;
+        }
+        Ok(())
+    }
+
+    /// Returns the metadata of the inode `ino`.
+    fn metadata(&self, ino: u64) -> Result<Metadata> {
+        let inode = self.inode(ino)?;
+        Ok(Metadata {
+            file_type: inode.file_type(),
+            size: match &inode.node {
+                Node::File(data) => data.len() as u64,
+                Node::Dir { .. } => DIR_SIZE,
+                Node::Symlink(target) => target.len() as u64,
+            },
+            mode: inode.mode,
+            uid: inode.uid,
+            gid: inode.gid,
+            dev: DEV,
+            ino,
+            nlink: inode.nlink,
+            blocks: match &inode.node {
+                Node::File(data) => (data.len() as u64).div_ceil(BLOCK_SIZE) * (BLOCK_SIZE / 512),
+                Node::Dir { .. } => DIR_SIZE / 512,
+                Node::Symlink(_) => 0,
+            },
+            atime: Some(inode.atime.into()),
+            mtime: Some(inode.mtime.into()),
+            ctime: Some(inode.ctime.into()),
+            btime: Some(inode.btime.into()),
+        })
+    }
+}
+
+/// Returns the error for a path that goes through too many symlinks.
+pub(crate) fn symlink_loop() -> Error {
+    Error::other("Too many levels of symbolic links")
+}
+
+/// Checks that the extended attribute `name` is in a known namespace.
+fn check_xattr(name: &str) -> Result<()> {
+    match XATTR_NAMESPACES
+        .iter()
+        .any(|namespace| name.len() > namespace.len() && name.starts_with(namespace))
+    {
+        true => Ok(()),
+        false => Err(UnsupportedXattrError::new(Some(name.to_owned())).into()),
+    }
+}
+
+/// Splits `path` into its components, dropping empty ones.
+pub(crate) fn split(path: &str) -> VecDeque<String> {
+    path.split('/')
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// A filesystem held in memory, which behaves like a Linux filesystem.
+///
+/// Paths use `/` as separator, and relative paths are resolved against the current
+/// directory, which is initially the root. Symlinks are resolved as Linux does, and
+/// [`read_dir`](FileSystem::read_dir) lists entries from the most recently created one,
+/// as tmpfs does. Permissions are checked as for an unprivileged owner of every file.
+/// Files are created with user and group ID 0, and any ownership can be set. Extended
+/// attributes are supported in the namespaces of Linux, such as `user.`.
+///
+/// Clones share the same files, so a clone can be passed to
+/// [`with_file_system`](crate::with_file_system) and the original inspected afterwards.
+#[derive(Debug, Clone)]
+pub struct MemoryFileSystem {
+    state: Rc<RefCell<State>>,
+}
+
+impl MemoryFileSystem {
+    /// Creates a filesystem holding only an empty root directory.
+    pub fn new() -> Self {
+        let root = Node::Dir {
+            parent: ROOT,
+            entries: Vec::new(),
+        };
+        let mut root = Inode::new(root, 0o755);
+        root.nlink = 2;
+        let state = State {
+            inodes: HashMap::from([(ROOT, root)]),
+            next_ino: ROOT + 1,
+            cwd: ROOT,
+            umask: 0o022,
+        };
+        Self {
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    /// Changes the current directory.
+    pub fn set_current_dir(&self, path: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let ino = state.resolve_existing(path, true)?;
+        let _ = state.dir(ino)?;
+        state.cwd = ino;
+        Ok(())
+    }
+
+    /// Sets the mask of permission bits removed from created files and directories, and
+    /// returns the previous one. It is `0o022` initially.
+    pub fn set_umask(&self, umask: u32) -> u32 {
+        core::mem::replace(&mut self.state.borrow_mut().umask, umask & 0o777)
+    }
+}
+
+impl Default for MemoryFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn current_dir(&self) -> Result<String> {
+        let state = self.state.borrow();
+        state.dir_path(state.cwd)
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        let state = self.state.borrow();
+        state.metadata(state.resolve_existing(path, true)?)
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        let state = self.state.borrow();
+        state.metadata(state.resolve_existing(path, false)?)
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String> {
+        let state = self.state.borrow();
+        let resolved = state.resolve(path, true)?;
+        let ino = resolved
+            .ino
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+        match (&state.inode(ino)?.node, resolved.name) {
+            (Node::Dir { .. }, _) | (_, None) => state.dir_path(ino),
+            (_, Some(name)) => {
+                let parent = state.dir_path(resolved.parent)?;
+                Ok(match parent.as_str() {
+                    "/" => parent + &name,
+                    _ => parent + "/" + &name,
+                })
+            }
+        }
+    }
+
+    fn read_dir(&self, path: &str) -> Result<ReadDir> {
+        let state = self.state.borrow();
+        let ino = state.resolve_existing(path, true)?;
+        let (_, entries) = state.dir(ino)?;
+        state.inode(ino)?.check(READ)?;
+        let entries = entries
+            .iter()
+            .rev()
+            .map(|(name, ino)| {
+                Ok(RawDirEntry {
+                    name: OsString::from(name),
+                    file_type: state.inode(*ino)?.file_type(),
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        let state = self.state.borrow();
+        let ino = state.resolve_existing(path, false)?;
+        match &state.inode(ino)?.node {
+            Node::Symlink(target) => Ok(target.clone()),
+            _ => Err(ErrorKind::InvalidInput.into()),
+        }
+    }
+
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        let mut state = self.state.borrow_mut();
+        let resolved = state.resolve(path, true)?;
+        let ino = match (resolved.ino, resolved.name) {
+            (Some(_), _) if options.create_new => return Err(ErrorKind::AlreadyExists.into()),
+            (Some(ino), _) => {
+                let inode = state.inode_mut(ino)?;
+                if let Node::Dir { .. } = inode.node {
+                    if options.writes() {
+                        return Err(ErrorKind::IsADirectory.into());
+                    }
+                }
+                if options.read {
+                    inode.check(READ)?;
+                }
+                if options.writes() {
+                    inode.check(WRITE)?;
+                }
+                if let Node::File(data) = &mut inode.node {
+                    if options.truncate && options.writes() && !data.is_empty() {
+                        data.clear();
+                        inode.touch();
+                    }
+                }
+                ino
+            }
+            (None, Some(name)) if options.create || options.create_new => {
+                let mode = options.mode & 0o7777 & !state.umask;
+                let inode = Inode::new(Node::File(Vec::new()), mode);
+                state.link_new(resolved.parent, name, inode)?
+            }
+            (None, _) => return Err(ErrorKind::NotFound.into()),
+        };
+        state.inode_mut(ino)?.handles += 1;
+        Ok(Box::new(MemoryFile {
+            state: self.state.clone(),
+            ino,
+            pos: 0,
+            read: options.read,
+            write: options.writes(),
+            append: options.append,
+        }))
+    }
+
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (parent, name) = state.resolve_new(path)?;
+        let dir = Node::Dir {
+            parent,
+            entries: Vec::new(),
+        };
+        let mut inode = Inode::new(dir, mode & 0o7777 & !state.umask);
+        inode.nlink = 2;
+        let _ = state.link_new(parent, name, inode)?;
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let resolved = state.resolve(path, false)?;
+        let ino = resolved
+            .ino
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+        if let Node::Dir { .. } = state.inode(ino)?.node {
+            return Err(ErrorKind::IsADirectory.into());
+        }
+        let name = resolved
+            .name
+            .ok_or_else(|| Error::from(ErrorKind::IsADirectory))?;
+        state.unlink(resolved.parent, &name)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let resolved = state.resolve(path, false)?;
+        let ino = resolved
+            .ino
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+        let (_, entries) = state.dir(ino)?;
+        if ino == ROOT {
+            return Err(ErrorKind::ResourceBusy.into());
+        }
+        let name = resolved
+            .name
+            .ok_or_else(|| Error::from(ErrorKind::InvalidInput))?;
+        if !entries.is_empty() {
+            return Err(ErrorKind::DirectoryNotEmpty.into());
+        }
+        state.unlink(resolved.parent, &name)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let source = state.resolve(from, false)?;
+        let ino = source.ino.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+        let (Some(from_name), to_resolved) = (source.name, state.resolve(to, false)?) else {
+            return Err(ErrorKind::ResourceBusy.into());
+        };
+        let to_name = to_resolved
+            .name
+            .ok_or_else(|| Error::from(ErrorKind::ResourceBusy))?;
+        let is_dir = matches!(state.inode(ino)?.node, Node::Dir { .. });
+        if let Some(existing) = to_resolved.ino {
+            if existing == ino {
+                return Ok(());
+            }
+            match (is_dir, &state.inode(existing)?.node) {
+                (true, Node::Dir { entries, .. }) if !entries.is_empty() => {
+                    return Err(ErrorKind::DirectoryNotEmpty.into());
+                }
+                (true, Node::Dir { .. }) => {}
+                (true, _) => return Err(ErrorKind::NotADirectory.into()),
+                (false, Node::Dir { .. }) => return Err(ErrorKind::IsADirectory.into()),
+                (false, _) => {}
+            }
+        }
+        if is_dir {
+            // A directory cannot be moved into itself.
+            let mut dir = to_resolved.parent;
+            while dir != ROOT {
+                if dir == ino {
+                    return Err(ErrorKind::InvalidInput.into());
+                }
+                dir = state.dir(dir)?.0;
+            }
+        }
+        state.inode(source.parent)?.check(WRITE | SEARCH)?;
+        state.inode(to_resolved.parent)?.check(WRITE | SEARCH)?;
+        if to_resolved.ino.is_some() {
+            state.unlink(to_resolved.parent, &to_name)?;
+        }
+        let entries = state.entries_mut(source.parent)?;
+        entries.retain(|(name, _)| *name != from_name);
+        state.entries_mut(to_resolved.parent)?.push((to_name, ino));
+        let now = SystemTime::now();
+        for dir in [source.parent, to_resolved.parent] {
+            state.inode_mut(dir)?.touch();
+        }
+        let inode = state.inode_mut(ino)?;
+        inode.ctime = now;
+        if let Node::Dir { parent, .. } = &mut inode.node {
+            *parent = to_resolved.parent;
+            state.inode_mut(source.parent)?.nlink -= 1;
+            state.inode_mut(to_resolved.parent)?.nlink += 1;
+        }
+        Ok(())
+    }
+
+    fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (parent, name) = state.resolve_new(link)?;
+        let inode = Inode::new(Node::Symlink(target.to_owned()), 0o777);
+        let _ = state.link_new(parent, name, inode)?;
+        Ok(())
+    }
+
+    fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let ino = state.resolve_existing(original, false)?;
+        if let Node::Dir { .. } = state.inode(ino)?.node {
+            return Err(ErrorKind::PermissionDenied.into());
+        }
+        let (parent, name) = state.resolve_new(link)?;
+        state.inode(parent)?.check(WRITE | SEARCH)?;
+        state.entries_mut(parent)?.push((name, ino));
+        state.inode_mut(parent)?.touch();
+        let inode = state.inode_mut(ino)?;
+        inode.nlink += 1;
+        inode.ctime = SystemTime::now();
+        Ok(())
+    }
+
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let ino = state.resolve_existing(path, true)?;
+        let inode = state.inode_mut(ino)?;
+        inode.mode = mode & 0o7777;
+        inode.ctime = SystemTime::now();
+        Ok(())
+    }
+
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let ino = state.resolve_existing(path, follow)?;
+        let inode = state.inode_mut(ino)?;
+        inode.uid = uid.unwrap_or(inode.uid);
+        inode.gid = gid.unwrap_or(inode.gid);
+        inode.ctime = SystemTime::now();
+        Ok(())
+    }
+
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let ino = state.resolve_existing(path, follow)?;
+        let inode = state.inode_mut(ino)?;
+        inode.atime = atime.unwrap_or(inode.atime);
+        inode.mtime = mtime.unwrap_or(inode.mtime);
+        inode.ctime = SystemTime::now();
+        Ok(())
+    }
+
+    fn umask(&self) -> Result<u32> {
+        Ok(self.state.borrow().umask)
+    }
+
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        let state = self.state.borrow();
+        let ino = state.resolve_existing(path, follow)?;
+        Ok(state.inode(ino)?.xattrs.keys().cloned().collect())
+    }
+
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        check_xattr(name)?;
+        let state = self.state.borrow();
+        let ino = state.resolve_existing(path, follow)?;
+        Ok(state.inode(ino)?.xattrs.get(name).cloned())
+    }
+
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        check_xattr(name)?;
+        let mut state = self.state.borrow_mut();
+        let ino = state.resolve_existing(path, follow)?;
+        let inode = state.inode_mut(ino)?;
+        let _ = inode.xattrs.insert(name.to_owned(), value.to_vec());
+        inode.ctime = SystemTime::now();
+        Ok(())
+    }
+
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        check_xattr(name)?;
+        let mut state = self.state.borrow_mut();
+        let ino = state.resolve_existing(path, follow)?;
+        let inode = state.inode_mut(ino)?;
+        if inode.xattrs.remove(name).is_none() {
+            return Err(Error::new(ErrorKind::NotFound, "No such attribute"));
+        }
+        inode.ctime = SystemTime::now();
+        Ok(())
+    }
+}
+
+/// A file opened in a [MemoryFileSystem].
+struct MemoryFile {
+    state: Rc<RefCell<State>>,
+    ino: u64,
+    pos: u64,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl MemoryFile {
+    /// Runs `f` on the data of the file.
+    fn with_data<T>(&self, f: impl FnOnce(&mut Inode, &mut Vec<u8>) -> Result<T>) -> Result<T> {
+        let mut state = self.state.borrow_mut();
+        let inode = state.inode_mut(self.ino)?;
+        let mut node = core::mem::replace(&mut inode.node, Node::File(Vec::new()));
+        let result = match &mut node {
+            Node::File(data) => f(inode, data),
+            _ => Err(ErrorKind::IsADirectory.into()),
+        };
+        inode.node = node;
+        result
+    }
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if !self.read {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "File not opened for reading",
+            ));
+        }
+        let pos = self.pos;
+        let len = self.with_data(|inode, data| {
+            let start = usize::try_from(pos).unwrap_or(usize::MAX).min(data.len());
+            let len = buf.len().min(data.len() - start);
+            buf[..len].copy_from_slice(&data[start..start + len]);
+            inode.atime = SystemTime::now();
+            Ok(len)
+        })?;
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if !self.write {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "File not opened for writing",
+            ));
+        }
+        let (append, pos) = (self.append, self.pos);
+        self.pos = self.with_data(|inode, data| {
+            let start = match append {
+                true => data.len(),
+                false => usize::try_from(pos).map_err(|_| Error::from(ErrorKind::FileTooLarge))?,
+            };
+            let end = start + buf.len();
+            if data.len() < end {
+                data.resize(end, 0);
+            }
+            data[start..end].copy_from_slice(buf);
+            inode.touch();
+            Ok(end as u64)
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let len = self.with_data(|_, data| Ok(data.len() as u64))?;
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = pos.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Invalid seek"))?;
+        Ok(self.pos)
+    }
+}
+
+impl OpenFile for MemoryFile {
+    fn metadata(&self) -> Result<Metadata> {
+        self.state.borrow().metadata(self.ino)
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<()> {
+        if !self.write {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "File not opened for writing",
+            ));
+        }
+        self.with_data(|inode, data| {
+            data.resize(
+                usize::try_from(size).map_err(|_| Error::from(ErrorKind::FileTooLarge))?,
+                0,
+            );
+            inode.touch();
+            Ok(())
+        })
+    }
+
+    fn set_modified(&mut self, time: SystemTime) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let inode = state.inode_mut(self.ino)?;
+        inode.mtime = time;
+        inode.ctime = SystemTime::now();
+        Ok(())
+    }
+}
+
+impl Drop for MemoryFile {
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+        if let Some(inode) = state.inodes.get_mut(&self.ino) {
+            inode.handles -= 1;
+            if inode.nlink == 0 && inode.handles == 0 {
+                let _ = state.inodes.remove(&self.ino);
+            }
+        }
+    }
+}
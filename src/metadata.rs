@@ -0,0 +1,266 @@
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The type of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FileType {
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+    /// A symbolic link.
+    Symlink,
+    /// A named pipe.
+    Fifo,
+    /// A Unix domain socket.
+    Socket,
+    /// A block device.
+    BlockDevice,
+    /// A character device.
+    CharDevice,
+    /// A directory junction, on Windows.
+    Junction,
+    /// A type this crate does not know about.
+    #[default]
+    Unknown,
+}
+
+impl FileType {
+    /// Returns whether this is a directory.
+    pub fn is_dir(&self) -> bool {
+        *self == Self::Dir
+    }
+
+    /// Returns whether this is a regular file.
+    pub fn is_file(&self) -> bool {
+        *self == Self::File
+    }
+
+    /// Returns whether this is a symbolic link, which includes junctions as in the standard
+    /// library.
+    pub fn is_symlink(&self) -> bool {
+        matches!(self, Self::Symlink | Self::Junction)
+    }
+
+    /// Returns whether this is a named pipe.
+    pub fn is_fifo(&self) -> bool {
+        *self == Self::Fifo
+    }
+
+    /// Returns whether this is a Unix domain socket.
+    pub fn is_socket(&self) -> bool {
+        *self == Self::Socket
+    }
+
+    /// Returns whether this is a block device.
+    pub fn is_block_device(&self) -> bool {
+        *self == Self::BlockDevice
+    }
+
+    /// Returns whether this is a character device.
+    pub fn is_char_device(&self) -> bool {
+        *self == Self::CharDevice
+    }
+
+    /// Returns whether this is a directory junction.
+    pub fn is_junction(&self) -> bool {
+        *self == Self::Junction
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<fs::FileType> for FileType {
+    fn from(file_type: fs::FileType) -> Self {
+        #[cfg(unix)]
+        use std::os::unix::fs::FileTypeExt;
+
+        // Junctions are reported as symlinks, which `StdFileSystem` tells apart by their
+        // reparse tag.
+        match file_type {
+            t if t.is_dir() => Self::Dir,
+            t if t.is_file() => Self::File,
+            t if t.is_symlink() => Self::Symlink,
+            #[cfg(unix)]
+            t if t.is_fifo() => Self::Fifo,
+            #[cfg(unix)]
+            t if t.is_socket() => Self::Socket,
+            #[cfg(unix)]
+            t if t.is_block_device() => Self::BlockDevice,
+            #[cfg(unix)]
+            t if t.is_char_device() => Self::CharDevice,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A point in time, as the seconds and nanoseconds since the Unix epoch.
+///
+/// Times before the epoch have negative seconds, and the nanoseconds always count forward,
+/// so `-1.5` seconds is `secs: -2, nanos: 500_000_000`. Timestamps are ordered by time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    /// The whole seconds since the Unix epoch.
+    pub secs: i64,
+    /// The nanoseconds after `secs`, less than one billion.
+    pub nanos: u32,
+}
+
+impl Timestamp {
+    /// Creates a timestamp, carrying whole seconds of `nanos` over into `secs`.
+    pub const fn new(secs: i64, nanos: u32) -> Self {
+        Self {
+            secs: secs.saturating_add((nanos / 1_000_000_000) as i64),
+            nanos: nanos % 1_000_000_000,
+        }
+    }
+
+    /// Converts the timestamp to a [SystemTime], or returns `None` if the system cannot
+    /// represent it.
+    #[cfg(feature = "std")]
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        let nanos = std::time::Duration::from_nanos(u64::from(self.nanos));
+        let secs = std::time::Duration::from_secs(self.secs.unsigned_abs());
+        match self.secs < 0 {
+            true => UNIX_EPOCH.checked_sub(secs)?.checked_add(nanos),
+            false => UNIX_EPOCH.checked_add(secs)?.checked_add(nanos),
+        }
+    }
+}
+
+/// Converts a time, saturating at the range of [Timestamp].
+#[cfg(feature = "std")]
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        let secs = |secs: u64| i64::try_from(secs).unwrap_or(i64::MAX);
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => Self::new(secs(since.as_secs()), since.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                match before.subsec_nanos() {
+                    0 => Self::new(-secs(before.as_secs()), 0),
+                    nanos => Self::new(-secs(before.as_secs()) - 1, 1_000_000_000 - nanos),
+                }
+            }
+        }
+    }
+}
+
+/// The metadata of a file, in the same form for every [FileSystem](crate::FileSystem).
+///
+/// Fields that a filesystem does not report are zero or `None`. Backends other than the
+/// host one construct it field by field, starting from [`Metadata::default`]. It does not
+/// need the standard library, so that code that only inspects metadata works without it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    /// The type of the file.
+    pub file_type: FileType,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The permission bits of the file, as for `chmod(2)`.
+    pub mode: u32,
+    /// The user ID of the owner of the file.
+    pub uid: u32,
+    /// The group ID of the owner of the file.
+    pub gid: u32,
+    /// The ID of the device that holds the file.
+    pub dev: u64,
+    /// The inode number of the file.
+    pub ino: u64,
+    /// The number of hard links to the file.
+    pub nlink: u64,
+    /// The number of 512-byte blocks allocated for the file.
+    pub blocks: u64,
+    /// The time of the last access to the file.
+    pub atime: Option<Timestamp>,
+    /// The time of the last modification of the file.
+    pub mtime: Option<Timestamp>,
+    /// The time of the last change to the metadata of the file.
+    pub ctime: Option<Timestamp>,
+    /// The time the file was created.
+    pub btime: Option<Timestamp>,
+}
+
+impl Metadata {
+    /// Returns whether the file is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.file_type.is_dir()
+    }
+
+    /// Returns whether the file is a regular file.
+    pub fn is_file(&self) -> bool {
+        self.file_type.is_file()
+    }
+
+    /// Returns whether the file is a symbolic link, which includes junctions.
+    pub fn is_symlink(&self) -> bool {
+        self.file_type.is_symlink()
+    }
+
+    /// Returns whether the file is a named pipe.
+    pub fn is_fifo(&self) -> bool {
+        self.file_type.is_fifo()
+    }
+
+    /// Returns whether the file is a Unix domain socket.
+    pub fn is_socket(&self) -> bool {
+        self.file_type.is_socket()
+    }
+
+    /// Returns whether the file is a block device.
+    pub fn is_block_device(&self) -> bool {
+        self.file_type.is_block_device()
+    }
+
+    /// Returns whether the file is a character device.
+    pub fn is_char_device(&self) -> bool {
+        self.file_type.is_char_device()
+    }
+
+    /// Returns whether the file is a directory junction.
+    pub fn is_junction(&self) -> bool {
+        self.file_type.is_junction()
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<fs::Metadata> for Metadata {
+    fn from(metadata: fs::Metadata) -> Self {
+        let file_type = FileType::from(metadata.file_type());
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                use std::os::unix::fs::MetadataExt;
+                let (mode, dev, ino) = (metadata.mode() & 0o7777, metadata.dev(), metadata.ino());
+                let (uid, gid, nlink) = (metadata.uid(), metadata.gid(), metadata.nlink());
+                let blocks = metadata.blocks();
+                let nanos = u32::try_from(metadata.ctime_nsec()).unwrap_or_default();
+                let ctime = Some(Timestamp::new(metadata.ctime(), nanos));
+            } else {
+                let mode = match (metadata.permissions().readonly(), file_type.is_dir()) {
+                    (false, true) => 0o777,
+                    (false, false) => 0o666,
+                    (true, true) => 0o555,
+                    (true, false) => 0o444,
+                };
+                let (uid, gid, dev, ino, nlink, blocks) = (0, 0, 0, 0, 0, 0);
+                let ctime = None;
+            }
+        }
+        Self {
+            file_type,
+            size: metadata.len(),
+            mode,
+            uid,
+            gid,
+            dev,
+            ino,
+            nlink,
+            blocks,
+            atime: metadata.accessed().ok().map(Timestamp::from),
+            mtime: metadata.modified().ok().map(Timestamp::from),
+            ctime,
+            btime: metadata.created().ok().map(Timestamp::from),
+        }
+    }
+}
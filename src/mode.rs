@@ -0,0 +1,231 @@
+use std::str::FromStr;
+
+use crate::ParseModeError;
+
+/// The permission bits that a [ModeChange] can change.
+const ALL: u32 = 0o7777;
+/// The set-user-ID and set-group-ID bits.
+const SET_ID: u32 = 0o6000;
+/// The read, write and execute bits of the owner, group and others.
+const RWX: u32 = 0o777;
+/// The execute bits.
+const EXECUTE: u32 = 0o111;
+
+/// How a clause of a [ModeChange] gets its bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Flag {
+    /// The bits are given.
+    Ordinary,
+    /// The bits are copied from a class of the current mode, as for `g=u`.
+    CopyExisting,
+    /// The execute bits are added if the file is a directory or already executable.
+    XIfAnyX,
+}
+
+/// A single `+`, `-` or `=` operation of a [ModeChange].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Clause {
+    op: u8,
+    flag: Flag,
+    /// The bits of the classes given before the operator, or 0 if none is given.
+    affected: u32,
+    value: u32,
+    /// The bits that are explicitly changed.
+    mentioned: u32,
+}
+
+/// A change of permission bits, parsed from a mode as accepted by `chmod(1)`.
+///
+/// This is either an octal number such as `755`, or a comma-separated list of symbolic
+/// clauses such as `u+x`, `go-w` or `a=r,u+w`, as described in `chmod(1)`. The semantics
+/// are those of GNU `chmod`: clauses without classes are masked by the umask, and numeric
+/// modes with fewer than 5 digits keep the set-user-ID and set-group-ID bits of
+/// directories unless they set them.
+///
+/// A numeric mode converts into the change that sets the bits to it exactly, as an octal
+/// mode with 5 digits does.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModeChange {
+    clauses: Vec<Clause>,
+}
+
+impl ModeChange {
+    /// Parses `mode` as described for [ModeChange].
+    pub fn parse(mode: &str) -> Result<Self, ParseModeError> {
+        let invalid = || ParseModeError::new(mode);
+        let bytes = mode.as_bytes();
+        if bytes.first().is_some_and(u8::is_ascii_digit) {
+            let (value, mentioned) = parse_octal(bytes).ok_or_else(invalid)?;
+            return Ok(Self::equals(value, mentioned));
+        }
+
+        let mut clauses = Vec::new();
+        let mut i = 0;
+        loop {
+            let mut affected = 0;
+            loop {
+                match bytes.get(i) {
+                    Some(b'u') => affected |= 0o4700,
+                    Some(b'g') => affected |= 0o2070,
+                    Some(b'o') => affected |= 0o1007,
+                    Some(b'a') => affected |= ALL,
+                    Some(b'=' | b'+' | b'-') => break,
+                    _ => return Err(invalid()),
+                }
+                i += 1;
+            }
+            while let Some(&op @ (b'=' | b'+' | b'-')) = bytes.get(i) {
+                i += 1;
+                let (mut value, mut flag, mut mentioned) = (0, Flag::CopyExisting, 0);
+                match bytes.get(i) {
+                    Some(b'0'..=b'7') if affected == 0 => {
+                        let end = bytes[i..]
+                            .iter()
+                            .position(|byte| !matches!(byte, b'0'..=b'7'))
+                            .map_or(bytes.len(), |end| i + end);
+                        (value, _) = parse_octal(&bytes[i..end]).ok_or_else(invalid)?;
+                        // Numeric modes are not masked by the umask, as in GNU `chmod`.
+                        (affected, mentioned) = (ALL, ALL);
+                        flag = Flag::Ordinary;
+                        i = end;
+                    }
+                    Some(b'u') => (value, i) = (0o700, i + 1),
+                    Some(b'g') => (value, i) = (0o070, i + 1),
+                    Some(b'o') => (value, i) = (0o007, i + 1),
+                    _ => {
+                        flag = Flag::Ordinary;
+                        while let Some(byte) = bytes.get(i) {
+                            match byte {
+                                b'r' => value |= 0o444,
+                                b'w' => value |= 0o222,
+                                b'x' => value |= EXECUTE,
+                                b'X' => flag = Flag::XIfAnyX,
+                                b's' => value |= SET_ID,
+                                b't' => value |= 0o1000,
+                                _ => break,
+                            }
+                            i += 1;
+                        }
+                    }
+                }
+                if mentioned == 0 {
+                    mentioned = match affected {
+                        0 => value,
+                        affected => affected & value,
+                    };
+                }
+                clauses.push(Clause {
+                    op,
+                    flag,
+                    affected,
+                    value,
+                    mentioned,
+                });
+            }
+            match bytes.get(i) {
+                None => return Ok(Self { clauses }),
+                Some(b',') => i += 1,
+                Some(_) => return Err(invalid()),
+            }
+        }
+    }
+
+    /// Returns a change that sets the bits to `value`, changing only the bits `mentioned`.
+    fn equals(value: u32, mentioned: u32) -> Self {
+        let clause = Clause {
+            op: b'=',
+            flag: Flag::Ordinary,
+            affected: ALL,
+            value,
+            mentioned,
+        };
+        Self {
+            clauses: vec![clause],
+        }
+    }
+
+    /// Returns whether applying the change depends on the umask, because a clause does not
+    /// name the classes it affects.
+    pub fn uses_umask(&self) -> bool {
+        self.clauses.iter().any(|clause| clause.affected == 0)
+    }
+
+    /// Returns the permission bits that result from applying the change to `mode`, the
+    /// bits of a directory if `is_dir` is true, with the process umask `umask`.
+    pub fn apply(&self, mode: u32, is_dir: bool, umask: u32) -> u32 {
+        let mut mode = mode & ALL;
+        for clause in &self.clauses {
+            let omit = match is_dir {
+                true => SET_ID & !clause.mentioned,
+                false => 0,
+            };
+            let mut value = clause.value;
+            match clause.flag {
+                Flag::Ordinary => {}
+                Flag::CopyExisting => {
+                    value &= mode;
+                    let spread = |bits: u32| match value & bits {
+                        0 => 0,
+                        _ => bits,
+                    };
+                    value = spread(0o444) | spread(0o222) | spread(EXECUTE);
+                }
+                Flag::XIfAnyX => {
+                    if is_dir || mode & EXECUTE != 0 {
+                        value |= EXECUTE;
+                    }
+                }
+            }
+            let affected = match clause.affected {
+                0 => !umask,
+                affected => affected,
+            };
+            value &= affected & !omit;
+            match clause.op {
+                b'=' => {
+                    let preserved = match clause.affected {
+                        0 => omit,
+                        affected => !affected | omit,
+                    };
+                    mode = (mode & preserved) | value;
+                }
+                b'+' => mode |= value,
+                _ => mode &= !value,
+            }
+        }
+        mode
+    }
+}
+
+impl FromStr for ModeChange {
+    type Err = ParseModeError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        Self::parse(mode)
+    }
+}
+
+impl From<u32> for ModeChange {
+    fn from(mode: u32) -> Self {
+        Self::equals(mode & ALL, ALL)
+    }
+}
+
+/// Parses an octal mode, and returns its bits with the bits it changes.
+fn parse_octal(digits: &[u8]) -> Option<(u32, u32)> {
+    let mut value = 0u32;
+    for digit in digits {
+        if !matches!(digit, b'0'..=b'7') {
+            return None;
+        }
+        value = value * 8 + u32::from(digit - b'0');
+        if value > ALL {
+            return None;
+        }
+    }
+    let mentioned = match digits.len() {
+        ..5 => (value & SET_ID) | 0o1000 | RWX,
+        _ => ALL,
+    };
+    Some((value, mentioned))
+}
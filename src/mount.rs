@@ -0,0 +1,140 @@
+use std::io::{Error, ErrorKind, Result};
+
+/// A mounted filesystem, as listed in `/proc/self/mountinfo` on Linux.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MountInfo {
+    /// The unique ID of the mount.
+    pub mount_id: u32,
+    /// The ID of the parent mount, or of the mount itself at the top of the tree.
+    pub parent_id: u32,
+    /// The major number of the device of the filesystem.
+    pub major: u32,
+    /// The minor number of the device of the filesystem.
+    pub minor: u32,
+    /// The directory of the filesystem that is mounted, such as the source of a bind mount.
+    pub root: String,
+    /// The directory the filesystem is mounted on.
+    pub mount_point: String,
+    /// The options of the mount, such as `rw` or `noatime`.
+    pub mount_options: Vec<String>,
+    /// The optional fields, such as `shared:1` for the propagation of the mount.
+    pub optional_fields: Vec<String>,
+    /// The type of the filesystem, such as `ext4`, `tmpfs` or `nfs`.
+    pub fs_type: String,
+    /// The source of the filesystem, such as a device path, or `none`.
+    pub source: String,
+    /// The options of the filesystem itself, shared by all of its mounts.
+    pub super_options: Vec<String>,
+}
+
+impl MountInfo {
+    /// Returns whether the mount or its filesystem is read-only.
+    pub fn is_read_only(&self) -> bool {
+        let read_only = |options: &[String]| options.iter().any(|option| option == "ro");
+        read_only(&self.mount_options) || read_only(&self.super_options)
+    }
+
+    /// Returns the mount of `mounts` that `path` is on, which must be absolute and
+    /// canonical.
+    ///
+    /// This is the mount whose mount point is the longest prefix of the path, and the last
+    /// of those, since it hides the ones mounted before it on the same directory.
+    pub fn find<'a>(mounts: &'a [Self], path: &str) -> Option<&'a Self> {
+        mounts
+            .iter()
+            .filter(|mount| mount.contains(path))
+            .max_by_key(|mount| mount.mount_point.len())
+    }
+
+    /// Returns whether `path`, which must be canonical, is on this mount or below it.
+    fn contains(&self, path: &str) -> bool {
+        match path.strip_prefix(self.mount_point.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || self.mount_point == "/",
+            None => false,
+        }
+    }
+
+    /// Parses a line of the mount table.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(' ');
+        let mount_id = fields.next()?.parse().ok()?;
+        let parent_id = fields.next()?.parse().ok()?;
+        let (major, minor) = fields.next()?.split_once(':')?;
+        let (major, minor) = (major.parse().ok()?, minor.parse().ok()?);
+        let root = unescape(fields.next()?)?;
+        let mount_point = unescape(fields.next()?)?;
+        let mount_options = options(fields.next()?)?;
+        let mut optional_fields = Vec::new();
+        loop {
+            match fields.next()? {
+                "-" => break,
+                field => optional_fields.push(unescape(field)?),
+            }
+        }
+        let fs_type = unescape(fields.next()?)?;
+        let source = unescape(fields.next()?)?;
+        let super_options = options(fields.next()?)?;
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            mount_id,
+            parent_id,
+            major,
+            minor,
+            root,
+            mount_point,
+            mount_options,
+            optional_fields,
+            fs_type,
+            source,
+            super_options,
+        })
+    }
+}
+
+/// Parses a mount table in the format of `/proc/self/mountinfo`, as described in
+/// `proc_pid_mountinfo(5)`.
+///
+/// The octal escapes that the kernel uses for spaces, tabs, newlines and backslashes in
+/// paths are decoded. A line that is not in this format is an error of kind
+/// [`ErrorKind::InvalidData`].
+pub fn parse_mountinfo(text: &str) -> Result<Vec<MountInfo>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| {
+            MountInfo::parse(line).ok_or_else(|| {
+                let message = format!("Malformed mountinfo line {}: {line:?}", index + 1);
+                Error::new(ErrorKind::InvalidData, message)
+            })
+        })
+        .collect()
+}
+
+/// Splits a comma-separated list of options.
+fn options(field: &str) -> Option<Vec<String>> {
+    field.split(',').map(unescape).collect()
+}
+
+/// Decodes the octal escapes of a field, such as `\040` for a space.
+fn unescape(field: &str) -> Option<String> {
+    if !field.contains('\\') {
+        return Some(field.to_owned());
+    }
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut rest = field.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail) {
+            (b'\\', [a @ b'0'..=b'3', b @ b'0'..=b'7', c @ b'0'..=b'7', tail @ ..]) => {
+                bytes.push((a - b'0') << 6 | (b - b'0') << 3 | (c - b'0'));
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,689 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashSet};
+use std::ffi::OsString;
+use std::io::{self, Error, ErrorKind, Result};
+use std::iter;
+use std::time::SystemTime;
+
+use crate::memory::{split, symlink_loop, MAX_SYMLINKS};
+use crate::{
+    FileSystem, FileType, MemoryFileSystem, Metadata, OpenFile, OpenOptions, RawDirEntry, ReadDir,
+    Timestamp,
+};
+
+/// How a path differs between the layers of an [OverlayFileSystem].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChangeKind {
+    /// The file of the lower layer was removed, along with its contents if it is a
+    /// directory.
+    Removed,
+    /// The file does not exist in the lower layer.
+    Added,
+    /// The file of the lower layer was modified or replaced.
+    Modified,
+}
+
+/// A difference between the layers of an [OverlayFileSystem].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Change {
+    /// The absolute path of the file.
+    pub path: String,
+    /// How the file differs.
+    pub kind: ChangeKind,
+}
+
+/// The layer of an [OverlayFileSystem] that holds a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Upper,
+    Lower,
+}
+
+/// Maps the errors of a missing file to `None`.
+fn absent<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if matches!(err.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => {
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the parent of the absolute path `path`, or `None` for the root.
+fn parent(path: &str) -> Option<&str> {
+    match path.rfind('/')? {
+        0 if path.len() > 1 => Some("/"),
+        0 => None,
+        index => Some(&path[..index]),
+    }
+}
+
+/// Returns the proper ancestors of the absolute path `path`.
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    iter::successors(parent(path), |path| parent(path))
+}
+
+/// Joins the absolute path `dir` and `name`.
+fn join(dir: &str, name: &str) -> String {
+    match dir {
+        "/" => format!("/{name}"),
+        _ => format!("{dir}/{name}"),
+    }
+}
+
+/// Converts the name of a directory entry into a string.
+fn into_name(name: OsString) -> Result<String> {
+    name.into_string().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            "Failed to convert file name to string",
+        )
+    })
+}
+
+/// Returns the names of the entries of the directory `path` of `fs`.
+fn names(fs: &dyn FileSystem, path: &str) -> Result<Vec<String>> {
+    fs.read_dir(path)?
+        .map(|entry| into_name(entry?.name))
+        .collect()
+}
+
+/// Removes `path` from `fs`, with its contents if it is a directory.
+fn remove_tree(fs: &dyn FileSystem, path: &str) -> Result<()> {
+    match fs.symlink_metadata(path)?.is_dir() {
+        true => {
+            for name in names(fs, path)? {
+                remove_tree(fs, &join(path, &name))?;
+            }
+            fs.remove_dir(path)
+        }
+        false => fs.remove_file(path),
+    }
+}
+
+/// Copies the regular file `path` from `from` to `to`, which must not have it.
+fn copy_file(
+    from: &dyn FileSystem,
+    to: &dyn FileSystem,
+    path: &str,
+    metadata: &Metadata,
+) -> Result<()> {
+    let mut source = from.open(path, OpenOptions::new().read(true))?;
+    let options = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(metadata.mode)
+        .to_owned();
+    let mut target = to.open(path, &options)?;
+    let _ = io::copy(&mut source, &mut target)?;
+    if let Some(mtime) = metadata.mtime.and_then(Timestamp::to_system_time) {
+        target.set_modified(mtime)?;
+    }
+    to.set_permissions(path, metadata.mode)
+}
+
+/// A backend that overlays a writable upper layer on a read-only lower layer.
+///
+/// Reads see the files of the upper layer, then those of the lower layer. Writes go to the
+/// upper layer, copying files up from the lower layer first, and removals of files of the
+/// lower layer are recorded as whiteouts that hide them. The lower layer is never written
+/// to, until the changes are applied to it with [`commit`](Self::commit). As with overlayfs,
+/// copying up is not denied by the permissions of the directories it copies.
+///
+/// Paths are handled as Posix paths, relative paths being relative to the current
+/// directory of the lower layer. Symlinks are resolved by the overlay, so a symlink of one
+/// layer may point to a file of the other.
+#[derive(Debug)]
+pub struct OverlayFileSystem<L, U = MemoryFileSystem> {
+    lower: L,
+    upper: U,
+    /// The paths whose files of the lower layer are removed.
+    whiteouts: RefCell<BTreeSet<String>>,
+    /// The directories of the upper layer that hide the contents of the lower layer.
+    opaque: RefCell<BTreeSet<String>>,
+}
+
+impl<L: FileSystem> OverlayFileSystem<L> {
+    /// Creates an overlay on `lower` whose upper layer is held in memory.
+    pub fn new(lower: L) -> Self {
+        Self::with_upper(lower, MemoryFileSystem::new())
+    }
+}
+
+impl<L: FileSystem, U: FileSystem> OverlayFileSystem<L, U> {
+    /// Creates an overlay of `upper` on `lower`, where `upper` starts out empty.
+    pub fn with_upper(lower: L, upper: U) -> Self {
+        Self {
+            lower,
+            upper,
+            whiteouts: RefCell::default(),
+            opaque: RefCell::default(),
+        }
+    }
+
+    /// Returns the lower layer.
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// Returns the upper layer.
+    pub fn upper(&self) -> &U {
+        &self.upper
+    }
+
+    /// Returns how the overlay differs from the lower layer, sorted by path.
+    ///
+    /// Files copied up to the upper layer are reported as modified, even if they were only
+    /// opened for writing. A directory whose contents were replaced as a whole is reported
+    /// as removed and then added.
+    pub fn changes(&self) -> Result<Vec<Change>> {
+        let mut changes = self
+            .whiteouts
+            .borrow()
+            .iter()
+            .chain(self.opaque.borrow().iter())
+            .map(|path| Change {
+                path: path.clone(),
+                kind: ChangeKind::Removed,
+            })
+            .collect::<Vec<_>>();
+        self.diff_dir("/", &mut changes)?;
+        changes.sort();
+        Ok(changes)
+    }
+
+    /// Applies the changes to the lower layer, and empties the upper layer.
+    ///
+    /// The changes are applied in order, so a failure leaves the lower layer partially
+    /// updated and the overlay unchanged.
+    pub fn commit(&self) -> Result<()> {
+        let removed = self
+            .whiteouts
+            .borrow()
+            .union(&self.opaque.borrow())
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in removed {
+            if absent(self.lower.symlink_metadata(&path))?.is_some() {
+                remove_tree(&self.lower, &path)?;
+            }
+        }
+        self.commit_dir("/")?;
+        for name in names(&self.upper, "/")? {
+            remove_tree(&self.upper, &join("/", &name))?;
+        }
+        self.whiteouts.borrow_mut().clear();
+        self.opaque.borrow_mut().clear();
+        Ok(())
+    }
+
+    /// Records the changes below the directory `dir` of the upper layer.
+    fn diff_dir(&self, dir: &str, changes: &mut Vec<Change>) -> Result<()> {
+        for name in names(&self.upper, dir)? {
+            let path = join(dir, &name);
+            let upper = self.upper.symlink_metadata(&path)?;
+            let lower = match self.lower_hidden(&path) || self.opaque.borrow().contains(&path) {
+                true => None,
+                false => absent(self.lower.symlink_metadata(&path))?,
+            };
+            let kind = match lower {
+                None => Some(ChangeKind::Added),
+                Some(lower) if lower.is_dir() && upper.is_dir() => {
+                    (lower.mode != upper.mode).then_some(ChangeKind::Modified)
+                }
+                Some(_) => Some(ChangeKind::Modified),
+            };
+            if let Some(kind) = kind {
+                changes.push(Change {
+                    path: path.clone(),
+                    kind,
+                });
+            }
+            if upper.is_dir() {
+                self.diff_dir(&path, changes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the contents of the directory `dir` of the upper layer to the lower layer.
+    fn commit_dir(&self, dir: &str) -> Result<()> {
+        for name in names(&self.upper, dir)? {
+            let path = join(dir, &name);
+            let metadata = self.upper.symlink_metadata(&path)?;
+            let mut lower = absent(self.lower.symlink_metadata(&path))?;
+            if lower.is_some_and(|lower| !(lower.is_dir() && metadata.is_dir())) {
+                remove_tree(&self.lower, &path)?;
+                lower = None;
+            }
+            match metadata.file_type {
+                FileType::Dir => {
+                    if lower.is_none() {
+                        self.lower.create_dir(&path, 0o700)?;
+                    }
+                    self.commit_dir(&path)?;
+                    if lower.map(|lower| lower.mode) != Some(metadata.mode) {
+                        self.lower.set_permissions(&path, metadata.mode)?;
+                    }
+                }
+                FileType::Symlink => self.lower.symlink(&self.upper.read_link(&path)?, &path)?,
+                _ => copy_file(&self.upper, &self.lower, &path, &metadata)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the layer with this name.
+    fn layer(&self, layer: Layer) -> &dyn FileSystem {
+        match layer {
+            Layer::Upper => &self.upper,
+            Layer::Lower => &self.lower,
+        }
+    }
+
+    /// Returns whether the file of the lower layer at the absolute path `path` is hidden.
+    fn lower_hidden(&self, path: &str) -> bool {
+        let whiteouts = self.whiteouts.borrow();
+        let opaque = self.opaque.borrow();
+        whiteouts.contains(path)
+            || ancestors(path).any(|dir| whiteouts.contains(dir) || opaque.contains(dir))
+    }
+
+    /// Returns the layer and the metadata of the file at `path`, which has no symlinks in
+    /// its ancestors.
+    fn lookup(&self, path: &str) -> Result<Option<(Layer, Metadata)>> {
+        if let Some(metadata) = absent(self.upper.symlink_metadata(path))? {
+            return Ok(Some((Layer::Upper, metadata)));
+        }
+        if self.lower_hidden(path) {
+            return Ok(None);
+        }
+        Ok(absent(self.lower.symlink_metadata(path))?.map(|metadata| (Layer::Lower, metadata)))
+    }
+
+    /// Returns the absolute path of `path` without symlinks in its ancestors, nor in its
+    /// last component if `follow` is true. The last component need not exist.
+    fn resolve(&self, path: &str, follow: bool) -> Result<String> {
+        if path.is_empty() {
+            return Err(ErrorKind::NotFound.into());
+        }
+        let mut components = split(path);
+        let mut resolved = match path.starts_with('/') {
+            true => Vec::new(),
+            false => Vec::from(split(&self.lower.current_dir()?)),
+        };
+        let mut symlinks = 0;
+        while let Some(name) = components.pop_front() {
+            match name.as_str() {
+                "." => continue,
+                ".." => {
+                    let _ = resolved.pop();
+                    continue;
+                }
+                _ => resolved.push(name),
+            }
+            let last = components.is_empty();
+            let current = format!("/{}", resolved.join("/"));
+            match self.lookup(&current)? {
+                None if last => break,
+                None => return Err(ErrorKind::NotFound.into()),
+                Some((layer, metadata)) if metadata.is_symlink() && (follow || !last) => {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(symlink_loop());
+                    }
+                    let target = self.layer(layer).read_link(&current)?;
+                    let _ = resolved.pop();
+                    if target.starts_with('/') {
+                        resolved.clear();
+                    }
+                    let mut target_components = split(&target);
+                    target_components.extend(components);
+                    components = target_components;
+                }
+                Some((_, metadata)) if !last && !metadata.is_dir() => {
+                    return Err(ErrorKind::NotADirectory.into());
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(format!("/{}", resolved.join("/")))
+    }
+
+    /// Resolves `path` to an existing file.
+    fn resolve_existing(&self, path: &str, follow: bool) -> Result<(String, Layer, Metadata)> {
+        let path = self.resolve(path, follow)?;
+        let (layer, metadata) = self
+            .lookup(&path)?
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+        Ok((path, layer, metadata))
+    }
+
+    /// Resolves `path` to a file that does not exist yet, and prepares its parent.
+    fn resolve_new(&self, path: &str) -> Result<String> {
+        let path = self.resolve(path, false)?;
+        if self.lookup(&path)?.is_some() {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        self.copy_up_parent(&path)?;
+        Ok(path)
+    }
+
+    /// Copies the file at `path` to the upper layer, along with its ancestors, unless the
+    /// upper layer has it.
+    fn copy_up(&self, path: &str) -> Result<()> {
+        if absent(self.upper.symlink_metadata(path))?.is_some() {
+            return Ok(());
+        }
+        self.copy_up_parent(path)?;
+        let metadata = self.lower.symlink_metadata(path)?;
+        self.with_writable_parent(path, || match metadata.file_type {
+            FileType::Dir => self.upper.create_dir(path, 0o700),
+            FileType::Symlink => self.upper.symlink(&self.lower.read_link(path)?, path),
+            _ => copy_file(&self.lower, &self.upper, path, &metadata),
+        })?;
+        self.copy_up_xattrs(path)?;
+        match metadata.is_dir() {
+            true => self.upper.set_permissions(path, metadata.mode),
+            false => Ok(()),
+        }
+    }
+
+    /// Runs `f` with the parent of `path` in the upper layer writable and searchable by its
+    /// owner, since copying up is not subject to the permissions of the copied directories.
+    fn with_writable_parent(&self, path: &str, f: impl FnOnce() -> Result<()>) -> Result<()> {
+        let Some(parent) = parent(path) else {
+            return f();
+        };
+        let mode = self.upper.metadata(parent)?.mode;
+        if mode & 0o300 == 0o300 {
+            return f();
+        }
+        self.upper.set_permissions(parent, mode | 0o300)?;
+        let result = f();
+        self.upper.set_permissions(parent, mode)?;
+        result
+    }
+
+    /// Copies the extended attributes of the file at `path` to the upper layer, unless
+    /// either layer does not support them.
+    fn copy_up_xattrs(&self, path: &str) -> Result<()> {
+        let names = match self.lower.list_xattrs(path, false) {
+            Err(err) if err.kind() == ErrorKind::Unsupported => return Ok(()),
+            names => names?,
+        };
+        for name in names {
+            if let Some(value) = self.lower.get_xattr(path, &name, false)? {
+                match self.upper.set_xattr(path, &name, &value, false) {
+                    Err(err) if err.kind() == ErrorKind::Unsupported => return Ok(()),
+                    result => result?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the parent of `path` to the upper layer.
+    fn copy_up_parent(&self, path: &str) -> Result<()> {
+        match parent(path) {
+            Some(parent) => self.copy_up(parent),
+            None => Ok(()),
+        }
+    }
+
+    /// Copies the file at `path` to the upper layer, with all of its contents if it is a
+    /// directory.
+    fn copy_up_tree(&self, path: &str) -> Result<()> {
+        self.copy_up(path)?;
+        if self.upper.symlink_metadata(path)?.is_dir() {
+            for entry in self.read_dir(path)? {
+                self.copy_up_tree(&join(path, &into_name(entry?.name)?))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Hides the file of the lower layer at `path`, if there is one.
+    fn whiteout(&self, path: &str) -> Result<()> {
+        if !self.lower_hidden(path) && absent(self.lower.symlink_metadata(path))?.is_some() {
+            let _ = self.whiteouts.borrow_mut().insert(path.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Forgets the whiteouts and opaque directories below `path`, and `path` itself.
+    fn forget(&self, path: &str) {
+        let prefix = join(path, "");
+        let keep = |other: &String| other != path && !other.starts_with(&prefix);
+        self.whiteouts.borrow_mut().retain(keep);
+        self.opaque.borrow_mut().retain(keep);
+    }
+
+    /// Marks `path` as created in the upper layer, making it opaque if it replaces a
+    /// removed directory of the lower layer.
+    fn created(&self, path: &str, is_dir: bool) {
+        let removed = self.whiteouts.borrow_mut().remove(path);
+        if removed && is_dir {
+            let _ = self.opaque.borrow_mut().insert(path.to_owned());
+        }
+    }
+}
+
+impl<L: FileSystem, U: FileSystem> FileSystem for OverlayFileSystem<L, U> {
+    fn current_dir(&self) -> Result<String> {
+        self.lower.current_dir()
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        Ok(self.resolve_existing(path, true)?.2)
+    }
+
+    fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+        Ok(self.resolve_existing(path, false)?.2)
+    }
+
+    fn canonicalize(&self, path: &str) -> Result<String> {
+        Ok(self.resolve_existing(path, true)?.0)
+    }
+
+    fn read_dir(&self, path: &str) -> Result<ReadDir> {
+        let (path, layer, metadata) = self.resolve_existing(path, true)?;
+        if !metadata.is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        let mut entries = Vec::new();
+        let mut names = HashSet::new();
+        if layer == Layer::Upper {
+            for entry in self.upper.read_dir(&path)? {
+                let entry = entry?;
+                let _ = names.insert(entry.name.clone());
+                entries.push(entry);
+            }
+        }
+        let merged = !self.lower_hidden(&path) && !self.opaque.borrow().contains(&path);
+        if merged && absent(self.lower.symlink_metadata(&path))?.is_some_and(|m| m.is_dir()) {
+            let whiteouts = self.whiteouts.borrow();
+            for entry in self.lower.read_dir(&path)? {
+                let entry: RawDirEntry = entry?;
+                let removed = entry
+                    .name
+                    .to_str()
+                    .is_some_and(|name| whiteouts.contains(&join(&path, name)));
+                if !removed && !names.contains(&entry.name) {
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(Box::new(entries.into_iter().map(Ok)))
+    }
+
+    fn read_link(&self, path: &str) -> Result<String> {
+        let (path, layer, _) = self.resolve_existing(path, false)?;
+        self.layer(layer).read_link(&path)
+    }
+
+    fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        let path = self.resolve(path, true)?;
+        match self.lookup(&path)? {
+            Some(_) if options.create_new => return Err(ErrorKind::AlreadyExists.into()),
+            Some((layer, _)) if !options.writes() => return self.layer(layer).open(&path, options),
+            Some((_, metadata)) if metadata.is_dir() => return Err(ErrorKind::IsADirectory.into()),
+            Some(_) => self.copy_up(&path)?,
+            None if options.create || options.create_new => {
+                self.copy_up_parent(&path)?;
+                self.created(&path, false);
+            }
+            None => return Err(ErrorKind::NotFound.into()),
+        }
+        self.upper.open(&path, options)
+    }
+
+    fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+        let path = self.resolve_new(path)?;
+        self.upper.create_dir(&path, mode)?;
+        self.created(&path, true);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &str) -> Result<()> {
+        let (path, layer, metadata) = self.resolve_existing(path, false)?;
+        if metadata.is_dir() {
+            return Err(ErrorKind::IsADirectory.into());
+        }
+        if layer == Layer::Upper {
+            self.upper.remove_file(&path)?;
+        }
+        self.whiteout(&path)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<()> {
+        let (path, layer, metadata) = self.resolve_existing(path, false)?;
+        if !metadata.is_dir() {
+            return Err(ErrorKind::NotADirectory.into());
+        }
+        if path == "/" {
+            return Err(ErrorKind::ResourceBusy.into());
+        }
+        if self.read_dir(&path)?.next().is_some() {
+            return Err(ErrorKind::DirectoryNotEmpty.into());
+        }
+        if layer == Layer::Upper {
+            self.upper.remove_dir(&path)?;
+        }
+        self.forget(&path);
+        self.whiteout(&path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let (from, _, metadata) = self.resolve_existing(from, false)?;
+        let to = self.resolve(to, false)?;
+        if from == to {
+            return Ok(());
+        }
+        if from == "/" || to == "/" {
+            return Err(ErrorKind::ResourceBusy.into());
+        }
+        if let Some((_, target)) = self.lookup(&to)? {
+            match (metadata.is_dir(), target.is_dir()) {
+                (true, true) if self.read_dir(&to)?.next().is_some() => {
+                    return Err(ErrorKind::DirectoryNotEmpty.into());
+                }
+                (true, false) => return Err(ErrorKind::NotADirectory.into()),
+                (false, true) => return Err(ErrorKind::IsADirectory.into()),
+                _ => {}
+            }
+        }
+        if metadata.is_dir() && to.starts_with(&join(&from, "")) {
+            return Err(ErrorKind::InvalidInput.into());
+        }
+        self.copy_up_tree(&from)?;
+        self.copy_up_parent(&to)?;
+        self.upper.rename(&from, &to)?;
+        self.forget(&from);
+        self.whiteout(&from)?;
+        self.forget(&to);
+        if metadata.is_dir() && !self.lower_hidden(&to) {
+            // The contents were copied up, so those of the lower layer are hidden.
+            if absent(self.lower.symlink_metadata(&to))?.is_some() {
+                let _ = self.opaque.borrow_mut().insert(to);
+            }
+        }
+        Ok(())
+    }
+
+    fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        let link = self.resolve_new(link)?;
+        self.upper.symlink(target, &link)?;
+        self.created(&link, false);
+        Ok(())
+    }
+
+    fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+        let (original, _, metadata) = self.resolve_existing(original, false)?;
+        if metadata.is_dir() {
+            return Err(ErrorKind::PermissionDenied.into());
+        }
+        let link = self.resolve_new(link)?;
+        self.copy_up(&original)?;
+        self.upper.hard_link(&original, &link)?;
+        self.created(&link, false);
+        Ok(())
+    }
+
+    fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+        let (path, _, _) = self.resolve_existing(path, true)?;
+        self.copy_up(&path)?;
+        self.upper.set_permissions(&path, mode)
+    }
+
+    fn set_owner(
+        &self,
+        path: &str,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        let (path, _, _) = self.resolve_existing(path, follow)?;
+        self.copy_up(&path)?;
+        self.upper.set_owner(&path, uid, gid, false)
+    }
+
+    fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow: bool,
+    ) -> Result<()> {
+        let (path, _, _) = self.resolve_existing(path, follow)?;
+        self.copy_up(&path)?;
+        self.upper.set_times(&path, atime, mtime, false)
+    }
+
+    fn umask(&self) -> Result<u32> {
+        self.upper.umask()
+    }
+
+    fn list_xattrs(&self, path: &str, follow: bool) -> Result<Vec<String>> {
+        let (path, layer, _) = self.resolve_existing(path, follow)?;
+        self.layer(layer).list_xattrs(&path, false)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str, follow: bool) -> Result<Option<Vec<u8>>> {
+        let (path, layer, _) = self.resolve_existing(path, follow)?;
+        self.layer(layer).get_xattr(&path, name, false)
+    }
+
+    fn set_xattr(&self, path: &str, name: &str, value: &[u8], follow: bool) -> Result<()> {
+        let (path, _, _) = self.resolve_existing(path, follow)?;
+        self.copy_up(&path)?;
+        self.upper.set_xattr(&path, name, value, false)
+    }
+
+    fn remove_xattr(&self, path: &str, name: &str, follow: bool) -> Result<()> {
+        let (path, _, _) = self.resolve_existing(path, follow)?;
+        self.copy_up(&path)?;
+        self.upper.remove_xattr(&path, name, false)
+    }
+}
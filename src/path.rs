@@ -1,14 +1,92 @@
-use std::fs::{Metadata, ReadDir};
-use std::io::Result;
-use std::path::Path as StdPath;
+use std::convert::Infallible;
+#[cfg(windows)]
+use std::io::Error;
+use std::io::{ErrorKind, Read, Result, Write};
+use std::path::{Component as StdComponent, Path as StdPath, PathBuf};
+use std::time::SystemTime;
 
-use crate::PurePath;
+use crate::error::at;
+use crate::{
+    copy, fs, mount, sys, usage, CopyOptions, DiskUsage, ExpandUserError, ExpandVarsError, Glob,
+    IterDir, Metadata, ModeChange, MountInfo, OpenFile, OpenOptions, PurePath, ReadDir, ScanDir,
+    SizeOptions, TextOptions, TreeSize, UndefinedVarError, UnknownOwnerError, UnknownUserError,
+    VarOptions, Walk,
+};
+
+/// Converts a path returned by [std] back into a path of this crate.
+fn from_std_path<P: for<'a> From<&'a str>>(path: &StdPath) -> Result<P> {
+    Ok(P::from(path.to_str().ok_or_else(|| {
+        std::io::Error::new(ErrorKind::InvalidData, "Failed to convert path to string")
+    })?))
+}
+
+/// Returns the umask of the current backend, if applying `change` depends on it.
+fn umask_for(change: &ModeChange, path: &str) -> Result<u32> {
+    match change.uses_umask() {
+        true => fs::current().umask().map_err(at(path)),
+        false => Ok(0),
+    }
+}
+
+/// Returns the IDs of `user` and `group`, which are names or numeric IDs.
+fn owner_ids(user: Option<&str>, group: Option<&str>) -> Result<(Option<u32>, Option<u32>)> {
+    let uid = user
+        .map(|user| {
+            sys::user_id(user)
+                .or_else(|| user.parse().ok())
+                .ok_or_else(|| UnknownOwnerError::user(user))
+        })
+        .transpose()?;
+    let gid = group
+        .map(|group| {
+            sys::group_id(group)
+                .or_else(|| group.parse().ok())
+                .ok_or_else(|| UnknownOwnerError::group(group))
+        })
+        .transpose()?;
+    Ok((uid, gid))
+}
 
 /// A path trait.
+///
+/// Operations run against the [FileSystem](crate::FileSystem) of the current thread,
+/// which is the host filesystem unless it is replaced with [`with_file_system`](crate::with_file_system).
 pub trait Path: PurePath {
     /// Returns the canonical path.
     fn canonicalize(&self) -> Result<Self>;
 
+    /// Returns the home directory of the current user.
+    ///
+    /// On Unix, this is `$HOME`, falling back to the passwd database.
+    /// On Windows, this is `%USERPROFILE%`.
+    fn home() -> Result<Self>;
+
+    /// Replaces a leading `~` or `~user` with the home directory of the user.
+    ///
+    /// The home directories of other users are looked up in the passwd database on Unix.
+    /// A user without one is reported as an [UnknownUserError].
+    /// Use [`PurePath::expanduser_with`] to look them up in some other way.
+    fn expanduser(&self) -> Result<Self>;
+
+    /// Expands the environment variables in the path, according to `options`.
+    ///
+    /// Variables are looked up with [`std::env::var`], and those that are not valid UTF-8
+    /// are treated as undefined. An undefined variable in a strict expansion is reported as
+    /// an [UndefinedVarError].
+    fn expand_env_vars(&self, options: &VarOptions) -> Result<Self>;
+
+    /// Returns the absolute path with all symlinks resolved.
+    ///
+    /// If `strict` is true, this is the same as [`canonicalize`](Path::canonicalize).
+    /// Otherwise, symlinks are resolved for the longest prefix of the path that exists,
+    /// and the rest is appended lexically, with `..` removing the preceding component.
+    fn resolve(&self, strict: bool) -> Result<Self>;
+
+    /// Returns the path prefixed with the current directory, if it is relative.
+    ///
+    /// Unlike [`resolve`](Path::resolve), this neither resolves symlinks nor removes `..`.
+    fn absolute(&self) -> Result<Self>;
+
     // /// Returns whether the path exists.
     // fn exists(&self) -> bool;
 
@@ -32,72 +110,694 @@ pub trait Path: PurePath {
     /// Returns whether the path is a symlink.
     fn is_symlink(&self) -> bool {
         self.symlink_metadata()
-            .map(|metadata| metadata.file_type().is_symlink())
+            .map(|metadata| metadata.is_symlink())
+            .unwrap_or(false)
+    }
+
+    /// Returns whether the path is a directory junction, which is always false off Windows.
+    fn is_junction(&self) -> bool {
+        self.symlink_metadata()
+            .map(|metadata| metadata.is_junction())
+            .unwrap_or(false)
+    }
+
+    /// Returns whether the path is a named pipe.
+    fn is_fifo(&self) -> bool {
+        self.metadata()
+            .map(|metadata| metadata.is_fifo())
             .unwrap_or(false)
     }
 
+    /// Returns whether the path is a Unix domain socket.
+    fn is_socket(&self) -> bool {
+        self.metadata()
+            .map(|metadata| metadata.is_socket())
+            .unwrap_or(false)
+    }
+
+    /// Returns whether the path is a block device.
+    fn is_block_device(&self) -> bool {
+        self.metadata()
+            .map(|metadata| metadata.is_block_device())
+            .unwrap_or(false)
+    }
+
+    /// Returns whether the path is a character device.
+    fn is_char_device(&self) -> bool {
+        self.metadata()
+            .map(|metadata| metadata.is_char_device())
+            .unwrap_or(false)
+    }
+
+    /// Returns whether the path is a mount point, where another filesystem is mounted.
+    ///
+    /// This is the case if its parent is on another device, or is the same directory, as
+    /// for the root. Symlinks are never mount points, and neither are files of filesystems
+    /// that do not report inode numbers, such as the host filesystem on Windows.
+    fn is_mount(&self) -> bool;
+
+    /// Returns whether this path and `other` refer to the same file, following symlinks.
+    ///
+    /// Files are compared by their device and inode numbers, or by their canonical paths
+    /// if the filesystem does not report inode numbers.
+    fn samefile(&self, other: &Self) -> Result<bool>;
+
+    /// Returns the mount that the file at this path is on, following symlinks.
+    ///
+    /// The path is canonicalized by the current [FileSystem](crate::FileSystem), and looked
+    /// up in the mount table of the host, which is parsed with [`parse_mountinfo`]. This is
+    /// only supported on Linux, and fails with [`ErrorKind::Unsupported`] elsewhere.
+    ///
+    /// [`parse_mountinfo`]: crate::parse_mountinfo
+    fn mount_info(&self) -> Result<MountInfo>;
+
+    /// Returns the total, used and free space of the filesystem that holds this path.
+    ///
+    /// On the host filesystem, this is reported by `statvfs(3)` on Unix, and fails with
+    /// [`ErrorKind::Unsupported`] elsewhere.
+    fn disk_usage(&self) -> Result<DiskUsage>;
+
+    /// Returns the size of the directory tree rooted at this path, as `du` does.
+    ///
+    /// Directories count with their own size, and symlinks are not followed. Files with
+    /// several hard links in the tree are counted once. See [SizeOptions] for the other
+    /// options. The first error is returned, such as for a file that is removed during the
+    /// walk.
+    fn tree_size(&self, options: &SizeOptions) -> Result<TreeSize<Self>>
+    where
+        Self: Clone;
+
     /// Returns the metadata.
     fn metadata(&self) -> Result<Metadata>;
 
     /// Returns the directory entries.
     fn read_dir(&self) -> Result<ReadDir>;
 
+    /// Returns an iterator over the paths in this directory.
+    ///
+    /// Entries whose names are not valid UTF-8 are reported as errors by default,
+    /// see [`IterDir::non_utf8`].
+    fn iterdir(&self) -> Result<IterDir<Self>> {
+        self.scandir().map(IterDir::new)
+    }
+
+    /// Returns an iterator over the entries of this directory.
+    ///
+    /// Entries whose names are not valid UTF-8 are reported as errors by default,
+    /// see [`ScanDir::non_utf8`].
+    fn scandir(&self) -> Result<ScanDir<Self>>;
+
+    /// Returns a recursive walk over the directory tree rooted at this path.
+    ///
+    /// See [Walk] for the available options.
+    fn walk(&self) -> Walk<Self>
+    where
+        Self: Clone,
+    {
+        Walk::new(self.clone())
+    }
+
+    /// Returns an iterator over the paths below this one that match the relative `pattern`.
+    ///
+    /// See [Glob] for the pattern syntax and the available options.
+    fn glob(&self, pattern: &str) -> Result<Glob<Self>>;
+
+    /// Returns an iterator over the paths at any depth below this one that match the relative
+    /// `pattern`, as if it was prefixed with `**`.
+    fn rglob(&self, pattern: &str) -> Result<Glob<Self>>;
+
     /// Reads the symlink.
     fn read_link(&self) -> Result<Self>;
 
-    // /// Reads the file.
-    // fn read_file(&self) -> Vec<u8>;
-
     /// Returns the symlink metadata.
     fn symlink_metadata(&self) -> Result<Metadata>;
 
-    // /// Writes the file.
-    // fn write_file(&self, data: &[u8]);
+    /// Opens the file at this path with the given options.
+    fn open(&self, options: &OpenOptions) -> Result<Box<dyn OpenFile>>;
+
+    /// Reads the whole file as bytes.
+    fn read_bytes(&self) -> Result<Vec<u8>>;
+
+    /// Reads the whole file as UTF-8 text.
+    fn read_text(&self) -> Result<String> {
+        self.read_text_with(&TextOptions::default())
+    }
+
+    /// Reads the whole file as text, decoded according to `options`.
+    fn read_text_with(&self, options: &TextOptions) -> Result<String>;
+
+    /// Writes `data` to the file, replacing its contents.
+    ///
+    /// The file is created if it does not exist.
+    fn write_bytes(&self, data: &[u8]) -> Result<()>;
+
+    /// Writes `text` to the file as UTF-8, replacing its contents.
+    ///
+    /// The file is created if it does not exist.
+    fn write_text(&self, text: &str) -> Result<()> {
+        self.write_bytes(text.as_bytes())
+    }
+
+    /// Appends `data` to the file.
+    ///
+    /// The file is created if it does not exist.
+    fn append(&self, data: &[u8]) -> Result<()>;
+
+    /// Creates a directory at this path.
+    ///
+    /// `mode` is combined with the process umask on Unix, and ignored elsewhere.
+    /// If `parents` is true, missing parents are created as needed, with mode `0o777`
+    /// combined with the umask rather than `mode`.
+    /// If `exist_ok` is true, an existing directory at this path is not an error.
+    fn mkdir(&self, mode: u32, parents: bool, exist_ok: bool) -> Result<()>;
+
+    /// Creates a file at this path, or updates its modification time if it exists.
+    ///
+    /// `mode` is combined with the process umask on Unix, and ignored elsewhere.
+    /// If `exist_ok` is false, an existing file at this path is an error.
+    fn touch(&self, mode: u32, exist_ok: bool) -> Result<()>;
+
+    /// Removes the file or symlink at this path.
+    ///
+    /// If `missing_ok` is true, a missing file is not an error.
+    fn unlink(&self, missing_ok: bool) -> Result<()>;
+
+    /// Removes the empty directory at this path.
+    fn rmdir(&self) -> Result<()>;
+
+    /// Renames this path to `target`, and returns `target`.
+    ///
+    /// An existing file at `target` is replaced on Unix, while it is an error on Windows.
+    /// Use [`replace`](Path::replace) to replace it on every system.
+    fn rename(&self, target: &Self) -> Result<Self>;
+
+    /// Renames this path to `target`, replacing any existing file there, and returns `target`.
+    fn replace(&self, target: &Self) -> Result<Self>;
+
+    /// Makes this path a symlink pointing to `target`.
+    ///
+    /// On Windows, a directory symlink is created if `target` is a directory.
+    fn symlink_to(&self, target: &Self) -> Result<()>;
+
+    /// Makes this path a hard link to the same file as `target`.
+    fn hardlink_to(&self, target: &Self) -> Result<()>;
+
+    /// Changes the permissions of the file at this path.
+    ///
+    /// On Unix, `mode` holds the permission bits, as for `chmod(2)`.
+    /// Elsewhere, the file is made read-only if `mode` has no write bit set.
+    fn chmod(&self, mode: u32) -> Result<()>;
+
+    /// Changes the permissions of the file at this path as `chmod(1)` does, relative to its
+    /// current permissions.
+    ///
+    /// `mode` is parsed as a [ModeChange], such as `u+x`, `go-w`, `a=r,u+w` or `755`. The
+    /// umask of the [FileSystem](crate::FileSystem) is only needed for clauses that do not
+    /// name the classes they affect, such as `+x`.
+    fn chmod_symbolic(&self, mode: &str) -> Result<()>;
+
+    /// Creates a directory at this path with the permissions of `mkdir -m`, which are not
+    /// masked by the umask.
+    ///
+    /// The permissions are those that result from applying `mode` to `0o777`. Missing
+    /// parents are created as by [`mkdir`](Path::mkdir) with mode `0o777`, and an existing
+    /// directory keeps its permissions.
+    fn mkdir_with(&self, mode: &ModeChange, parents: bool, exist_ok: bool) -> Result<()>;
+
+    /// Creates a file at this path with the permissions that result from applying `mode` to
+    /// `0o666`, which are not masked by the umask, or updates its modification time if it
+    /// exists.
+    ///
+    /// An existing file keeps its permissions. If `exist_ok` is false, it is an error.
+    fn touch_with(&self, mode: &ModeChange, exist_ok: bool) -> Result<()>;
+
+    /// Changes the owner and group of the file at this path, leaving those that are `None`.
+    ///
+    /// `user` and `group` are names, looked up in the passwd and group databases, or
+    /// numeric IDs, as for `chown(1)`. A name that is neither is reported as an
+    /// [UnknownOwnerError].
+    fn chown(&self, user: Option<&str>, group: Option<&str>) -> Result<()>;
+
+    /// Changes the owner and group as [`chown`](Path::chown) does, but of a symlink at this
+    /// path rather than its target.
+    fn lchown(&self, user: Option<&str>, group: Option<&str>) -> Result<()>;
+
+    /// Sets the access and modification times of the file at this path, with nanosecond
+    /// precision, leaving those that are `None`.
+    ///
+    /// If `follow_symlinks` is false, the times of a symlink at this path are set rather
+    /// than those of its target, which is only supported on Unix for the host filesystem.
+    fn set_times(
+        &self,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow_symlinks: bool,
+    ) -> Result<()>;
+
+    /// Copies the file at this path to `dest`, or into it if it is a directory, and returns
+    /// the number of bytes copied.
+    ///
+    /// This is `shutil.copy` with the options of [CopyOptions], which also say what is
+    /// preserved. The contents are copied by [`FileSystem::copy_file`], which clones the
+    /// file with `FICLONE` on Linux if the filesystem supports reflinks, or else copies it
+    /// in the kernel with `copy_file_range(2)`, before falling back to reading and writing.
+    ///
+    /// [`FileSystem::copy_file`]: crate::FileSystem::copy_file
+    fn copy_to(&self, dest: &Self, options: &mut CopyOptions) -> Result<u64>
+    where
+        Self: Clone;
+
+    /// Copies the directory tree at this path to `dest`, and returns the number of bytes
+    /// copied.
+    ///
+    /// This is `shutil.copytree` with the options of [CopyOptions]. Files are copied as by
+    /// [`copy_to`](Path::copy_to), and the metadata of each directory is preserved once its
+    /// contents are copied. The first error stops the copy.
+    fn copy_tree(&self, dest: &Self, options: &mut CopyOptions) -> Result<u64>
+    where
+        Self: Clone;
+
+    /// Returns the names of the extended attributes of the file at this path, such as
+    /// `user.comment` or `security.capability`.
+    ///
+    /// If `follow_symlinks` is false, the attributes of a symlink at this path are listed
+    /// rather than those of its target. A filesystem that does not support extended
+    /// attributes, or a host other than Linux, is reported as an
+    /// [UnsupportedXattrError](crate::UnsupportedXattrError).
+    fn xattrs(&self, follow_symlinks: bool) -> Result<Vec<String>>;
+
+    /// Returns the value of the extended attribute `name` of the file at this path, or
+    /// `None` if it is not set.
+    ///
+    /// Symlinks and unsupported attributes are handled as by [`xattrs`](Path::xattrs).
+    fn get_xattr(&self, name: &str, follow_symlinks: bool) -> Result<Option<Vec<u8>>>;
+
+    /// Sets the extended attribute `name` of the file at this path to `value`.
+    ///
+    /// Symlinks and unsupported attributes are handled as by [`xattrs`](Path::xattrs).
+    fn set_xattr(&self, name: &str, value: &[u8], follow_symlinks: bool) -> Result<()>;
+
+    /// Removes the extended attribute `name` of the file at this path, which is an error of
+    /// kind [`ErrorKind::NotFound`] if it is not set.
+    ///
+    /// Symlinks and unsupported attributes are handled as by [`xattrs`](Path::xattrs).
+    fn remove_xattr(&self, name: &str, follow_symlinks: bool) -> Result<()>;
+
+    /// Returns the name of the owner of the file at this path, from the passwd database.
+    ///
+    /// An owner that is not in the database is reported as an [UnknownOwnerError].
+    fn owner(&self) -> Result<String> {
+        let uid = self.metadata()?.uid;
+        sys::user_name(uid).ok_or_else(|| UnknownOwnerError::user(uid.to_string()).into())
+    }
+
+    /// Returns the name of the group of the file at this path, from the group database.
+    ///
+    /// A group that is not in the database is reported as an [UnknownOwnerError].
+    fn group(&self) -> Result<String> {
+        let gid = self.metadata()?.gid;
+        sys::group_name(gid).ok_or_else(|| UnknownOwnerError::group(gid.to_string()).into())
+    }
 }
 
-impl<P: PurePath + AsRef<str> + for<'a> From<&'a str>> Path for P
+impl<P: PurePath<Error = Infallible> + AsRef<str> + for<'a> From<&'a str>> Path for P
 where
     Self: Sized,
 {
     fn canonicalize(&self) -> Result<Self> {
-        let std_path = StdPath::new(self.as_ref());
-        let canonical_path = std_path.canonicalize()?;
-        Ok(Self::from(canonical_path.to_str().ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to convert path to string",
-            )
-        })?))
+        let canonical_path = fs::current()
+            .canonicalize(self.as_ref())
+            .map_err(at(self.as_ref()))?;
+        Ok(Self::from(canonical_path.as_str()))
+    }
+
+    fn home() -> Result<Self> {
+        match sys::home_dir(None) {
+            Some(home) => Ok(Self::from(home.as_str())),
+            None => Err(UnknownUserError::new(None).into()),
+        }
+    }
+
+    fn expanduser(&self) -> Result<Self> {
+        let mut unknown = None;
+        let expanded = self.expanduser_with(|user| {
+            unknown = user.map(str::to_owned);
+            sys::home_dir(user)
+        });
+        match expanded {
+            Ok(path) => Ok(path),
+            Err(ExpandUserError::UnknownUser) => Err(UnknownUserError::new(unknown).into()),
+            Err(ExpandUserError::Capacity(never)) => match never {},
+        }
+    }
+
+    fn expand_env_vars(&self, options: &VarOptions) -> Result<Self> {
+        match self.expand_vars_with(options, |name| std::env::var(name).ok()) {
+            Ok(path) => Ok(path),
+            Err(ExpandVarsError::Undefined(name)) => Err(UndefinedVarError::new(name).into()),
+            Err(ExpandVarsError::Capacity(never)) => match never {},
+        }
+    }
+
+    fn resolve(&self, strict: bool) -> Result<Self> {
+        if strict {
+            return self.canonicalize();
+        }
+        let fs = fs::current();
+        let absolute = self.absolute()?;
+        let mut existing = StdPath::new(absolute.as_ref()).components();
+        let mut missing = Vec::new();
+        let mut resolved = loop {
+            let prefix = existing.as_path().to_str().unwrap_or_default();
+            match fs.canonicalize(prefix) {
+                Ok(resolved) => break PathBuf::from(resolved),
+                Err(err) => match existing.next_back() {
+                    Some(component) => missing.push(component),
+                    None => return Err(at(absolute.as_ref())(err)),
+                },
+            }
+        };
+        for component in missing.into_iter().rev() {
+            match component {
+                StdComponent::ParentDir => {
+                    let _ = resolved.pop();
+                }
+                StdComponent::CurDir => {}
+                component => resolved.push(component),
+            }
+        }
+        from_std_path(&resolved)
+    }
+
+    fn absolute(&self) -> Result<Self> {
+        if self.is_absolute() {
+            return Ok(Self::from(self.as_ref()));
+        }
+        let current_dir = Self::from(fs::current().current_dir()?.as_str());
+        match self.as_ref().is_empty() {
+            true => Ok(current_dir),
+            false => Ok(current_dir.join(self)),
+        }
     }
 
     fn try_exists(&self) -> Result<bool> {
-        let std_path = StdPath::new(self.as_ref());
-        Ok(std_path.exists())
+        match fs::current().metadata(self.as_ref()) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(at(self.as_ref())(err)),
+        }
+    }
+
+    fn is_mount(&self) -> bool {
+        let Ok(metadata) = self.symlink_metadata() else {
+            return false;
+        };
+        if !metadata.is_dir() || metadata.ino == 0 {
+            return false;
+        }
+        match self.join(&Self::from("..")).symlink_metadata() {
+            Ok(parent) => parent.dev != metadata.dev || parent.ino == metadata.ino,
+            Err(_) => false,
+        }
+    }
+
+    fn samefile(&self, other: &Self) -> Result<bool> {
+        let (metadata, other_metadata) = (self.metadata()?, other.metadata()?);
+        match metadata.ino == 0 || other_metadata.ino == 0 {
+            true => Ok(self.canonicalize()?.as_ref() == other.canonicalize()?.as_ref()),
+            false => Ok((metadata.dev, metadata.ino) == (other_metadata.dev, other_metadata.ino)),
+        }
+    }
+
+    fn mount_info(&self) -> Result<MountInfo> {
+        let path = self.canonicalize()?;
+        let mounts = mount::parse_mountinfo(&sys::mount_table()?)?;
+        match MountInfo::find(&mounts, path.as_ref()) {
+            Some(mount) => Ok(mount.clone()),
+            None => Err(at(self.as_ref())(std::io::Error::new(
+                ErrorKind::NotFound,
+                "No mount contains the path",
+            ))),
+        }
+    }
+
+    fn disk_usage(&self) -> Result<DiskUsage> {
+        fs::current()
+            .disk_usage(self.as_ref())
+            .map_err(at(self.as_ref()))
+    }
+
+    fn tree_size(&self, options: &SizeOptions) -> Result<TreeSize<Self>>
+    where
+        Self: Clone,
+    {
+        usage::tree_size(self, options)
     }
 
     fn metadata(&self) -> Result<Metadata> {
-        let std_path = StdPath::new(self.as_ref());
-        std_path.metadata()
+        fs::current()
+            .metadata(self.as_ref())
+            .map_err(at(self.as_ref()))
     }
 
     fn read_dir(&self) -> Result<ReadDir> {
-        let std_path = StdPath::new(self.as_ref());
-        std_path.read_dir()
+        fs::current()
+            .read_dir(self.as_ref())
+            .map_err(at(self.as_ref()))
+    }
+
+    fn scandir(&self) -> Result<ScanDir<Self>> {
+        let inner = self.read_dir()?;
+        Ok(ScanDir::new(inner, Self::from(self.as_ref())))
+    }
+
+    fn glob(&self, pattern: &str) -> Result<Glob<Self>> {
+        Glob::new(Self::from(self.as_ref()), &Self::from(pattern), false)
+    }
+
+    fn rglob(&self, pattern: &str) -> Result<Glob<Self>> {
+        Glob::new(Self::from(self.as_ref()), &Self::from(pattern), true)
     }
 
     fn read_link(&self) -> Result<Self> {
-        let std_path = StdPath::new(self.as_ref());
-        let target_path = std_path.read_link()?;
-        Ok(Self::from(target_path.to_str().ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to convert path to string",
-            )
-        })?))
+        let target_path = fs::current()
+            .read_link(self.as_ref())
+            .map_err(at(self.as_ref()))?;
+        Ok(Self::from(target_path.as_str()))
     }
 
     fn symlink_metadata(&self) -> Result<Metadata> {
-        let std_path = StdPath::new(self.as_ref());
-        std_path.symlink_metadata()
+        fs::current()
+            .symlink_metadata(self.as_ref())
+            .map_err(at(self.as_ref()))
+    }
+
+    fn open(&self, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+        fs::current()
+            .open(self.as_ref(), options)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn read_bytes(&self) -> Result<Vec<u8>> {
+        let mut file = self.open(OpenOptions::new().read(true))?;
+        let mut data = Vec::new();
+        let _ = file.read_to_end(&mut data).map_err(at(self.as_ref()))?;
+        Ok(data)
+    }
+
+    fn read_text_with(&self, options: &TextOptions) -> Result<String> {
+        options
+            .decode(self.read_bytes()?)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn write_bytes(&self, data: &[u8]) -> Result<()> {
+        let mut file = self.open(OpenOptions::new().write(true).create(true).truncate(true))?;
+        file.write_all(data).map_err(at(self.as_ref()))
+    }
+
+    fn append(&self, data: &[u8]) -> Result<()> {
+        let mut file = self.open(OpenOptions::new().append(true).create(true))?;
+        file.write_all(data).map_err(at(self.as_ref()))
+    }
+
+    fn mkdir(&self, mode: u32, parents: bool, exist_ok: bool) -> Result<()> {
+        let fs = fs::current();
+        let result = match fs.create_dir(self.as_ref(), mode) {
+            Err(err) if parents && err.kind() == ErrorKind::NotFound => {
+                if let Some(parent) = self.parent().filter(|parent| !parent.as_ref().is_empty()) {
+                    parent.mkdir(0o777, true, true)?;
+                }
+                fs.create_dir(self.as_ref(), mode)
+            }
+            result => result,
+        };
+        match result {
+            Err(err) if exist_ok && err.kind() == ErrorKind::AlreadyExists && self.is_dir() => {
+                Ok(())
+            }
+            result => result.map_err(at(self.as_ref())),
+        }
+    }
+
+    fn touch(&self, mode: u32, exist_ok: bool) -> Result<()> {
+        let mut options = OpenOptions::new();
+        let _ = options.mode(mode);
+        if exist_ok {
+            let mut file = self.open(options.append(true).create(true))?;
+            file.set_modified(SystemTime::now())
+                .map_err(at(self.as_ref()))
+        } else {
+            let _ = self.open(options.write(true).create_new(true))?;
+            Ok(())
+        }
+    }
+
+    fn unlink(&self, missing_ok: bool) -> Result<()> {
+        match fs::current().remove_file(self.as_ref()) {
+            Err(err) if missing_ok && err.kind() == ErrorKind::NotFound => Ok(()),
+            result => result.map_err(at(self.as_ref())),
+        }
+    }
+
+    fn rmdir(&self) -> Result<()> {
+        fs::current()
+            .remove_dir(self.as_ref())
+            .map_err(at(self.as_ref()))
+    }
+
+    fn rename(&self, target: &Self) -> Result<Self> {
+        #[cfg(windows)]
+        if fs::current().symlink_metadata(target.as_ref()).is_ok() {
+            return Err(at(target.as_ref())(Error::new(
+                ErrorKind::AlreadyExists,
+                "Rename target already exists",
+            )));
+        }
+        self.replace(target)
+    }
+
+    fn replace(&self, target: &Self) -> Result<Self> {
+        fs::current()
+            .rename(self.as_ref(), target.as_ref())
+            .map_err(at(self.as_ref()))?;
+        Ok(Self::from(target.as_ref()))
+    }
+
+    fn symlink_to(&self, target: &Self) -> Result<()> {
+        fs::current()
+            .symlink(target.as_ref(), self.as_ref())
+            .map_err(at(self.as_ref()))
+    }
+
+    fn hardlink_to(&self, target: &Self) -> Result<()> {
+        fs::current()
+            .hard_link(target.as_ref(), self.as_ref())
+            .map_err(at(self.as_ref()))
+    }
+
+    fn chmod(&self, mode: u32) -> Result<()> {
+        fs::current()
+            .set_permissions(self.as_ref(), mode)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn chmod_symbolic(&self, mode: &str) -> Result<()> {
+        let change = ModeChange::parse(mode)?;
+        let metadata = self.metadata()?;
+        let umask = umask_for(&change, self.as_ref())?;
+        self.chmod(change.apply(metadata.mode, metadata.is_dir(), umask))
+    }
+
+    fn mkdir_with(&self, mode: &ModeChange, parents: bool, exist_ok: bool) -> Result<()> {
+        let umask = umask_for(mode, self.as_ref())?;
+        if parents {
+            if let Some(parent) = self.parent().filter(|parent| !parent.as_ref().is_empty()) {
+                parent.mkdir(0o777, true, true)?;
+            }
+        }
+        let mode = mode.apply(0o777, true, umask);
+        match fs::current().create_dir(self.as_ref(), mode) {
+            Err(err) if exist_ok && err.kind() == ErrorKind::AlreadyExists && self.is_dir() => {
+                Ok(())
+            }
+            Err(err) => Err(at(self.as_ref())(err)),
+            Ok(()) => self.chmod(mode),
+        }
+    }
+
+    fn touch_with(&self, mode: &ModeChange, exist_ok: bool) -> Result<()> {
+        let umask = umask_for(mode, self.as_ref())?;
+        let mode = mode.apply(0o666, false, umask);
+        match self.touch(mode, false) {
+            Err(err) if exist_ok && err.kind() == ErrorKind::AlreadyExists => {
+                self.touch(mode, true)
+            }
+            Err(err) => Err(err),
+            Ok(()) => self.chmod(mode),
+        }
+    }
+
+    fn chown(&self, user: Option<&str>, group: Option<&str>) -> Result<()> {
+        let (uid, gid) = owner_ids(user, group)?;
+        fs::current()
+            .set_owner(self.as_ref(), uid, gid, true)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn lchown(&self, user: Option<&str>, group: Option<&str>) -> Result<()> {
+        let (uid, gid) = owner_ids(user, group)?;
+        fs::current()
+            .set_owner(self.as_ref(), uid, gid, false)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn set_times(
+        &self,
+        atime: Option<SystemTime>,
+        mtime: Option<SystemTime>,
+        follow_symlinks: bool,
+    ) -> Result<()> {
+        fs::current()
+            .set_times(self.as_ref(), atime, mtime, follow_symlinks)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn copy_to(&self, dest: &Self, options: &mut CopyOptions) -> Result<u64>
+    where
+        Self: Clone,
+    {
+        copy::copy(self, dest, options)
+    }
+
+    fn copy_tree(&self, dest: &Self, options: &mut CopyOptions) -> Result<u64>
+    where
+        Self: Clone,
+    {
+        copy::copy_tree(self, dest, options)
+    }
+
+    fn xattrs(&self, follow_symlinks: bool) -> Result<Vec<String>> {
+        fs::current()
+            .list_xattrs(self.as_ref(), follow_symlinks)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn get_xattr(&self, name: &str, follow_symlinks: bool) -> Result<Option<Vec<u8>>> {
+        fs::current()
+            .get_xattr(self.as_ref(), name, follow_symlinks)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn set_xattr(&self, name: &str, value: &[u8], follow_symlinks: bool) -> Result<()> {
+        fs::current()
+            .set_xattr(self.as_ref(), name, value, follow_symlinks)
+            .map_err(at(self.as_ref()))
+    }
+
+    fn remove_xattr(&self, name: &str, follow_symlinks: bool) -> Result<()> {
+        fs::current()
+            .remove_xattr(self.as_ref(), name, follow_symlinks)
+            .map_err(at(self.as_ref()))
     }
 }
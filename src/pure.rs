@@ -1,4 +1,6 @@
-use crate::{Component, Components, String, ToOwned};
+use core::convert::Infallible;
+
+use crate::{Component, ExpandUserError, ExpandVarsError, Flavor, PathStorage, VarOptions};
 
 /// The current directory.
 const CURRENT_DIR: &str = ".";
@@ -30,34 +32,7 @@ fn split_once_with_delimiter<'i>(
 }
 
 /// A path parser.
-pub(crate) trait ParsablePath {
-    /// The primary component separator.
-    ///
-    /// For example, `'/'` on Posix systems and `'\\'` on Windows.
-    const PRIMARY_COMPONENT_SEPARATOR: char;
-    /// The secondary component separator.
-    ///
-    /// For example, `None` on Posix systems and `Some('/')` on Windows.
-    const SECONDARY_COMPONENT_SEPARATOR: Option<char>;
-    /// The component separators.
-    ///
-    /// For example, `&['/', '\\']` on Windows.
-    const COMPONENT_SEPARATORS: &'static [char] = match Self::SECONDARY_COMPONENT_SEPARATOR {
-        Some(c) => &[Self::PRIMARY_COMPONENT_SEPARATOR, c],
-        None => &[Self::PRIMARY_COMPONENT_SEPARATOR],
-    };
-    /// The extension separator.
-    const EXTENSION_SEPARATOR: char;
-    /// The drive separator.
-    const DRIVE_SEPARATOR: Option<char>;
-    /// The current directory.
-    const CURRENT_DIR: &'static str;
-    /// The parent directory.
-    const PARENT_DIR: &'static str;
-
-    /// Returns a mutable reference to the path as a string.
-    fn as_string_mut(&mut self) -> &mut String;
-
+pub(crate) trait ParsablePath: Flavor {
     /// Returns the first component of the path and the rest of the path in a lexical way.
     /// That means, `..` and `.` are not resolved or even considered.
     fn split_first_lexical(path: &str) -> (&str, Option<(&str, &str)>) {
@@ -191,26 +166,50 @@ pub(crate) trait ParsablePath {
         Self::split_last(s).1
     }
 
+    /// Returns the separator to put between `parent` and a relative child, which is none
+    /// for an empty parent so that the child stays relative.
+    fn separator_after<'b>(parent: &str, buf: &'b mut [u8; 4]) -> &'b str {
+        match parent.is_empty() || parent.ends_with(Self::COMPONENT_SEPARATORS) {
+            true => "",
+            false => Self::PRIMARY_COMPONENT_SEPARATOR.encode_utf8(buf),
+        }
+    }
+
     /// Joins the given path with the parent in place.
-    fn join_in_place(parent: &mut String, child: &str) {
+    ///
+    /// The parent is left untouched if the joined path does not fit into it.
+    fn join_in_place<S: PathStorage>(parent: &mut S, child: &str) -> Result<(), S::Error> {
         if Self::is_absolute(child) {
-            parent.clear();
-            parent.push_str(child);
-            return;
+            *parent = S::try_from_str(child)?;
+            return Ok(());
         }
-        Self::as_dir(parent);
-        parent.push_str(child.as_ref());
+        let mut buf = [0; 4];
+        let separator = Self::separator_after(parent.as_str(), &mut buf);
+        parent.try_extend(&[separator, child])
     }
 
     /// Joins the given path.
-    fn join(parent: &str, child: &str) -> String {
+    fn join<S: PathStorage>(parent: &str, child: &str) -> Result<S, S::Error> {
         if Self::is_absolute(child) {
-            return child.to_owned();
+            return S::try_from_str(child);
         }
-        let mut joined = parent.to_owned();
-        Self::as_dir(&mut joined);
-        joined.push_str(child.as_ref());
-        joined
+        let mut buf = [0; 4];
+        let separator = Self::separator_after(parent, &mut buf);
+        let mut joined = S::default();
+        joined.try_extend(&[parent, separator, child])?;
+        Ok(joined)
+    }
+
+    /// Returns the user of a leading `~` or `~user`, and the rest of the path.
+    ///
+    /// The user is `None` for a lone `~`.
+    fn split_user(path: &str) -> Option<(Option<&str>, &str)> {
+        let path = path.strip_prefix('~')?;
+        let (user, rest) = match split_once_with_delimiter(path, Self::COMPONENT_SEPARATORS) {
+            Some((user, _, rest)) => (user, rest.trim_start_matches(Self::COMPONENT_SEPARATORS)),
+            None => (path, ""),
+        };
+        Some(((!user.is_empty()).then_some(user), rest))
     }
 
     /// Returns the file stem and extension of the path.
@@ -238,15 +237,25 @@ pub(crate) trait ParsablePath {
         ext
     }
 
-    /// Replace the extension of the path with the given extension in place.
-    fn with_extension(path: &str, ext: &str) -> String {
-        let (path, _) = Self::split_extension(path);
-        let mut new = path.unwrap_or("").to_owned();
-        if !ext.is_empty() {
-            new.push(Self::EXTENSION_SEPARATOR);
-            new.push_str(ext);
+    /// Returns the path with the extension of its file name replaced by the given one.
+    ///
+    /// The parent is kept, and anything after the file name is dropped.
+    fn with_extension<S: PathStorage>(path: &str, ext: &str) -> Result<S, S::Error> {
+        let (stem, _) = Self::split_extension(path);
+        // The file name is a slice of the path, so the parent is what precedes it.
+        let parent = match stem {
+            Some(stem) => &path[..stem.as_ptr() as usize - path.as_ptr() as usize],
+            None => "",
+        };
+        let stem = stem.unwrap_or("");
+        let mut buf = [0; 4];
+        let separator = Self::EXTENSION_SEPARATOR.encode_utf8(&mut buf);
+        let mut new = S::default();
+        match ext.is_empty() {
+            true => new.try_extend(&[parent, stem])?,
+            false => new.try_extend(&[parent, stem, separator, ext])?,
         }
-        new
+        Ok(new)
     }
 
     /// Returns the driver of the path and the rest of the path.
@@ -273,37 +282,47 @@ pub(crate) trait ParsablePath {
     fn is_relative(path: &str) -> bool {
         !Self::is_absolute(path)
     }
-
-    /// Append component separator if not already present.
-    fn as_dir(path: &mut String) {
-        if !path.ends_with(Self::COMPONENT_SEPARATORS) {
-            path.push(Self::PRIMARY_COMPONENT_SEPARATOR);
-        }
-    }
 }
 
+impl<F: Flavor> ParsablePath for F {}
+
 /// A pure path.
 pub trait PurePath: Sized {
+    /// The error returned when an operation would exceed the capacity of the path.
+    ///
+    /// This is [`Infallible`] for paths that can grow without bound.
+    type Error;
+
     /// Returns the parent of the path.
     fn parent(&self) -> Option<Self>;
 
     /// Returns the last component of the path, if there is one.
     fn file_name(&self) -> Option<&str>;
 
-    // /// Replace the extension of the path with the given extension.
-    // fn with_extension(&self, ext: &str) -> Self {
-    //     let (path, _) = Self::Parser::split_extension(self.as_ref());
-    //     let mut joined = path.to_string();
-    //     joined.push(Self::Parser::EXTENSION_SEPARATOR);
-    //     joined.push_str(ext);
-    //     Self::from(joined)
-    // }
-
     /// Joins the given path in place.
-    fn join_in_place(&mut self, path: &Self);
+    fn join_in_place(&mut self, path: &Self)
+    where
+        Self: PurePath<Error = Infallible>,
+    {
+        let Ok(()) = self.try_join_in_place(path);
+    }
+
+    /// Tries to join the given path in place.
+    ///
+    /// The path is left untouched on failure.
+    fn try_join_in_place(&mut self, path: &Self) -> Result<(), Self::Error>;
 
     /// Joins the given path.
-    fn join(&self, path: &Self) -> Self;
+    fn join(&self, path: &Self) -> Self
+    where
+        Self: PurePath<Error = Infallible>,
+    {
+        let Ok(joined) = self.try_join(path);
+        joined
+    }
+
+    /// Tries to join the given path.
+    fn try_join(&self, path: &Self) -> Result<Self, Self::Error>;
 
     /// Returns the file stem of the path.
     fn file_stem(&self) -> Option<&str>;
@@ -312,7 +331,16 @@ pub trait PurePath: Sized {
     fn extension(&self) -> Option<&str>;
 
     /// Replace the extension of the path with the given extension.
-    fn with_extension(&mut self, ext: &str) -> Self;
+    fn with_extension(&mut self, ext: &str) -> Self
+    where
+        Self: PurePath<Error = Infallible>,
+    {
+        let Ok(new) = self.try_with_extension(ext);
+        new
+    }
+
+    /// Tries to replace the extension of the path with the given extension.
+    fn try_with_extension(&self, ext: &str) -> Result<Self, Self::Error>;
 
     /// Returns whether the path is absolute.
     fn is_absolute(&self) -> bool;
@@ -325,50 +353,38 @@ pub trait PurePath: Sized {
     /// Returns the components of the path.
     fn components(&self) -> impl DoubleEndedIterator<Item = Component<'_>>;
 
+    /// Replaces a leading `~` or `~user` with the home directory returned by `home`.
+    ///
+    /// `home` is called with the name of the user, or `None` for a lone `~`.
+    /// Paths that do not start with `~` are returned unchanged.
+    fn expanduser_with<H: AsRef<str>>(
+        &self,
+        home: impl FnOnce(Option<&str>) -> Option<H>,
+    ) -> Result<Self, ExpandUserError<Self::Error>>;
+
+    /// Expands the environment variables in the path that `lookup` defines, using the syntaxes
+    /// of the flavor.
+    ///
+    /// `lookup` is called with the name of each variable. Undefined variables are left as
+    /// they are.
+    fn expand_vars<V: AsRef<str>>(
+        &self,
+        lookup: impl FnMut(&str) -> Option<V>,
+    ) -> Result<Self, ExpandVarsError<'_, Self::Error>> {
+        self.expand_vars_with(&VarOptions::default(), lookup)
+    }
+
+    /// Expands the environment variables in the path that `lookup` defines, according to
+    /// `options`.
+    fn expand_vars_with<V: AsRef<str>>(
+        &self,
+        options: &VarOptions,
+        lookup: impl FnMut(&str) -> Option<V>,
+    ) -> Result<Self, ExpandVarsError<'_, Self::Error>>;
+
     // fn strip_extension(&self) -> Self;
     // fn strip_prefix(&self, prefix: &str) -> Option<Self>;
     // fn strip_suffix(&self, suffix: &str) -> Option<Self>;
     // fn starts_with(&self, path: &str) -> bool;
     // fn ends_with(&self, path: &str) -> bool;
 }
-
-impl<P: ParsablePath + Sized + AsRef<str> + for<'a> From<&'a str> + From<String>> PurePath for P {
-    fn parent(&self) -> Option<Self> {
-        let parent = Self::parent(self.as_ref());
-        parent.map(Self::from)
-    }
-
-    fn file_name(&self) -> Option<&str> {
-        Self::file_name(self.as_ref())
-    }
-
-    fn join_in_place(&mut self, path: &Self) {
-        Self::join_in_place(self.as_string_mut(), path.as_ref());
-    }
-
-    fn join(&self, path: &Self) -> Self {
-        let joined = Self::join(self.as_ref(), path.as_ref());
-        Self::from(joined)
-    }
-
-    fn file_stem(&self) -> Option<&str> {
-        <Self as ParsablePath>::file_stem(self.as_ref())
-    }
-
-    fn extension(&self) -> Option<&str> {
-        <Self as ParsablePath>::extension(self.as_ref())
-    }
-
-    fn with_extension(&mut self, ext: &str) -> Self {
-        let new = <Self as ParsablePath>::with_extension(self.as_ref(), ext);
-        Self::from(new)
-    }
-
-    fn is_absolute(&self) -> bool {
-        Self::is_absolute(self.as_ref())
-    }
-
-    fn components(&self) -> impl DoubleEndedIterator<Item = Component<'_>> {
-        <Components<'_, Self>>::new(self.as_ref())
-    }
-}
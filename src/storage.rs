@@ -0,0 +1,226 @@
+#[cfg(feature = "alloc")]
+use core::convert::Infallible;
+use core::fmt::{self, Debug, Display, Formatter};
+
+#[cfg(all(feature = "alloc", not(feature = "std"), target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::{borrow::Cow, boxed::Box, rc::Rc};
+#[cfg(feature = "std")]
+use std::{borrow::Cow, boxed::Box, rc::Rc, sync::Arc};
+
+#[cfg(feature = "alloc")]
+use crate::String;
+
+/// The error returned when a path does not fit into the capacity of its storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CapacityError;
+
+impl Display for CapacityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("path exceeds the capacity of its storage")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
+/// The backing storage of a [GenericPath](crate::GenericPath).
+pub trait PathStorage: Default {
+    /// The error returned when the storage runs out of capacity.
+    ///
+    /// This is [`Infallible`](core::convert::Infallible) for storages that can grow without bound.
+    type Error;
+
+    /// Returns the stored path.
+    fn as_str(&self) -> &str;
+
+    /// Appends all of `parts` to the stored path.
+    ///
+    /// The storage is left untouched on failure.
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), Self::Error>;
+
+    /// Creates a storage holding `s`.
+    fn try_from_str(s: &str) -> Result<Self, Self::Error> {
+        let mut storage = Self::default();
+        storage.try_extend(&[s])?;
+        Ok(storage)
+    }
+}
+
+/// Returns the total length of `parts`.
+#[cfg(feature = "alloc")]
+fn total_len(s: &str, parts: &[&str]) -> usize {
+    parts.iter().fold(s.len(), |len, part| len + part.len())
+}
+
+/// Concatenates `s` and `parts` into a new string.
+#[cfg(feature = "alloc")]
+fn concat(s: &str, parts: &[&str]) -> String {
+    let mut new = String::with_capacity(total_len(s, parts));
+    new.push_str(s);
+    parts.iter().for_each(|part| new.push_str(part));
+    new
+}
+
+#[cfg(feature = "alloc")]
+impl PathStorage for String {
+    type Error = Infallible;
+
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), Infallible> {
+        self.reserve(total_len("", parts));
+        parts.iter().for_each(|part| self.push_str(part));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PathStorage for Box<str> {
+    type Error = Infallible;
+
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), Infallible> {
+        *self = concat(self, parts).into_boxed_str();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PathStorage for Rc<str> {
+    type Error = Infallible;
+
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), Infallible> {
+        *self = concat(self, parts).into();
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl PathStorage for Arc<str> {
+    type Error = Infallible;
+
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), Infallible> {
+        *self = concat(self, parts).into();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PathStorage for Cow<'_, str> {
+    type Error = Infallible;
+
+    fn as_str(&self) -> &str {
+        self
+    }
+
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), Infallible> {
+        self.to_mut().try_extend(parts)
+    }
+}
+
+/// A string stored inline in a buffer of `N` bytes.
+#[derive(Clone, Copy)]
+pub struct ArrayString<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ArrayString<N> {
+    /// Returns the capacity of the string in bytes.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Debug for ArrayString<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> Default for ArrayString<N> {
+    fn default() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> PathStorage for ArrayString<N> {
+    type Error = CapacityError;
+
+    fn as_str(&self) -> &str {
+        // SAFETY: `buf[..len]` is only ever written from whole `&str`s.
+        unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), CapacityError> {
+        let len = parts.iter().fold(self.len, |len, part| len + part.len());
+        if len > N {
+            return Err(CapacityError);
+        }
+        for part in parts {
+            let end = self.len + part.len();
+            self.buf[self.len..end].copy_from_slice(part.as_bytes());
+            self.len = end;
+        }
+        Ok(())
+    }
+}
+
+/// A string stored inline while it fits into `N` bytes, and on the heap otherwise.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub enum SmallString<const N: usize> {
+    /// A string stored inline.
+    Inline(ArrayString<N>),
+    /// A string stored on the heap.
+    Heap(String),
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> Default for SmallString<N> {
+    fn default() -> Self {
+        Self::Inline(ArrayString::default())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> PathStorage for SmallString<N> {
+    type Error = Infallible;
+
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Inline(s) => s.as_str(),
+            Self::Heap(s) => s,
+        }
+    }
+
+    fn try_extend(&mut self, parts: &[&str]) -> Result<(), Infallible> {
+        match self {
+            Self::Inline(s) => {
+                if s.try_extend(parts).is_err() {
+                    *self = Self::Heap(concat(s.as_str(), parts));
+                }
+                Ok(())
+            }
+            Self::Heap(s) => s.try_extend(parts),
+        }
+    }
+}
@@ -0,0 +1,635 @@
+use std::env;
+use std::time::SystemTime;
+
+use crate::{DiskUsage, UnsupportedXattrError};
+
+/// An entry of the passwd or group database.
+///
+/// # Safety
+///
+/// All zeros must be a valid value of the type.
+#[cfg(unix)]
+unsafe trait Entry {}
+
+// SAFETY: These are plain old data.
+#[cfg(unix)]
+unsafe impl Entry for libc::passwd {}
+// SAFETY: These are plain old data.
+#[cfg(unix)]
+unsafe impl Entry for libc::group {}
+
+/// Looks up an entry with a reentrant function such as `getpwnam_r`, which `call` calls
+/// with the entry, a buffer and its length, and the result pointer.
+///
+/// The entry is returned with the buffer that its strings point into.
+#[cfg(unix)]
+fn lookup<T: Entry>(
+    mut call: impl FnMut(*mut T, *mut libc::c_char, usize, *mut *mut T) -> libc::c_int,
+) -> Option<(T, Vec<libc::c_char>)> {
+    let mut buf = vec![0; 1024];
+    loop {
+        // SAFETY: All zeros is a valid value of an `Entry`.
+        let mut entry: T = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let code = call(&mut entry, buf.as_mut_ptr(), buf.len(), &mut result);
+        if code == libc::ERANGE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if code != 0 || result.is_null() {
+            return None;
+        }
+        return Some((entry, buf));
+    }
+}
+
+/// Converts a string of an entry returned by [lookup].
+#[cfg(unix)]
+fn entry_string(string: *const libc::c_char) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    // SAFETY: The strings of an entry are NUL-terminated and in its buffer.
+    let string = unsafe { std::ffi::CStr::from_ptr(string) };
+    string.to_str().ok().map(str::to_owned)
+}
+
+/// Returns the passwd entry of `user`, or of the current user if it is `None`.
+#[cfg(unix)]
+fn passwd(user: Option<&str>) -> Option<(libc::passwd, Vec<libc::c_char>)> {
+    let user = user.map(std::ffi::CString::new).transpose().ok()?;
+    lookup(|entry, buf, len, result| {
+        // SAFETY: All pointers are valid, and `len` is the length of `buf`.
+        unsafe {
+            match &user {
+                Some(user) => libc::getpwnam_r(user.as_ptr(), entry, buf, len, result),
+                None => libc::getpwuid_r(libc::getuid(), entry, buf, len, result),
+            }
+        }
+    })
+}
+
+/// Returns the home directory of `user` from the passwd database, or of the current user if
+/// it is `None`.
+#[cfg(unix)]
+fn passwd_home(user: Option<&str>) -> Option<String> {
+    let (passwd, _buf) = passwd(user)?;
+    entry_string(passwd.pw_dir)
+}
+
+/// Returns the ID of the user named `name`.
+pub(crate) fn user_id(name: &str) -> Option<u32> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            passwd(Some(name)).map(|(passwd, _)| passwd.pw_uid)
+        } else {
+            let _ = name;
+            None
+        }
+    }
+}
+
+/// Returns the name of the user with the ID `uid`.
+pub(crate) fn user_name(uid: u32) -> Option<String> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            let (passwd, _buf) = lookup(|entry, buf, len, result| {
+                // SAFETY: All pointers are valid, and `len` is the length of `buf`.
+                unsafe { libc::getpwuid_r(uid, entry, buf, len, result) }
+            })?;
+            entry_string(passwd.pw_name)
+        } else {
+            let _ = uid;
+            None
+        }
+    }
+}
+
+/// Returns the ID of the group named `name`.
+pub(crate) fn group_id(name: &str) -> Option<u32> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            let name = std::ffi::CString::new(name).ok()?;
+            let (group, _buf) = lookup(|entry, buf, len, result| {
+                // SAFETY: All pointers are valid, and `len` is the length of `buf`.
+                unsafe { libc::getgrnam_r(name.as_ptr(), entry, buf, len, result) }
+            })?;
+            Some(group.gr_gid)
+        } else {
+            let _ = name;
+            None
+        }
+    }
+}
+
+/// Returns the name of the group with the ID `gid`.
+pub(crate) fn group_name(gid: u32) -> Option<String> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            let (group, _buf) = lookup(|entry, buf, len, result| {
+                // SAFETY: All pointers are valid, and `len` is the length of `buf`.
+                unsafe { libc::getgrgid_r(gid, entry, buf, len, result) }
+            })?;
+            entry_string(group.gr_name)
+        } else {
+            let _ = gid;
+            None
+        }
+    }
+}
+
+/// Converts a value of a C type that is narrower on some systems.
+#[cfg(unix)]
+fn widen<T: Into<U>, U>(value: T) -> U {
+    value.into()
+}
+
+/// Converts a value to a C type that is narrower on some systems.
+#[cfg(unix)]
+fn narrow<T: TryInto<U>, U>(value: T) -> Option<U> {
+    value.try_into().ok()
+}
+
+/// Returns the home directory of `user`, or of the current user if it is `None`.
+///
+/// On Unix, this is `$HOME` for the current user, and looked up in the passwd database
+/// otherwise. On Windows, this is `%USERPROFILE%` for the current user, and a sibling of it
+/// for other users.
+pub(crate) fn home_dir(user: Option<&str>) -> Option<String> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            match user {
+                Some(user) => passwd_home(Some(user)),
+                None => env::var("HOME")
+                    .ok()
+                    .filter(|home| !home.is_empty())
+                    .or_else(|| passwd_home(None)),
+            }
+        } else if #[cfg(windows)] {
+            let home = env::var("USERPROFILE").ok().filter(|home| !home.is_empty())?;
+            match user {
+                Some(user) if env::var("USERNAME").ok().as_deref() != Some(user) => {
+                    let parent = std::path::Path::new(&home).parent()?;
+                    parent.join(user).into_os_string().into_string().ok()
+                }
+                _ => Some(home),
+            }
+        } else {
+            let _ = user;
+            None
+        }
+    }
+}
+
+/// Returns the mount table of the current process, in the format of `/proc/self/mountinfo`.
+pub(crate) fn mount_table() -> std::io::Result<String> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            std::fs::read_to_string("/proc/self/mountinfo")
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "The mount table is only available on Linux",
+            ))
+        }
+    }
+}
+
+/// Returns the space of the filesystem that holds `path`, as reported by `statvfs(3)`.
+pub(crate) fn disk_usage(path: &str) -> std::io::Result<DiskUsage> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::ffi::CString;
+            use std::io::{Error, ErrorKind};
+            use std::mem;
+
+            let path = CString::new(path).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+            // SAFETY: `statvfs` is plain old data, for which all zeros is a valid value.
+            let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+            // SAFETY: `path` is NUL-terminated, and `stat` is valid for writes.
+            if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+                return Err(Error::last_os_error());
+            }
+            let (blocks, free, available, fragment): (u64, u64, u64, u64) = (
+                widen(stat.f_blocks),
+                widen(stat.f_bfree),
+                widen(stat.f_bavail),
+                widen(stat.f_frsize),
+            );
+            // Some filesystems, such as FUSE and network ones, report inconsistent counts.
+            Ok(DiskUsage {
+                total: blocks.saturating_mul(fragment),
+                used: blocks.saturating_sub(free).saturating_mul(fragment),
+                free: available.saturating_mul(fragment),
+            })
+        } else {
+            let _ = path;
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Disk usage is only available on Unix",
+            ))
+        }
+    }
+}
+
+/// Returns whether `path` is a directory junction, which is a reparse point with the
+/// `IO_REPARSE_TAG_MOUNT_POINT` tag, rather than a symlink.
+#[cfg(windows)]
+pub(crate) fn is_junction(path: &str) -> std::io::Result<bool> {
+    use std::ffi::c_void;
+    use std::io::Error;
+    use std::mem;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x0400;
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+    /// `FileAttributeTagInfo` of `FILE_INFO_BY_HANDLE_CLASS`.
+    const FILE_ATTRIBUTE_TAG_INFO: i32 = 9;
+
+    /// `FILE_ATTRIBUTE_TAG_INFO`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct AttributeTagInfo {
+        file_attributes: u32,
+        reparse_tag: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetFileInformationByHandleEx(
+            file: *mut c_void,
+            class: i32,
+            info: *mut c_void,
+            size: u32,
+        ) -> i32;
+    }
+
+    // The reparse point itself is opened, without any access to its contents.
+    let file = std::fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)?;
+    let mut info = AttributeTagInfo::default();
+    // SAFETY: The handle is open, and `info` is valid for writes of its size.
+    let ok = unsafe {
+        GetFileInformationByHandleEx(
+            file.as_raw_handle(),
+            FILE_ATTRIBUTE_TAG_INFO,
+            (&mut info as *mut AttributeTagInfo).cast(),
+            mem::size_of::<AttributeTagInfo>() as u32,
+        )
+    };
+    if ok == 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(info.file_attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+        && info.reparse_tag == IO_REPARSE_TAG_MOUNT_POINT)
+}
+
+/// Returns the umask of the process.
+pub(crate) fn umask() -> std::io::Result<u32> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            // Linux reports it without changing it, which would race with other threads.
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            if let Ok(status) = std::fs::read_to_string("/proc/self/status") {
+                let umask = status.lines().find_map(|line| line.strip_prefix("Umask:"));
+                if let Some(Ok(umask)) = umask.map(|umask| u32::from_str_radix(umask.trim(), 8)) {
+                    return Ok(umask);
+                }
+            }
+            // SAFETY: `umask` cannot fail.
+            let umask = unsafe { libc::umask(0o022) };
+            // SAFETY: This restores the umask.
+            let _ = unsafe { libc::umask(umask) };
+            Ok(widen(umask))
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// Sets the access and modification times of `path`, leaving those that are `None`.
+pub(crate) fn set_times(
+    path: &str,
+    atime: Option<SystemTime>,
+    mtime: Option<SystemTime>,
+    follow: bool,
+) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::ffi::CString;
+            use std::time::UNIX_EPOCH;
+
+            let out_of_range = || Error::new(ErrorKind::InvalidInput, "Time is out of range");
+            let timespec = |time: Option<SystemTime>| -> std::io::Result<libc::timespec> {
+                let Some(time) = time else {
+                    return Ok(libc::timespec {
+                        tv_sec: 0,
+                        tv_nsec: libc::UTIME_OMIT,
+                    });
+                };
+                let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+                    Ok(since) => (i64::try_from(since.as_secs()), since.subsec_nanos()),
+                    Err(err) => {
+                        let before = err.duration();
+                        let secs = i64::try_from(before.as_secs()).map(|secs| -secs);
+                        match before.subsec_nanos() {
+                            0 => (secs, 0),
+                            nanos => (secs.map(|secs| secs - 1), 1_000_000_000 - nanos),
+                        }
+                    }
+                };
+                Ok(libc::timespec {
+                    tv_sec: narrow(secs.map_err(|_| out_of_range())?).ok_or_else(out_of_range)?,
+                    tv_nsec: narrow(nanos).ok_or_else(out_of_range)?,
+                })
+            };
+            let path = CString::new(path).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+            let times = [timespec(atime)?, timespec(mtime)?];
+            let flags = match follow {
+                true => 0,
+                false => libc::AT_SYMLINK_NOFOLLOW,
+            };
+            // SAFETY: `path` is NUL-terminated, and `times` holds two elements.
+            if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), flags) } != 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        } else {
+            if !follow {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Setting the times of symlinks is only supported on Unix",
+                ));
+            }
+            let mut times = std::fs::FileTimes::new();
+            if let Some(atime) = atime {
+                times = times.set_accessed(atime);
+            }
+            if let Some(mtime) = mtime {
+                times = times.set_modified(mtime);
+            }
+            let mut options = std::fs::File::options();
+            #[cfg(windows)]
+            {
+                use std::os::windows::fs::OpenOptionsExt;
+
+                const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+                const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+                // Only the attributes are written, so that read-only files can be opened,
+                // and directories can only be opened with backup semantics.
+                let _ = options
+                    .access_mode(FILE_WRITE_ATTRIBUTES)
+                    .custom_flags(FILE_FLAG_BACKUP_SEMANTICS);
+            }
+            #[cfg(not(windows))]
+            let _ = options.write(true);
+            options.open(path)?.set_times(times)
+        }
+    }
+}
+
+/// Returns the result of an xattr system call, or the error for `name`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn xattr_result<T: TryInto<usize>>(result: T, name: Option<&str>) -> std::io::Result<usize> {
+    use std::io::Error;
+
+    match result.try_into() {
+        Ok(len) => Ok(len),
+        Err(_) => {
+            let error = Error::last_os_error();
+            match error.raw_os_error() {
+                Some(libc::ENOTSUP) => {
+                    Err(UnsupportedXattrError::new(name.map(str::to_owned)).into())
+                }
+                _ => Err(error),
+            }
+        }
+    }
+}
+
+/// Calls an xattr system call that fills `buf` with a growing buffer, and returns the
+/// filled part.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn xattr_read(
+    name: Option<&str>,
+    mut call: impl FnMut(*mut libc::c_void, usize) -> libc::ssize_t,
+) -> std::io::Result<Vec<u8>> {
+    loop {
+        let len = xattr_result(call(std::ptr::null_mut(), 0), name)?;
+        let mut buf = vec![0u8; len];
+        match xattr_result(call(buf.as_mut_ptr().cast(), buf.len()), name) {
+            Ok(len) => {
+                buf.truncate(len);
+                return Ok(buf);
+            }
+            // The value grew in between.
+            Err(err) if err.raw_os_error() == Some(libc::ERANGE) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Converts `path` and the attribute `name` into C strings.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn xattr_strings(
+    path: &str,
+    name: Option<&str>,
+) -> std::io::Result<(std::ffi::CString, std::ffi::CString)> {
+    use std::ffi::CString;
+    use std::io::{Error, ErrorKind};
+
+    let invalid = |err| Error::new(ErrorKind::InvalidInput, err);
+    let path = CString::new(path).map_err(invalid)?;
+    let name = CString::new(name.unwrap_or_default()).map_err(invalid)?;
+    Ok((path, name))
+}
+
+/// Returns the names of the extended attributes of `path`.
+pub(crate) fn list_xattrs(path: &str, follow: bool) -> std::io::Result<Vec<String>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let (path, _) = xattr_strings(path, None)?;
+            let names = xattr_read(None, |buf, len| {
+                // SAFETY: `path` is NUL-terminated, and `buf` is valid for `len` bytes.
+                unsafe {
+                    match follow {
+                        true => libc::listxattr(path.as_ptr(), buf.cast(), len),
+                        false => libc::llistxattr(path.as_ptr(), buf.cast(), len),
+                    }
+                }
+            })?;
+            names
+                .split(|&byte| byte == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    String::from_utf8(name.to_vec()).map_err(|err| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+                    })
+                })
+                .collect()
+        } else {
+            let _ = (path, follow);
+            Err(UnsupportedXattrError::new(None).into())
+        }
+    }
+}
+
+/// Returns the value of the extended attribute `name` of `path`, or `None` if it is not set.
+pub(crate) fn get_xattr(path: &str, name: &str, follow: bool) -> std::io::Result<Option<Vec<u8>>> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let (path, c_name) = xattr_strings(path, Some(name))?;
+            let value = xattr_read(Some(name), |buf, len| {
+                // SAFETY: The strings are NUL-terminated, and `buf` is valid for `len` bytes.
+                unsafe {
+                    match follow {
+                        true => libc::getxattr(path.as_ptr(), c_name.as_ptr(), buf, len),
+                        false => libc::lgetxattr(path.as_ptr(), c_name.as_ptr(), buf, len),
+                    }
+                }
+            });
+            match value {
+                Ok(value) => Ok(Some(value)),
+                Err(err) if err.raw_os_error() == Some(libc::ENODATA) => Ok(None),
+                Err(err) => Err(err),
+            }
+        } else {
+            let _ = (path, follow);
+            Err(UnsupportedXattrError::new(Some(name.to_owned())).into())
+        }
+    }
+}
+
+/// Sets the extended attribute `name` of `path` to `value`.
+pub(crate) fn set_xattr(path: &str, name: &str, value: &[u8], follow: bool) -> std::io::Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let (path, c_name) = xattr_strings(path, Some(name))?;
+            let (value, len) = (value.as_ptr().cast(), value.len());
+            // SAFETY: The strings are NUL-terminated, and `value` is valid for `len` bytes.
+            let result = unsafe {
+                match follow {
+                    true => libc::setxattr(path.as_ptr(), c_name.as_ptr(), value, len, 0),
+                    false => libc::lsetxattr(path.as_ptr(), c_name.as_ptr(), value, len, 0),
+                }
+            };
+            xattr_result(result, Some(name)).map(|_| ())
+        } else {
+            let _ = (path, value, follow);
+            Err(UnsupportedXattrError::new(Some(name.to_owned())).into())
+        }
+    }
+}
+
+/// Removes the extended attribute `name` of `path`.
+pub(crate) fn remove_xattr(path: &str, name: &str, follow: bool) -> std::io::Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let (path, c_name) = xattr_strings(path, Some(name))?;
+            // SAFETY: The strings are NUL-terminated.
+            let result = unsafe {
+                match follow {
+                    true => libc::removexattr(path.as_ptr(), c_name.as_ptr()),
+                    false => libc::lremovexattr(path.as_ptr(), c_name.as_ptr()),
+                }
+            };
+            match xattr_result(result, Some(name)) {
+                Err(err) if err.raw_os_error() == Some(libc::ENODATA) => {
+                    Err(std::io::Error::new(std::io::ErrorKind::NotFound, "No such attribute"))
+                }
+                result => result.map(|_| ()),
+            }
+        } else {
+            let _ = (path, follow);
+            Err(UnsupportedXattrError::new(Some(name.to_owned())).into())
+        }
+    }
+}
+
+/// Copies the contents of the file at `from` to `to`, as described by
+/// [`FileSystem::copy_file`](crate::FileSystem::copy_file).
+///
+/// On Linux, the file is cloned with `FICLONE` if the filesystem supports reflinks, or else
+/// copied in the kernel with `copy_file_range(2)`. Both fall back to reading and writing.
+pub(crate) fn copy_file(
+    from: &str,
+    to: &str,
+    progress: &mut dyn FnMut(u64),
+) -> std::io::Result<u64> {
+    let mut source = std::fs::File::open(from)?;
+    let mut target = std::fs::File::create(to)?;
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+
+        let len = source.metadata()?.len();
+        // SAFETY: Both file descriptors are open.
+        if unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } == 0 {
+            progress(len);
+            return Ok(len);
+        }
+        if let Some(copied) = copy_file_range(&source, &target, progress)? {
+            return Ok(copied);
+        }
+    }
+    crate::fs::copy_chunks(&mut source, &mut target, progress)
+}
+
+/// Copies `source` to `target` with `copy_file_range(2)` from their current positions, or
+/// returns `None` if nothing could be copied this way.
+#[cfg(target_os = "linux")]
+fn copy_file_range(
+    source: &std::fs::File,
+    target: &std::fs::File,
+    progress: &mut dyn FnMut(u64),
+) -> std::io::Result<Option<u64>> {
+    use std::io::Error;
+    use std::os::fd::AsRawFd;
+    use std::ptr;
+
+    /// The number of bytes asked for per call, which bounds the time between progress
+    /// reports.
+    const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+    let mut copied = 0;
+    loop {
+        // SAFETY: Both file descriptors are open, and null offsets use and update the
+        // positions of the files.
+        let result = unsafe {
+            libc::copy_file_range(
+                source.as_raw_fd(),
+                ptr::null_mut(),
+                target.as_raw_fd(),
+                ptr::null_mut(),
+                CHUNK_SIZE,
+                0,
+            )
+        };
+        match usize::try_from(result) {
+            // Some pseudo-filesystems report nothing to copy for files with contents.
+            Ok(0) if copied == 0 => return Ok(None),
+            Ok(0) => return Ok(Some(copied)),
+            Ok(len) => {
+                copied += len as u64;
+                progress(copied);
+            }
+            Err(_) => {
+                let error = Error::last_os_error();
+                match error.raw_os_error() {
+                    Some(libc::EINTR) => {}
+                    Some(
+                        libc::ENOSYS | libc::EXDEV | libc::EINVAL | libc::EOPNOTSUPP | libc::EPERM,
+                    ) if copied == 0 => return Ok(None),
+                    _ => return Err(error),
+                }
+            }
+        }
+    }
+}
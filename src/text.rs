@@ -0,0 +1,84 @@
+use std::io::{Error, ErrorKind, Result};
+
+/// The byte order mark of UTF-8.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// The byte order mark of little-endian UTF-16.
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+/// The byte order mark of big-endian UTF-16.
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// The encoding of a text file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8.
+    #[default]
+    Utf8,
+    /// Little-endian UTF-16.
+    Utf16Le,
+    /// Big-endian UTF-16.
+    Utf16Be,
+    /// Detected from the byte order mark, falling back to UTF-8 without one.
+    ///
+    /// The detected byte order mark is always stripped.
+    Detect,
+}
+
+/// How line endings are translated when reading text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Newline {
+    /// Line endings are kept as they are.
+    #[default]
+    Keep,
+    /// `\r\n` and lone `\r` are translated into `\n`.
+    Normalize,
+}
+
+/// Options for reading text files with [`Path::read_text_with`](crate::Path::read_text_with).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TextOptions {
+    /// The encoding of the file.
+    pub encoding: Encoding,
+    /// How line endings are translated.
+    pub newline: Newline,
+    /// Whether a leading byte order mark is removed.
+    pub strip_bom: bool,
+}
+
+impl TextOptions {
+    /// Decodes `bytes` into a string.
+    pub(crate) fn decode(&self, bytes: Vec<u8>) -> Result<String> {
+        let (encoding, bom) = match self.encoding {
+            Encoding::Detect if bytes.starts_with(UTF8_BOM) => (Encoding::Utf8, true),
+            Encoding::Detect if bytes.starts_with(UTF16_LE_BOM) => (Encoding::Utf16Le, true),
+            Encoding::Detect if bytes.starts_with(UTF16_BE_BOM) => (Encoding::Utf16Be, true),
+            Encoding::Detect => (Encoding::Utf8, false),
+            encoding => (encoding, self.strip_bom),
+        };
+        let mut text = match encoding {
+            Encoding::Utf16Le => decode_utf16(&bytes, u16::from_le_bytes)?,
+            Encoding::Utf16Be => decode_utf16(&bytes, u16::from_be_bytes)?,
+            _ => String::from_utf8(bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+        };
+        if bom && text.starts_with('\u{FEFF}') {
+            let _ = text.remove(0);
+        }
+        if self.newline == Newline::Normalize && text.contains('\r') {
+            text = text.replace("\r\n", "\n").replace('\r', "\n");
+        }
+        Ok(text)
+    }
+}
+
+/// Decodes UTF-16 code units read with `from_bytes`.
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String> {
+    let chunks = bytes.chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "UTF-16 data has an odd number of bytes",
+        ));
+    }
+    char::decode_utf16(chunks.map(|chunk| from_bytes([chunk[0], chunk[1]])))
+        .collect::<core::result::Result<String, _>>()
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
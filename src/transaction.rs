@@ -0,0 +1,261 @@
+use std::convert::Infallible;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::error::at;
+use crate::{Path, PurePath, RollbackError};
+
+/// A queued operation of a [Transaction].
+#[derive(Debug, Clone)]
+enum Step<P> {
+    Rename { from: P, to: P },
+    Write { path: P, data: Vec<u8> },
+    Remove { path: P },
+    Mkdir { path: P, mode: u32 },
+}
+
+/// An operation that undoes part of a [Step].
+#[derive(Debug)]
+enum Undo<P> {
+    /// Moves `backup` back to `path`.
+    Restore { path: P, backup: P },
+    /// Removes the file created at this path, which did not exist before.
+    Remove(P),
+    /// Removes the directory created at this path.
+    Rmdir(P),
+}
+
+/// A batch of operations that are applied in order, and rolled back if one of them fails.
+///
+/// Files that are replaced or removed are moved into a staging directory rather than
+/// deleted, and moved back on rollback. New contents are written to the staging directory
+/// first, and then renamed into place. The staging directory is created if needed, and
+/// should be on the same filesystem as the paths changed, so that moving files is cheap
+/// and does not fail.
+///
+/// Each commit stages its files in a new subdirectory of the staging directory, named by
+/// a number. It is removed afterwards, unless the rollback fails too, in which case it
+/// keeps the backups that could not be restored, and later commits leave it alone.
+#[derive(Debug, Clone)]
+pub struct Transaction<P> {
+    staging: P,
+    steps: Vec<Step<P>>,
+}
+
+impl<P> Transaction<P>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    /// Creates an empty transaction that keeps its backups in `staging`.
+    pub fn new(staging: P) -> Self {
+        Self {
+            staging,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Queues renaming `from` to `to`, replacing any file at `to`.
+    pub fn rename(&mut self, from: &P, to: &P) -> &mut Self {
+        self.steps.push(Step::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        });
+        self
+    }
+
+    /// Queues writing `data` to the file at `path`, replacing any file there while keeping
+    /// its permissions.
+    ///
+    /// Writing to a path that is a directory, a symlink or another file that is not a
+    /// regular file fails.
+    pub fn write(&mut self, path: &P, data: impl Into<Vec<u8>>) -> &mut Self {
+        self.steps.push(Step::Write {
+            path: path.clone(),
+            data: data.into(),
+        });
+        self
+    }
+
+    /// Queues removing the file or directory tree at `path`.
+    pub fn remove(&mut self, path: &P) -> &mut Self {
+        self.steps.push(Step::Remove { path: path.clone() });
+        self
+    }
+
+    /// Queues creating the directory at `path`, whose parent must exist by then.
+    pub fn mkdir(&mut self, path: &P, mode: u32) -> &mut Self {
+        self.steps.push(Step::Mkdir {
+            path: path.clone(),
+            mode,
+        });
+        self
+    }
+
+    /// Returns the number of queued operations.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns whether no operation is queued.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Applies the queued operations in order.
+    ///
+    /// If one fails, the operations applied so far are undone in reverse order, and its
+    /// error is returned. Undoing continues past operations that cannot be undone, and if
+    /// there are any, a [RollbackError] with all the errors is returned.
+    pub fn commit(self) -> Result<()> {
+        let created = !self.staging.try_exists()?;
+        self.staging.mkdir(0o700, true, true)?;
+        let dir = self.create_dir()?;
+        let mut undo = Vec::new();
+        let mut staged = Vec::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            if let Err(error) = Self::apply(&dir, index, step, &mut undo, &mut staged) {
+                let rollback = Self::rollback(undo);
+                if !rollback.is_empty() {
+                    return Err(RollbackError::new(error, rollback).into());
+                }
+                self.clean_up(&dir, &staged, created)?;
+                return Err(error);
+            }
+        }
+        self.clean_up(&dir, &staged, created)
+    }
+
+    /// Creates the subdirectory of the staging directory for this commit, with the first
+    /// number that is not taken.
+    fn create_dir(&self) -> Result<P> {
+        let mut number = 0_u64;
+        loop {
+            let dir = self.staging.join(&P::from(number.to_string().as_str()));
+            match dir.mkdir(0o700, false, false) {
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => number += 1,
+                result => return result.map(|()| dir),
+            }
+        }
+    }
+
+    /// Applies `step`, staging files in `dir`, and records how to undo it in `undo` and the
+    /// staging files it uses in `staged`.
+    fn apply(
+        dir: &P,
+        index: usize,
+        step: &Step<P>,
+        undo: &mut Vec<Undo<P>>,
+        staged: &mut Vec<P>,
+    ) -> Result<()> {
+        let backup = dir.join(&P::from(index.to_string().as_str()));
+        match step {
+            Step::Rename { from, to } => {
+                if to.try_exists()? || to.is_symlink() {
+                    staged.push(backup.clone());
+                    let _ = to.replace(&backup)?;
+                    undo.push(Undo::Restore {
+                        path: to.clone(),
+                        backup,
+                    });
+                }
+                let _ = from.replace(to)?;
+                undo.push(Undo::Restore {
+                    path: from.clone(),
+                    backup: to.clone(),
+                });
+            }
+            Step::Write { path, data } => {
+                let new = dir.join(&P::from(format!("{index}.new").as_str()));
+                let existing = match path.symlink_metadata() {
+                    Ok(metadata) if metadata.is_dir() => {
+                        return Err(at(path.as_ref())(ErrorKind::IsADirectory.into()));
+                    }
+                    Ok(metadata) if !metadata.is_file() => {
+                        let error = Error::new(
+                            ErrorKind::InvalidInput,
+                            "Cannot write to a file that is not a regular file",
+                        );
+                        return Err(at(path.as_ref())(error));
+                    }
+                    Ok(_) => Some(path.metadata()?),
+                    Err(err) if err.kind() == ErrorKind::NotFound => None,
+                    Err(err) => return Err(err),
+                };
+                staged.push(new.clone());
+                new.write_bytes(data)?;
+                if let Some(metadata) = existing {
+                    new.chmod(metadata.mode)?;
+                    staged.push(backup.clone());
+                    let _ = path.replace(&backup)?;
+                    undo.push(Undo::Restore {
+                        path: path.clone(),
+                        backup,
+                    });
+                }
+                let _ = new.replace(path)?;
+                undo.push(Undo::Remove(path.clone()));
+            }
+            Step::Remove { path } => {
+                staged.push(backup.clone());
+                let _ = path.replace(&backup)?;
+                undo.push(Undo::Restore {
+                    path: path.clone(),
+                    backup,
+                });
+            }
+            Step::Mkdir { path, mode } => {
+                path.mkdir(*mode, false, false)?;
+                undo.push(Undo::Rmdir(path.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Undoes the operations recorded in `undo`, last first, and returns the errors of
+    /// those that could not be undone.
+    fn rollback(undo: Vec<Undo<P>>) -> Vec<Error> {
+        let mut errors = Vec::new();
+        for undo in undo.into_iter().rev() {
+            let result = match undo {
+                Undo::Restore { path, backup } => backup.replace(&path).map(drop),
+                Undo::Remove(path) => path.unlink(false),
+                Undo::Rmdir(path) => path.rmdir(),
+            };
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+        errors
+    }
+
+    /// Removes the files in `staged` that are still there and the subdirectory `dir` of
+    /// this commit, and the staging directory if it was `created`.
+    fn clean_up(&self, dir: &P, staged: &[P], created: bool) -> Result<()> {
+        for path in staged {
+            if path.try_exists()? || path.is_symlink() {
+                remove_tree(path)?;
+            }
+        }
+        dir.rmdir()?;
+        match created {
+            true => self.staging.rmdir(),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Removes `path`, with its contents if it is a directory.
+fn remove_tree<P>(path: &P) -> Result<()>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    if !path.symlink_metadata()?.is_dir() {
+        return path.unlink(false);
+    }
+    for (dir, _, files) in path.walk().top_down(false) {
+        for file in files {
+            dir.join(&P::from(file.as_str())).unlink(false)?;
+        }
+        dir.rmdir()?;
+    }
+    Ok(())
+}
@@ -1,47 +1,14 @@
-use core::ops::Div;
+use crate::{Component, Flavor, GenericPath, PosixPath, PurePath, String, Unified, WindowsPath};
 
-use crate::pure::ParsablePath;
-use crate::{Component, PosixPath, PurePath, String, ToOwned, WindowsPath};
-
-/// A path for Posix systems.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct UnifiedPath {
-    path: String,
-}
-
-impl ParsablePath for UnifiedPath {
-    const PRIMARY_COMPONENT_SEPARATOR: char = '/';
-    const SECONDARY_COMPONENT_SEPARATOR: Option<char> = None;
-    const EXTENSION_SEPARATOR: char = '.';
-    const DRIVE_SEPARATOR: Option<char> = Some(':');
-    const CURRENT_DIR: &'static str = ".";
-    const PARENT_DIR: &'static str = "..";
-
-    fn as_string_mut(&mut self) -> &mut String {
-        &mut self.path
-    }
-}
-
-impl From<String> for UnifiedPath {
-    fn from(path: String) -> Self {
-        Self { path }
-    }
-}
-
-impl<'a> From<&'a str> for UnifiedPath {
-    fn from(path: &'a str) -> Self {
-        Self {
-            path: path.to_owned(),
-        }
-    }
-}
+/// A path that is parsed the same way on every system.
+pub type UnifiedPath = GenericPath<Unified, String>;
 
 impl<'a> FromIterator<Component<'a>> for UnifiedPath {
     fn from_iter<T: IntoIterator<Item = Component<'a>>>(iter: T) -> Self {
-        const COMPONENT_SEPARATOR: char = UnifiedPath::PRIMARY_COMPONENT_SEPARATOR;
-        const DRIVE_SEPARATOR: char = UnifiedPath::DRIVE_SEPARATOR.unwrap();
-        const CURRENT_DIR: &str = UnifiedPath::CURRENT_DIR;
-        const PARENT_DIR: &str = UnifiedPath::PARENT_DIR;
+        const COMPONENT_SEPARATOR: char = Unified::PRIMARY_COMPONENT_SEPARATOR;
+        const DRIVE_SEPARATOR: char = Unified::DRIVE_SEPARATOR.unwrap();
+        const CURRENT_DIR: &str = Unified::CURRENT_DIR;
+        const PARENT_DIR: &str = Unified::PARENT_DIR;
         let mut path = String::new();
         for component in iter {
             match component {
@@ -72,7 +39,7 @@ impl<'a> FromIterator<Component<'a>> for UnifiedPath {
                 }
             }
         }
-        Self { path }
+        Self::from(path)
     }
 }
 
@@ -87,26 +54,3 @@ impl From<WindowsPath> for UnifiedPath {
         path.components().collect()
     }
 }
-
-impl AsRef<str> for UnifiedPath {
-    fn as_ref(&self) -> &str {
-        &self.path
-    }
-}
-
-impl Div for UnifiedPath {
-    type Output = Self;
-
-    fn div(mut self, rhs: Self) -> <Self as Div>::Output {
-        <Self as PurePath>::join_in_place(&mut self, &rhs);
-        self
-    }
-}
-
-impl Div for &UnifiedPath {
-    type Output = UnifiedPath;
-
-    fn div(self, rhs: Self) -> <Self as Div>::Output {
-        <UnifiedPath as PurePath>::join(self, rhs)
-    }
-}
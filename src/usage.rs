@@ -0,0 +1,126 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::io::{Error, Result};
+use std::rc::Rc;
+
+use crate::{Metadata, Path, PurePath};
+
+/// The space of a filesystem, returned by [`Path::disk_usage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DiskUsage {
+    /// The size of the filesystem in bytes.
+    pub total: u64,
+    /// The number of bytes in use.
+    pub used: u64,
+    /// The number of bytes available to unprivileged users, which excludes those reserved
+    /// for the superuser.
+    pub free: u64,
+}
+
+/// Options for [`Path::tree_size`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SizeOptions {
+    /// Whether the space allocated for files is summed, as by `du`, rather than their
+    /// apparent sizes, as by `du --apparent-size`.
+    pub allocated: bool,
+    /// Whether directories on other filesystems than the root are skipped, as by `du -x`.
+    pub one_file_system: bool,
+    /// Whether the size of each directory in the tree is reported as well.
+    pub breakdown: bool,
+}
+
+/// The size of a directory tree, returned by [`Path::tree_size`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TreeSize<P> {
+    /// The size of the tree in bytes.
+    pub size: u64,
+    /// The number of files counted, including directories and the root.
+    pub files: u64,
+    /// The size of each directory with its contents, including the root, with
+    /// subdirectories before their parents. This is empty unless
+    /// [`SizeOptions::breakdown`] is set.
+    pub dirs: Vec<(P, u64)>,
+}
+
+/// Returns the size of the tree rooted at `root`, as described by [`Path::tree_size`].
+pub(crate) fn tree_size<P>(root: &P, options: &SizeOptions) -> Result<TreeSize<P>>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    let size = |metadata: &Metadata| match options.allocated {
+        true => metadata.blocks * 512,
+        false => metadata.size,
+    };
+    let metadata = root.symlink_metadata()?;
+    if !metadata.is_dir() {
+        return Ok(TreeSize {
+            size: size(&metadata),
+            files: 1,
+            dirs: Vec::new(),
+        });
+    }
+
+    let error = Rc::new(RefCell::new(None::<Error>));
+    let on_error = {
+        let error = error.clone();
+        move |err| {
+            let _ = error.borrow_mut().get_or_insert(err);
+        }
+    };
+    let mut walk = root.walk().on_error(on_error);
+    let mut seen = HashSet::new();
+    // The directories in top-down order, with the index of their parent and their own size.
+    let mut dirs: Vec<(P, Option<usize>, u64)> = Vec::new();
+    let mut parents = HashMap::new();
+    let mut files = 0;
+    while let Some((dir, subdirs, names)) = walk.next() {
+        if error.borrow().is_some() {
+            break;
+        }
+        let index = dirs.len();
+        let mut total = size(&dir.symlink_metadata()?);
+        for name in names {
+            let metadata = dir.join(&P::from(name.as_str())).symlink_metadata()?;
+            if metadata.nlink > 1 && !seen.insert((metadata.dev, metadata.ino)) {
+                continue;
+            }
+            total += size(&metadata);
+            files += 1;
+        }
+        let mut skipped = Vec::new();
+        for name in subdirs {
+            let subdir = dir.join(&P::from(name.as_str()));
+            if options.one_file_system && subdir.symlink_metadata()?.dev != metadata.dev {
+                skipped.push(name);
+            } else {
+                let _ = parents.insert(subdir.as_ref().to_owned(), index);
+            }
+        }
+        walk.prune(|name| !skipped.iter().any(|skipped| skipped == name));
+        let parent = parents.remove(dir.as_ref());
+        dirs.push((dir, parent, total));
+        files += 1;
+    }
+    if let Some(error) = error.borrow_mut().take() {
+        return Err(error);
+    }
+
+    let mut totals: Vec<u64> = dirs.iter().map(|(_, _, size)| *size).collect();
+    for index in (1..dirs.len()).rev() {
+        if let Some(parent) = dirs[index].1 {
+            totals[parent] += totals[index];
+        }
+    }
+    let size = totals.first().copied().unwrap_or_default();
+    let dirs = match options.breakdown {
+        true => dirs
+            .into_iter()
+            .zip(totals)
+            .rev()
+            .map(|((dir, _, _), size)| (dir, size))
+            .collect(),
+        false => Vec::new(),
+    };
+    Ok(TreeSize { size, files, dirs })
+}
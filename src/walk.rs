@@ -0,0 +1,247 @@
+use std::convert::Infallible;
+use std::io::Error;
+use std::rc::Rc;
+
+use crate::error::at;
+use crate::{fs, DirEntry, FileSystem, Path, PurePath};
+
+/// The identity of a directory, used to detect symlink loops.
+type DirId = (u64, u64);
+
+/// A directory that has been read but whose subdirectories are not all walked yet.
+struct Frame<P> {
+    dir: P,
+    dirs: Vec<P>,
+    dir_names: Vec<String>,
+    file_names: Vec<String>,
+    depth: usize,
+    ancestors: Vec<DirId>,
+}
+
+/// A recursive walk over a directory tree, returned by [`Path::walk`].
+///
+/// It yields `(dir, dirs, files)` for each directory, where `dirs` and `files` are the names
+/// of the subdirectories and other entries of `dir`.
+/// Errors are passed to the [`on_error`](Walk::on_error) callback, and ignored without one.
+/// The tree is read from the backend that was current when the walk was created.
+pub struct Walk<P> {
+    top_down: bool,
+    follow_symlinks: bool,
+    max_depth: Option<usize>,
+    on_error: Option<Box<dyn FnMut(Error)>>,
+    root: Option<P>,
+    /// The directories still to read in top-down order, with the next one last.
+    todo: Vec<(P, usize, Vec<DirId>)>,
+    /// The directories read in bottom-up order, with the deepest one last.
+    stack: Vec<Frame<P>>,
+    /// The directory yielded last in top-down order, whose subdirectories may still be pruned.
+    pending: Option<Frame<P>>,
+    /// The backend the tree is read from.
+    fs: Rc<dyn FileSystem>,
+}
+
+impl<P> Walk<P> {
+    /// Creates a walk over the tree rooted at `root`.
+    pub(crate) fn new(root: P) -> Self {
+        Self {
+            top_down: true,
+            follow_symlinks: false,
+            max_depth: None,
+            on_error: None,
+            root: Some(root),
+            todo: Vec::new(),
+            stack: Vec::new(),
+            pending: None,
+            fs: fs::current(),
+        }
+    }
+
+    /// Sets whether a directory is yielded before its subdirectories, which is the default,
+    /// or after them.
+    pub fn top_down(mut self, top_down: bool) -> Self {
+        self.top_down = top_down;
+        self
+    }
+
+    /// Sets whether symlinks to directories are walked into.
+    ///
+    /// They are listed among the files otherwise, which is the default.
+    /// Symlink loops are detected by device and inode where the filesystem reports them,
+    /// and reported as errors.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets the maximum depth to walk into, where the root has depth 0.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets the callback that errors are passed to.
+    pub fn on_error(mut self, on_error: impl FnMut(Error) + 'static) -> Self {
+        self.on_error = Some(Box::new(on_error));
+        self
+    }
+
+    /// Keeps only the subdirectories of the directory yielded last for which `keep` returns
+    /// true, so that the others are not walked into.
+    ///
+    /// This only has an effect in top-down order, before the next call to [`next`](Iterator::next).
+    pub fn prune(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        if let Some(frame) = &mut self.pending {
+            let mut names = frame.dir_names.iter();
+            frame
+                .dirs
+                .retain(|_| names.next().is_some_and(|name| keep(name)));
+        }
+    }
+
+    /// Passes an error to the callback.
+    fn report(&mut self, error: Error) {
+        if let Some(on_error) = &mut self.on_error {
+            on_error(error);
+        }
+    }
+}
+
+impl<P> Walk<P>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    /// Returns the identity of `dir`, if its filesystem reports one.
+    fn dir_id(dir: &P) -> Option<DirId> {
+        let metadata = dir.metadata().ok()?;
+        (metadata.ino != 0).then_some((metadata.dev, metadata.ino))
+    }
+
+    /// Returns whether `entry` should be walked into.
+    fn is_dir(&self, entry: &DirEntry<P>) -> bool {
+        match entry.is_symlink() {
+            true => self.follow_symlinks && entry.path().is_dir(),
+            false => entry.is_dir(),
+        }
+    }
+
+    /// Reads `dir`, or reports why it cannot be read.
+    fn read(&mut self, dir: P, depth: usize, mut ancestors: Vec<DirId>) -> Option<Frame<P>> {
+        if self.follow_symlinks {
+            if let Some(id) = Self::dir_id(&dir) {
+                if ancestors.contains(&id) {
+                    let error = Error::other("Symlink loop detected");
+                    self.report(at(dir.as_ref())(error));
+                    return None;
+                }
+                ancestors.push(id);
+            }
+        }
+        let entries = match dir.scandir() {
+            Ok(entries) => entries,
+            Err(error) => {
+                self.report(error);
+                return None;
+            }
+        };
+        let mut frame = Frame {
+            dir,
+            dirs: Vec::new(),
+            dir_names: Vec::new(),
+            file_names: Vec::new(),
+            depth,
+            ancestors,
+        };
+        for entry in entries {
+            match entry {
+                Ok(entry) if self.is_dir(&entry) => {
+                    frame.dir_names.push(entry.file_name().to_owned());
+                    frame.dirs.push(entry.into_path());
+                }
+                Ok(entry) => frame.file_names.push(entry.file_name().to_owned()),
+                Err(error) => self.report(error),
+            }
+        }
+        Some(frame)
+    }
+
+    /// Returns whether the subdirectories of `frame` are too deep to walk into.
+    fn at_max_depth(&self, frame: &Frame<P>) -> bool {
+        self.max_depth
+            .is_some_and(|max_depth| frame.depth >= max_depth)
+    }
+
+    /// Returns the next directory in top-down order.
+    fn next_top_down(&mut self) -> Option<(P, Vec<String>, Vec<String>)> {
+        if let Some(frame) = self.pending.take() {
+            if !self.at_max_depth(&frame) {
+                for dir in frame.dirs.into_iter().rev() {
+                    self.todo
+                        .push((dir, frame.depth + 1, frame.ancestors.clone()));
+                }
+            }
+        }
+        loop {
+            let (dir, depth, ancestors) = self.todo.pop()?;
+            if let Some(mut frame) = self.read(dir, depth, ancestors) {
+                let file_names = core::mem::take(&mut frame.file_names);
+                let item = (frame.dir.clone(), frame.dir_names.clone(), file_names);
+                self.pending = Some(frame);
+                return Some(item);
+            }
+        }
+    }
+
+    /// Returns the next directory in bottom-up order.
+    fn next_bottom_up(&mut self) -> Option<(P, Vec<String>, Vec<String>)> {
+        loop {
+            let frame = self.stack.last()?;
+            let at_max_depth = self.at_max_depth(frame);
+            let frame = self.stack.last_mut()?;
+            if let (false, Some(dir)) = (at_max_depth, frame.dirs.pop()) {
+                let (depth, ancestors) = (frame.depth + 1, frame.ancestors.clone());
+                if let Some(mut child) = self.read(dir, depth, ancestors) {
+                    child.dirs.reverse();
+                    self.stack.push(child);
+                }
+                continue;
+            }
+            let frame = self.stack.pop()?;
+            return Some((frame.dir, frame.dir_names, frame.file_names));
+        }
+    }
+}
+
+impl<P> Iterator for Walk<P>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    type Item = (P, Vec<String>, Vec<String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        fs::enter(self.fs.clone(), || self.advance())
+    }
+}
+
+impl<P> Walk<P>
+where
+    P: Path + PurePath<Error = Infallible> + Clone + AsRef<str> + for<'a> From<&'a str>,
+{
+    /// Returns the next directory, with the backend of the walk current.
+    fn advance(&mut self) -> Option<(P, Vec<String>, Vec<String>)> {
+        if let Some(root) = self.root.take() {
+            match self.top_down {
+                true => self.todo.push((root, 0, Vec::new())),
+                false => {
+                    if let Some(mut frame) = self.read(root, 0, Vec::new()) {
+                        frame.dirs.reverse();
+                        self.stack.push(frame);
+                    }
+                }
+            }
+        }
+        match self.top_down {
+            true => self.next_top_down(),
+            false => self.next_bottom_up(),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use pathlib::Component::{self, *};
+use pathlib::{
+    ArrayPath, CapacityError, ExpandUserError, ExpandVarsError, Posix, PurePath, Unified, Windows,
+};
+
+const COMPONENTS: &[(&str, &[Component<'static>])] = &[
+    ("/foo/bar", &[Root, Normal("foo"), Normal("bar")]),
+    ("foo", &[Normal("foo")]),
+    ("", &[]),
+    ("/usr/bin/", &[Root, Normal("usr"), Normal("bin")]),
+    ("foo.txt/.//", &[Normal("foo.txt")]),
+    ("/./", &[Root]),
+    ("./..", &[CurDir, ParentDir]),
+    ("/a/..//.//", &[Root, Normal("a"), ParentDir]),
+];
+
+#[test]
+fn components() {
+    for &(path, components) in COMPONENTS {
+        let posix = ArrayPath::<Posix, 32>::try_from(path).unwrap();
+        assert!(
+            posix.components().eq(components.iter().copied()),
+            "{path:?}"
+        );
+        assert!(
+            posix
+                .components()
+                .rev()
+                .eq(components.iter().rev().copied()),
+            "{path:?}"
+        );
+
+        let windows = ArrayPath::<Windows, 32>::try_from(path).unwrap();
+        assert!(
+            windows.components().eq(components.iter().copied()),
+            "{path:?}"
+        );
+
+        let unified = ArrayPath::<Unified, 32>::try_from(path).unwrap();
+        assert!(
+            unified.components().eq(components.iter().copied()),
+            "{path:?}"
+        );
+    }
+}
+
+#[test]
+fn parent_and_file_name() {
+    let path = ArrayPath::<Posix, 16>::try_from("/foo/bar.txt").unwrap();
+    assert_eq!(path.parent().unwrap().as_str(), "/foo");
+    assert_eq!(path.file_name(), Some("bar.txt"));
+    assert_eq!(path.file_stem(), Some("bar"));
+    assert_eq!(path.extension(), Some("txt"));
+    assert!(path.is_absolute());
+    let path = path.try_with_extension("rs").unwrap();
+    assert_eq!(path.as_str(), "/foo/bar.rs");
+    assert_eq!(path.try_with_extension("").unwrap().as_str(), "/foo/bar");
+    let path = ArrayPath::<Posix, 16>::try_from("foo/bar.txt/.").unwrap();
+    assert_eq!(
+        path.try_with_extension("rs").unwrap().as_str(),
+        "foo/bar.rs"
+    );
+}
+
+const JOIN: &[(&str, &str, &str, &str)] = &[
+    ("/foo", "bar", "/foo/bar", "/foo\\bar"),
+    ("/foo", "/bar", "/bar", "/bar"),
+    ("/foo/", "bar", "/foo/bar", "/foo/bar"),
+    ("/foo/", "bar/baz/", "/foo/bar/baz/", "/foo/bar/baz/"),
+];
+
+#[test]
+fn join() {
+    for &(a, b, c, d) in JOIN {
+        let posix = ArrayPath::<Posix, 16>::try_from(a).unwrap();
+        let child = ArrayPath::try_from(b).unwrap();
+        assert_eq!(posix.try_join(&child).unwrap().as_str(), c);
+        let mut pushed = posix;
+        pushed.try_push(b).unwrap();
+        assert_eq!(pushed.as_str(), c);
+
+        let windows = ArrayPath::<Windows, 16>::try_from(a).unwrap();
+        let child = ArrayPath::try_from(b).unwrap();
+        assert_eq!(windows.try_join(&child).unwrap().as_str(), d);
+    }
+}
+
+#[test]
+fn capacity() {
+    assert_eq!(
+        ArrayPath::<Posix, 4>::try_from("/foo/bar"),
+        Err(CapacityError),
+    );
+
+    let mut path = ArrayPath::<Posix, 8>::try_from("/foo").unwrap();
+    assert_eq!(format!("{:?}", path.storage()), "\"/foo\"");
+    assert_eq!(path.try_push("bar"), Ok(()));
+    assert_eq!(path.as_str(), "/foo/bar");
+    assert_eq!(path.try_push("baz"), Err(CapacityError));
+    assert_eq!(path.as_str(), "/foo/bar");
+    assert_eq!(path.try_push("/barbazqux"), Err(CapacityError));
+    assert_eq!(path.as_str(), "/foo/bar");
+    assert_eq!(path.try_with_extension("markdown"), Err(CapacityError));
+
+    let mut path = ArrayPath::<Posix, 8>::try_from("/foo").unwrap();
+    let child = ArrayPath::try_from("bar").unwrap();
+    assert_eq!(path.try_join_in_place(&child), Ok(()));
+    assert_eq!(path.len(), path.capacity());
+    assert_eq!(path.try_join_in_place(&child), Err(CapacityError));
+    assert_eq!(path.as_str(), "/foo/bar");
+}
+
+#[test]
+fn expanduser() {
+    let home = |user: Option<&str>| match user {
+        None => Some("/home/me"),
+        Some("root") => Some("/root"),
+        Some(_) => None,
+    };
+    let path = ArrayPath::<Posix, 16>::try_from("~/notes").unwrap();
+    assert_eq!(
+        path.expanduser_with(home).unwrap().as_str(),
+        "/home/me/notes"
+    );
+    let path = ArrayPath::<Posix, 16>::try_from("~root").unwrap();
+    assert_eq!(path.expanduser_with(home).unwrap().as_str(), "/root");
+    let path = ArrayPath::<Posix, 16>::try_from("~nobody/x").unwrap();
+    assert_eq!(
+        path.expanduser_with(home),
+        Err(ExpandUserError::UnknownUser)
+    );
+    let path = ArrayPath::<Posix, 16>::try_from("~/notes/today").unwrap();
+    assert_eq!(
+        path.expanduser_with(home),
+        Err(ExpandUserError::Capacity(CapacityError))
+    );
+}
+
+#[test]
+fn expand_vars() {
+    let lookup = |name: &str| (name == "HOME").then_some("/home/me");
+    let path = ArrayPath::<Posix, 16>::try_from("$HOME/a").unwrap();
+    assert_eq!(path.expand_vars(lookup).unwrap().as_str(), "/home/me/a");
+    let path = ArrayPath::<Posix, 16>::try_from("$HOME/abcdefgh").unwrap();
+    assert_eq!(
+        path.expand_vars(lookup),
+        Err(ExpandVarsError::Capacity(CapacityError))
+    );
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "alloc")]
+
 #[cfg(feature = "std")]
 use std::{
     ffi::OsStr,
@@ -221,6 +223,9 @@ const JOIN: &[(&str, &str, &str, &str)] = &[
     ("/foo/", "bar/baz", "/foo/bar/baz", "/foo/bar/baz"),
     ("/foo/", "bar/baz/", "/foo/bar/baz/", "/foo/bar/baz/"),
     ("/foo/", "/bar/baz/", "/bar/baz/", "/bar/baz/"),
+    ("", "bar", "bar", "bar"),
+    ("", "bar/baz", "bar/baz", "bar/baz"),
+    ("", "/bar", "/bar", "/bar"),
 ];
 #[test]
 fn join() {
@@ -345,6 +350,107 @@ fn extension() {
     }
 }
 
+const EXPANDUSER: &[(&str, &str, &str)] = &[
+    ("~", "/home/me", "C:\\Users\\me"),
+    ("~/", "/home/me", "C:\\Users\\me"),
+    ("~/a/b", "/home/me/a/b", "C:\\Users\\me\\a/b"),
+    ("~//a", "/home/me/a", "C:\\Users\\me\\a"),
+    ("~bob/a", "/home/bob/a", "C:\\Users\\bob\\a"),
+    ("a/~/b", "a/~/b", "a/~/b"),
+    ("/~", "/~", "/~"),
+];
+
+#[test]
+fn expanduser() {
+    for &(path, posix, windows) in EXPANDUSER {
+        let expanded = PosixPath::from(path).expanduser_with(|user| match user {
+            Some(user) => Some(format!("/home/{user}")),
+            None => Some("/home/me".to_owned()),
+        });
+        assert_eq!(
+            expanded.unwrap().as_str(),
+            posix,
+            "expanduser() of {path:?}"
+        );
+
+        let expanded = WindowsPath::from(path).expanduser_with(|user| match user {
+            Some(user) => Some(format!("C:\\Users\\{user}")),
+            None => Some("C:\\Users\\me".to_owned()),
+        });
+        assert_eq!(
+            expanded.unwrap().as_str(),
+            windows,
+            "expanduser() of {path:?}"
+        );
+    }
+    assert!(UnifiedPath::from("~x")
+        .expanduser_with(|_| None::<&str>)
+        .is_err());
+}
+
+const EXPAND_VARS: &[(&str, &str, &str)] = &[
+    (
+        "${XDG_CACHE_HOME}/app",
+        "/cache/app",
+        "${XDG_CACHE_HOME}/app",
+    ),
+    ("%APPDATA%\\app", "%APPDATA%\\app", "C:\\AppData\\app"),
+    ("$HOME/a$HOME", "/home/a/home", "$HOME/a$HOME"),
+    (
+        "$UNDEFINED/${UNDEFINED}",
+        "$UNDEFINED/${UNDEFINED}",
+        "$UNDEFINED/${UNDEFINED}",
+    ),
+    ("%UNDEFINED%/100%%", "%UNDEFINED%/100%%", "%UNDEFINED%/100%"),
+    ("$/${}/${HOME/50%", "$/${}/${HOME/50%", "$/${}/${HOME/50%"),
+    ("a$HOME_b", "a$HOME_b", "a$HOME_b"),
+];
+
+fn lookup(name: &str) -> Option<&'static str> {
+    match name {
+        "XDG_CACHE_HOME" => Some("/cache"),
+        "APPDATA" => Some("C:\\AppData"),
+        "HOME" => Some("/home"),
+        _ => None,
+    }
+}
+
+#[test]
+fn expand_vars() {
+    use pathlib::{ExpandVarsError, VarOptions, VarSyntax};
+
+    for &(path, posix, windows) in EXPAND_VARS {
+        let expanded = PosixPath::from(path).expand_vars(lookup).unwrap();
+        assert_eq!(expanded.as_str(), posix, "expand_vars() of {path:?}");
+        let expanded = WindowsPath::from(path).expand_vars(lookup).unwrap();
+        assert_eq!(expanded.as_str(), windows, "expand_vars() of {path:?}");
+    }
+
+    let path = UnifiedPath::from("${XDG_CACHE_HOME}/%APPDATA%");
+    assert_eq!(
+        path.expand_vars(lookup).unwrap().as_str(),
+        "/cache/C:\\AppData"
+    );
+    let options = VarOptions {
+        syntax: Some(VarSyntax::WINDOWS),
+        ..VarOptions::default()
+    };
+    assert_eq!(
+        path.expand_vars_with(&options, lookup).unwrap().as_str(),
+        "${XDG_CACHE_HOME}/C:\\AppData"
+    );
+
+    let options = VarOptions {
+        strict: true,
+        ..VarOptions::default()
+    };
+    let path = PosixPath::from("$HOME/$UNDEFINED");
+    assert_eq!(
+        path.expand_vars_with(&options, lookup),
+        Err(ExpandVarsError::Undefined("UNDEFINED"))
+    );
+}
+
 #[cfg(feature = "std")]
 mod fs_ {
     use std::fs;
@@ -467,3 +573,2602 @@ mod is_file {
         assert!(!metadata.is_symlink());
     }
 }
+
+#[cfg(feature = "std")]
+mod mutate {
+    use std::io::ErrorKind;
+
+    use pathlib::{Path, PurePath, UnifiedPath};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn unified() {
+        let dir = UnifiedPath::from("./tmp-mutate");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+
+        let nested = &dir / &UnifiedPath::from("a/b");
+        assert!(nested.mkdir(0o755, false, false).is_err());
+        nested.mkdir(0o755, true, false).unwrap();
+        assert!(nested.is_dir());
+        assert_eq!(
+            nested.mkdir(0o755, true, false).unwrap_err().kind(),
+            ErrorKind::AlreadyExists,
+        );
+        nested.mkdir(0o755, true, true).unwrap();
+
+        let file = &nested / &UnifiedPath::from("foo.txt");
+        file.touch(0o644, true).unwrap();
+        assert!(file.is_file());
+        file.touch(0o644, true).unwrap();
+        assert!(file.touch(0o644, false).is_err());
+
+        let renamed = file
+            .rename(&(&nested / &UnifiedPath::from("bar.txt")))
+            .unwrap();
+        assert!(!file.try_exists().unwrap());
+        assert_eq!(renamed.file_name(), Some("bar.txt"));
+        file.touch(0o644, false).unwrap();
+        let replaced = file.replace(&renamed).unwrap();
+        assert_eq!(replaced, renamed);
+        assert!(!file.try_exists().unwrap());
+
+        let hardlink = &nested / &UnifiedPath::from("hardlink.txt");
+        hardlink.hardlink_to(&renamed).unwrap();
+        assert!(hardlink.is_file());
+
+        #[cfg(unix)]
+        {
+            renamed.chmod(0o600).unwrap();
+            assert_eq!(renamed.metadata().unwrap().mode, 0o600);
+
+            let symlink = &nested / &UnifiedPath::from("symlink.txt");
+            symlink.symlink_to(&UnifiedPath::from("bar.txt")).unwrap();
+            assert!(symlink.is_symlink());
+            assert_eq!(symlink.read_link().unwrap().as_str(), "bar.txt");
+            symlink.unlink(false).unwrap();
+        }
+
+        hardlink.unlink(false).unwrap();
+        renamed.unlink(false).unwrap();
+        assert!(renamed.unlink(false).is_err());
+        renamed.unlink(true).unwrap();
+        assert!(dir.rmdir().is_err());
+        nested.rmdir().unwrap();
+        nested.parent().unwrap().rmdir().unwrap();
+        dir.rmdir().unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod read_write {
+    use std::io::{ErrorKind, Read};
+
+    use pathlib::{
+        Encoding, Newline, OpenOptions, Path, PathError, PurePath, TextOptions, UnifiedPath,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn unified() {
+        let dir = UnifiedPath::from("./tmp-read-write");
+        dir.mkdir(0o755, false, true).unwrap();
+        let file = &dir / &UnifiedPath::from("foo.txt");
+
+        file.write_text("Hello").unwrap();
+        file.append(b", world!").unwrap();
+        assert_eq!(file.read_bytes().unwrap(), b"Hello, world!");
+        assert_eq!(file.read_text().unwrap(), "Hello, world!");
+
+        let mut content = String::new();
+        let _ = file
+            .open(OpenOptions::new().read(true))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "Hello, world!");
+
+        file.write_bytes(b"\xEF\xBB\xBFa\r\nb\rc\n").unwrap();
+        assert_eq!(file.read_text().unwrap(), "\u{FEFF}a\r\nb\rc\n");
+        let options = TextOptions {
+            newline: Newline::Normalize,
+            strip_bom: true,
+            ..TextOptions::default()
+        };
+        assert_eq!(file.read_text_with(&options).unwrap(), "a\nb\nc\n");
+
+        file.write_bytes(b"\xFF\xFEa\x00\r\x00\n\x00").unwrap();
+        let options = TextOptions {
+            encoding: Encoding::Detect,
+            newline: Newline::Normalize,
+            ..TextOptions::default()
+        };
+        assert_eq!(file.read_text_with(&options).unwrap(), "a\n");
+        let options = TextOptions {
+            encoding: Encoding::Utf16Le,
+            strip_bom: true,
+            ..TextOptions::default()
+        };
+        assert_eq!(file.read_text_with(&options).unwrap(), "a\r\n");
+        file.write_bytes(b"\xFF").unwrap();
+        let err = file.read_text().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with(file.as_str()), "{err}");
+        file.unlink(false).unwrap();
+
+        let err = file.read_bytes().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        let inner = err.get_ref().unwrap().downcast_ref::<PathError>().unwrap();
+        assert_eq!(inner.path(), file.as_str());
+        assert_eq!(inner.io_error().kind(), ErrorKind::NotFound);
+        assert_eq!(file.file_name(), Some("foo.txt"));
+        dir.rmdir().unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod iterdir {
+    use std::io::ErrorKind;
+
+    use pathlib::{NonUtf8Error, NonUtf8Policy, Path, PosixPath};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn posix() {
+        let dir = PosixPath::from("./tmp-iterdir");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        (&dir / &PosixPath::from("sub"))
+            .mkdir(0o755, true, false)
+            .unwrap();
+        (&dir / &PosixPath::from("foo.txt"))
+            .touch(0o644, false)
+            .unwrap();
+
+        let mut paths = dir
+            .iterdir()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                PosixPath::from("./tmp-iterdir/foo.txt"),
+                PosixPath::from("./tmp-iterdir/sub"),
+            ],
+        );
+
+        let mut entries = dir
+            .scandir()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        assert_eq!(entries[0].file_name(), "foo.txt");
+        assert!(entries[0].is_file());
+        assert!(entries[0].metadata().unwrap().is_file());
+        assert_eq!(entries[1].path(), PosixPath::from("./tmp-iterdir/sub"));
+        assert!(entries[1].is_dir());
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+
+            let name = OsStr::from_bytes(b"non-utf8-\xFF");
+            std::fs::write(std::path::Path::new(dir.as_str()).join(name), b"").unwrap();
+
+            let errors = dir
+                .iterdir()
+                .unwrap()
+                .filter_map(Result::err)
+                .collect::<Vec<_>>();
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].kind(), ErrorKind::InvalidData);
+            let error = errors[0].get_ref().unwrap().downcast_ref::<NonUtf8Error>();
+            assert_eq!(error.unwrap().path().file_name(), Some(name));
+
+            let paths = dir.iterdir().unwrap().non_utf8(NonUtf8Policy::Skip);
+            assert_eq!(paths.collect::<Result<Vec<_>, _>>().unwrap().len(), 2);
+        }
+
+        assert_eq!(
+            (&dir / &PosixPath::from("missing"))
+                .iterdir()
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound,
+        );
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod walk {
+    use std::cell::RefCell;
+    use std::io::ErrorKind;
+    use std::rc::Rc;
+
+    use pathlib::{Path, PosixPath};
+
+    fn tree(name: &str) -> PosixPath {
+        let dir = PosixPath::from(name);
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        for sub in ["a/aa", "a/ab", "b"] {
+            (&dir / &PosixPath::from(sub))
+                .mkdir(0o755, true, false)
+                .unwrap();
+        }
+        for file in ["f", "a/f", "a/aa/f"] {
+            (&dir / &PosixPath::from(file)).touch(0o644, false).unwrap();
+        }
+        dir
+    }
+
+    fn sorted(mut names: Vec<String>) -> Vec<String> {
+        names.sort();
+        names
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn top_down() {
+        let dir = tree("./tmp-walk-top-down");
+        let walked = dir.walk().collect::<Vec<_>>();
+        assert_eq!(walked.len(), 5);
+        assert_eq!(walked[0].0, dir);
+        assert_eq!(sorted(walked[0].1.clone()), ["a", "b"]);
+        assert_eq!(walked[0].2, ["f"]);
+        let a = walked.iter().position(|(d, ..)| d.as_str().ends_with("/a"));
+        let aa = walked
+            .iter()
+            .position(|(d, ..)| d.as_str().ends_with("/a/aa"));
+        assert!(a.unwrap() < aa.unwrap());
+
+        let mut walk = dir.walk();
+        let mut dirs = Vec::new();
+        while let Some((dir, ..)) = walk.next() {
+            walk.prune(|name| name != "a");
+            dirs.push(dir);
+        }
+        assert_eq!(dirs, [dir.clone(), &dir / &PosixPath::from("b")]);
+
+        assert_eq!(dir.walk().max_depth(0).count(), 1);
+        assert_eq!(dir.walk().max_depth(1).count(), 3);
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn bottom_up() {
+        let dir = tree("./tmp-walk-bottom-up");
+        let walked = dir.walk().top_down(false).collect::<Vec<_>>();
+        assert_eq!(walked.len(), 5);
+        assert_eq!(walked[4].0, dir);
+        let a = walked.iter().position(|(d, ..)| d.as_str().ends_with("/a"));
+        let aa = walked
+            .iter()
+            .position(|(d, ..)| d.as_str().ends_with("/a/aa"));
+        assert!(a.unwrap() > aa.unwrap());
+        let (_, dirs, files) = &walked[a.unwrap()];
+        assert_eq!(sorted(dirs.clone()), ["aa", "ab"]);
+        assert_eq!(files, &["f"]);
+
+        assert_eq!(dir.walk().top_down(false).max_depth(1).count(), 3);
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn errors() {
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let sink = errors.clone();
+        let missing = PosixPath::from("./tmp-walk-missing");
+        let walk = missing
+            .walk()
+            .on_error(move |err| sink.borrow_mut().push(err.kind()));
+        assert_eq!(walk.count(), 0);
+        assert_eq!(*errors.borrow(), [ErrorKind::NotFound]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn symlink_loop() {
+        let dir = tree("./tmp-walk-symlink-loop");
+        let link = &dir / &PosixPath::from("a/aa/loop");
+        link.symlink_to(&PosixPath::from("../..")).unwrap();
+
+        let walked = dir.walk().collect::<Vec<_>>();
+        assert_eq!(walked.len(), 5);
+        let (_, dirs, files) = walked
+            .iter()
+            .find(|(d, ..)| d.as_str().ends_with("/aa"))
+            .unwrap();
+        assert!(dirs.is_empty());
+        assert_eq!(sorted(files.clone()), ["f", "loop"]);
+
+        let errors = Rc::new(RefCell::new(0));
+        let sink = errors.clone();
+        let walk = dir
+            .walk()
+            .follow_symlinks(true)
+            .on_error(move |_| *sink.borrow_mut() += 1);
+        assert_eq!(walk.count(), 5);
+        assert_eq!(*errors.borrow(), 1);
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod glob {
+    use pathlib::{Path, UnifiedPath};
+
+    fn tree(name: &str) -> UnifiedPath {
+        let dir = UnifiedPath::from(name);
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        for sub in ["src/bin", "src/.cache", "docs"] {
+            (&dir / &UnifiedPath::from(sub))
+                .mkdir(0o755, true, false)
+                .unwrap();
+        }
+        for file in [
+            "Cargo.toml",
+            "src/lib.rs",
+            "src/Main.RS",
+            "src/bin/a.rs",
+            "src/bin/b1.rs",
+            "src/.cache/c.rs",
+            "src/.hidden.rs",
+            "docs/x[1].md",
+        ] {
+            (&dir / &UnifiedPath::from(file))
+                .touch(0o644, false)
+                .unwrap();
+        }
+        dir
+    }
+
+    fn names(dir: &UnifiedPath, paths: impl Iterator<Item = UnifiedPath>) -> Vec<String> {
+        let prefix = format!("{}/", dir.as_str());
+        let mut names = paths
+            .map(|path| path.as_str().strip_prefix(&prefix).unwrap().to_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn wildcards() {
+        let dir = tree("./tmp-glob-wildcards");
+        let glob = |pattern| names(&dir, dir.glob(pattern).unwrap());
+        assert_eq!(glob("*.toml"), ["Cargo.toml"]);
+        assert_eq!(glob("src/*.rs"), ["src/lib.rs"]);
+        assert_eq!(glob("src/bin/?.rs"), ["src/bin/a.rs"]);
+        assert_eq!(glob("src/bin/[!a]*"), ["src/bin/b1.rs"]);
+        assert_eq!(glob("src/bin/b[0-9].rs"), ["src/bin/b1.rs"]);
+        assert_eq!(glob("*/bin"), ["src/bin"]);
+        assert_eq!(glob("docs/x[[]1].md"), ["docs/x[1].md"]);
+        assert_eq!(glob("src/.*.rs"), ["src/.hidden.rs"]);
+        assert_eq!(glob("Cargo.toml"), ["Cargo.toml"]);
+        assert!(glob("missing/*").is_empty());
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn recursive() {
+        let dir = tree("./tmp-glob-recursive");
+        let glob = |pattern| names(&dir, dir.glob(pattern).unwrap());
+        assert_eq!(
+            glob("src/**/*.rs"),
+            ["src/bin/a.rs", "src/bin/b1.rs", "src/lib.rs"]
+        );
+        assert_eq!(glob("**/bin"), ["src/bin"]);
+        assert_eq!(glob("**/**/a.rs"), ["src/bin/a.rs"]);
+        assert_eq!(
+            glob("src/**"),
+            [
+                "src",
+                "src/Main.RS",
+                "src/bin",
+                "src/bin/a.rs",
+                "src/bin/b1.rs",
+                "src/lib.rs"
+            ]
+        );
+        assert_eq!(
+            names(&dir, dir.rglob("*.rs").unwrap()),
+            ["src/bin/a.rs", "src/bin/b1.rs", "src/lib.rs"]
+        );
+        assert_eq!(
+            names(&dir, dir.rglob("*.rs").unwrap().include_hidden(true)),
+            [
+                "src/.cache/c.rs",
+                "src/.hidden.rs",
+                "src/bin/a.rs",
+                "src/bin/b1.rs",
+                "src/lib.rs"
+            ]
+        );
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn options() {
+        let dir = tree("./tmp-glob-options");
+        let glob = |pattern| dir.glob(pattern).unwrap().case_sensitive(false);
+        assert_eq!(names(&dir, glob("SRC/*.rs")), ["src/Main.RS", "src/lib.rs"]);
+        assert_eq!(
+            names(&dir, glob("src/[l-m]*")),
+            ["src/Main.RS", "src/lib.rs"]
+        );
+        assert_eq!(
+            names(&dir, glob("src/[K-M]*")),
+            ["src/Main.RS", "src/lib.rs"]
+        );
+
+        #[cfg(unix)]
+        {
+            let link = &dir / &UnifiedPath::from("docs/src");
+            link.symlink_to(&UnifiedPath::from("../src")).unwrap();
+            assert_eq!(
+                names(&dir, dir.glob("docs/**/a.rs").unwrap()),
+                Vec::<String>::new()
+            );
+            assert_eq!(
+                names(
+                    &dir,
+                    dir.glob("docs/**/a.rs").unwrap().follow_symlinks(true)
+                ),
+                ["docs/src/bin/a.rs"]
+            );
+            assert_eq!(
+                names(&dir, dir.glob("docs/*/lib.rs").unwrap()),
+                ["docs/src/lib.rs"]
+            );
+        }
+
+        assert!(dir.glob("/etc/*").is_err());
+        assert!(dir.glob("").is_err());
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod resolve {
+    use pathlib::{Path, PosixPath, PurePath};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn posix() {
+        let dir = PosixPath::from("./tmp-resolve");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        (&dir / &PosixPath::from("real"))
+            .mkdir(0o755, true, false)
+            .unwrap();
+        let real = (&dir / &PosixPath::from("real")).canonicalize().unwrap();
+
+        let missing = &dir / &PosixPath::from("real/missing/../out/file.txt");
+        assert!(missing.resolve(true).is_err());
+        assert_eq!(
+            missing.resolve(false).unwrap(),
+            &real / &PosixPath::from("out/file.txt")
+        );
+        assert_eq!(
+            (&dir / &PosixPath::from("real/./a"))
+                .resolve(false)
+                .unwrap(),
+            &real / &PosixPath::from("a")
+        );
+
+        #[cfg(unix)]
+        {
+            let link = &dir / &PosixPath::from("link");
+            link.symlink_to(&PosixPath::from("real")).unwrap();
+            let through_link = &link / &PosixPath::from("new/file.txt");
+            assert_eq!(
+                through_link.resolve(false).unwrap(),
+                &real / &PosixPath::from("new/file.txt")
+            );
+            assert_eq!(link.resolve(true).unwrap(), real);
+        }
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn absolute() {
+        let current_dir = std::env::current_dir().unwrap();
+        let current_dir = PosixPath::from(current_dir.to_str().unwrap());
+        let relative = PosixPath::from("a/../b");
+        assert_eq!(relative.absolute().unwrap(), &current_dir / &relative);
+        assert!(relative.absolute().unwrap().is_absolute());
+        assert_eq!(PosixPath::from("").absolute().unwrap(), current_dir);
+        let absolute = PosixPath::from("/x/../y");
+        assert_eq!(absolute.absolute().unwrap(), absolute);
+    }
+}
+
+#[cfg(feature = "std")]
+mod home {
+    use pathlib::{Path, PosixPath, UnknownUserError};
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn posix() {
+        let home = std::env::var("HOME").unwrap();
+        assert_eq!(PosixPath::home().unwrap().as_str(), home);
+        assert_eq!(
+            PosixPath::from("~/x").expanduser().unwrap(),
+            PosixPath::from(format!("{home}/x"))
+        );
+        assert_eq!(
+            PosixPath::from("~root").expanduser().unwrap(),
+            PosixPath::from("/root")
+        );
+
+        let err = PosixPath::from("~no-such-user-here/x")
+            .expanduser()
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        let err = err.get_ref().unwrap().downcast_ref::<UnknownUserError>();
+        assert_eq!(err.unwrap().user(), Some("no-such-user-here"));
+    }
+}
+
+#[cfg(feature = "std")]
+mod env_vars {
+    use pathlib::{Path, PosixPath, UndefinedVarError, VarOptions};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn posix() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(
+            PosixPath::from("${PATH}/x")
+                .expand_env_vars(&VarOptions::default())
+                .unwrap(),
+            PosixPath::from(format!("{path}/x"))
+        );
+
+        let options = VarOptions {
+            strict: true,
+            ..VarOptions::default()
+        };
+        let err = PosixPath::from("$PATHLIB_UNDEFINED_VARIABLE")
+            .expand_env_vars(&options)
+            .unwrap_err();
+        let err = err.get_ref().unwrap().downcast_ref::<UndefinedVarError>();
+        assert_eq!(err.unwrap().name(), "PATHLIB_UNDEFINED_VARIABLE");
+    }
+}
+
+#[cfg(feature = "std")]
+mod backend {
+    use std::cell::RefCell;
+    use std::io::{Error, ErrorKind, Result};
+    use std::rc::Rc;
+
+    use pathlib::{
+        with_file_system, FileSystem, Metadata, OpenFile, OpenOptions, Path, PosixPath, ReadDir,
+        StdFileSystem,
+    };
+
+    /// A backend that records the paths it is asked about, and refuses to write.
+    #[derive(Default)]
+    struct ReadOnly {
+        log: RefCell<Vec<String>>,
+    }
+
+    impl ReadOnly {
+        fn log(&self, path: &str) {
+            self.log.borrow_mut().push(path.to_owned());
+        }
+    }
+
+    fn denied<T>() -> Result<T> {
+        Err(Error::new(ErrorKind::PermissionDenied, "read-only"))
+    }
+
+    impl FileSystem for ReadOnly {
+        fn current_dir(&self) -> Result<String> {
+            StdFileSystem.current_dir()
+        }
+
+        fn metadata(&self, path: &str) -> Result<Metadata> {
+            self.log(path);
+            StdFileSystem.metadata(path)
+        }
+
+        fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+            self.log(path);
+            StdFileSystem.symlink_metadata(path)
+        }
+
+        fn canonicalize(&self, path: &str) -> Result<String> {
+            self.log(path);
+            StdFileSystem.canonicalize(path)
+        }
+
+        fn read_dir(&self, path: &str) -> Result<ReadDir> {
+            self.log(path);
+            StdFileSystem.read_dir(path)
+        }
+
+        fn read_link(&self, path: &str) -> Result<String> {
+            self.log(path);
+            StdFileSystem.read_link(path)
+        }
+
+        fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+            self.log(path);
+            match options.writes() || options.create || options.create_new {
+                true => denied(),
+                false => StdFileSystem.open(path, options),
+            }
+        }
+
+        fn create_dir(&self, _: &str, _: u32) -> Result<()> {
+            denied()
+        }
+
+        fn remove_file(&self, _: &str) -> Result<()> {
+            denied()
+        }
+
+        fn remove_dir(&self, _: &str) -> Result<()> {
+            denied()
+        }
+
+        fn rename(&self, _: &str, _: &str) -> Result<()> {
+            denied()
+        }
+
+        fn symlink(&self, _: &str, _: &str) -> Result<()> {
+            denied()
+        }
+
+        fn hard_link(&self, _: &str, _: &str) -> Result<()> {
+            denied()
+        }
+
+        fn set_permissions(&self, _: &str, _: u32) -> Result<()> {
+            denied()
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn swap() {
+        let fs = Rc::new(ReadOnly::default());
+        let path = PosixPath::from("./Cargo.toml");
+        with_file_system(fs.clone(), || {
+            assert!(path.is_file());
+            assert!(path.read_text().unwrap().contains("[package]"));
+            let err = path.write_text("").unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+            let err = PosixPath::from("./tmp-backend").mkdir(0o755, true, false);
+            assert_eq!(err.unwrap_err().kind(), ErrorKind::PermissionDenied);
+            assert!(PosixPath::from("./src")
+                .iterdir()
+                .unwrap()
+                .any(|path| path.unwrap() == PosixPath::from("./src/lib.rs")));
+        });
+        assert_eq!(
+            *fs.log.borrow(),
+            ["./Cargo.toml", "./Cargo.toml", "./Cargo.toml", "./src"]
+        );
+
+        assert!(!PosixPath::from("./tmp-backend").is_dir());
+        assert!(path.metadata().unwrap().is_file());
+        assert_eq!(fs.log.borrow().len(), 4);
+    }
+}
+
+#[cfg(feature = "std")]
+mod memory {
+    use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+
+    use pathlib::{with_file_system, FileSystem, MemoryFileSystem, OpenOptions, Path, PosixPath};
+
+    #[test]
+    fn mutate() {
+        let fs = MemoryFileSystem::new();
+        with_file_system(fs.clone(), || {
+            let dir = PosixPath::from("/tmp/a/b");
+            dir.mkdir(0o755, true, false).unwrap();
+            assert!(dir.is_dir());
+            assert_eq!(
+                PosixPath::from("tmp/..").resolve(true).unwrap().as_str(),
+                "/"
+            );
+
+            let file = &dir / &PosixPath::from("foo.txt");
+            file.write_text("Hello").unwrap();
+            file.append(b", world!").unwrap();
+            assert_eq!(file.read_text().unwrap(), "Hello, world!");
+            let metadata = file.metadata().unwrap();
+            assert_eq!((metadata.size, metadata.mode), (13, 0o644));
+
+            let renamed = file.rename(&PosixPath::from("/tmp/bar.txt")).unwrap();
+            assert!(!file.try_exists().unwrap());
+            assert_eq!(renamed.metadata().unwrap().ino, metadata.ino);
+            let hardlink = PosixPath::from("/tmp/a/hardlink.txt");
+            hardlink.hardlink_to(&renamed).unwrap();
+            assert_eq!(hardlink.metadata().unwrap().ino, metadata.ino);
+            renamed.chmod(0o600).unwrap();
+            assert_eq!(hardlink.metadata().unwrap().mode, 0o600);
+
+            assert_eq!(
+                PosixPath::from("/tmp/a").rmdir().unwrap_err().kind(),
+                ErrorKind::DirectoryNotEmpty
+            );
+            renamed.unlink(false).unwrap();
+            assert_eq!(hardlink.read_text().unwrap(), "Hello, world!");
+        });
+        assert!(!std::path::Path::new("/tmp/a/hardlink.txt").exists());
+        assert_eq!(
+            fs.read_link("/tmp").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn symlinks() {
+        let fs = MemoryFileSystem::new();
+        fs.create_dir("/a", 0o755).unwrap();
+        fs.create_dir("/a/b", 0o755).unwrap();
+        fs.symlink("a/b", "/link").unwrap();
+        fs.symlink("../../missing", "/a/b/dangling").unwrap();
+        fs.symlink("loop", "/loop").unwrap();
+        drop(fs.open("/link/file", OpenOptions::new().write(true).create(true)));
+
+        assert_eq!(fs.canonicalize("/link/../b/./file").unwrap(), "/a/b/file");
+        assert_eq!(fs.canonicalize("link/..").unwrap(), "/a");
+        assert_eq!(fs.read_link("/link").unwrap(), "a/b");
+        assert!(fs.symlink_metadata("/link").unwrap().is_symlink());
+        assert!(fs.metadata("/link").unwrap().is_dir());
+        assert_eq!(
+            fs.metadata("/a/b/dangling").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert!(fs.symlink_metadata("/a/b/dangling").is_ok());
+        assert_eq!(
+            fs.metadata("/link/file/").unwrap_err().kind(),
+            ErrorKind::NotADirectory
+        );
+        assert!(fs
+            .metadata("/loop")
+            .unwrap_err()
+            .to_string()
+            .contains("symbolic links"));
+
+        // Creating through a dangling symlink creates its target.
+        drop(fs.open("/a/b/dangling", OpenOptions::new().write(true).create(true)));
+        assert!(fs.metadata("/missing").unwrap().is_file());
+
+        fs.set_current_dir("/link").unwrap();
+        assert_eq!(fs.current_dir().unwrap(), "/a/b");
+        fs.rename("/a/b", "/c").unwrap();
+        assert_eq!(fs.current_dir().unwrap(), "/c");
+        assert_eq!(
+            fs.rename("/c", "/c/d").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn read_dir() {
+        let fs = MemoryFileSystem::new();
+        with_file_system(fs.clone(), || {
+            for name in ["b", "c", "a"] {
+                PosixPath::from(name).touch(0o644, false).unwrap();
+            }
+            let names = PosixPath::from("/")
+                .iterdir()
+                .unwrap()
+                .map(|path| path.unwrap().as_str().to_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["/a", "/c", "/b"]);
+        });
+        assert_eq!(
+            fs.metadata("/a/x").unwrap_err().kind(),
+            ErrorKind::NotADirectory
+        );
+
+        let _ = fs.set_umask(0);
+        fs.create_dir("/locked", 0o300).unwrap();
+        assert_eq!(
+            fs.read_dir("/locked").err().unwrap().kind(),
+            ErrorKind::PermissionDenied
+        );
+        fs.set_permissions("/locked", 0o500).unwrap();
+        assert_eq!(
+            fs.create_dir("/locked/sub", 0o777).unwrap_err().kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(fs.read_dir("/locked").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn open_file() {
+        let fs = MemoryFileSystem::new();
+        let mut file = fs
+            .open(
+                "/file",
+                OpenOptions::new().read(true).write(true).create(true),
+            )
+            .unwrap();
+        file.write_all(b"Hello, world!").unwrap();
+        let _ = file.seek(SeekFrom::Start(7)).unwrap();
+        let mut content = String::new();
+        let _ = file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "world!");
+
+        // The file stays readable through its handle after it is unlinked.
+        fs.remove_file("/file").unwrap();
+        file.set_len(5).unwrap();
+        file.rewind().unwrap();
+        content.clear();
+        let _ = file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "Hello");
+        assert_eq!(file.metadata().unwrap().size, 5);
+        assert_eq!(
+            fs.open("/file", OpenOptions::new().read(true))
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn escaped_iterators() {
+        let fs = MemoryFileSystem::new();
+        let (walk, glob, mut scan) = with_file_system(fs, || {
+            let dir = PosixPath::from("/no-such-dir-on-disk/sub");
+            dir.mkdir(0o755, true, false).unwrap();
+            (&dir / &PosixPath::from("file.txt"))
+                .touch(0o644, false)
+                .unwrap();
+            let root = PosixPath::from("/no-such-dir-on-disk");
+            (
+                root.walk(),
+                root.rglob("*.txt").unwrap(),
+                root.scandir().unwrap(),
+            )
+        });
+        let dirs = walk
+            .map(|(dir, _, _)| dir.as_str().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(dirs, ["/no-such-dir-on-disk", "/no-such-dir-on-disk/sub"]);
+        assert_eq!(
+            glob.collect::<Vec<_>>(),
+            [PosixPath::from("/no-such-dir-on-disk/sub/file.txt")]
+        );
+        let entry = scan.next().unwrap().unwrap();
+        assert!(entry.metadata().unwrap().is_dir());
+    }
+}
+
+#[cfg(feature = "std")]
+mod overlay {
+    use std::io::ErrorKind;
+    use std::rc::Rc;
+
+    use pathlib::{
+        with_file_system, Change, ChangeKind, FileSystem, MemoryFileSystem, OverlayFileSystem,
+        Path, PosixPath, StdFileSystem,
+    };
+
+    fn change(path: &str, kind: ChangeKind) -> Change {
+        Change {
+            path: path.to_owned(),
+            kind,
+        }
+    }
+
+    fn lower() -> MemoryFileSystem {
+        let fs = MemoryFileSystem::new();
+        with_file_system(fs.clone(), || {
+            PosixPath::from("/src/sub")
+                .mkdir(0o755, true, false)
+                .unwrap();
+            PosixPath::from("/src/lib.rs").write_text("lib").unwrap();
+            PosixPath::from("/src/sub/mod.rs")
+                .write_text("mod")
+                .unwrap();
+            PosixPath::from("/link")
+                .symlink_to(&PosixPath::from("src/sub"))
+                .unwrap();
+        });
+        fs
+    }
+
+    #[test]
+    fn layers() {
+        let overlay = Rc::new(OverlayFileSystem::new(lower()));
+        with_file_system(overlay.clone(), || {
+            let file = PosixPath::from("/link/mod.rs");
+            assert_eq!(file.read_text().unwrap(), "mod");
+            file.write_text("changed").unwrap();
+            assert_eq!(file.read_text().unwrap(), "changed");
+
+            PosixPath::from("/src/lib.rs").unlink(false).unwrap();
+            PosixPath::from("/src/new.rs").touch(0o644, false).unwrap();
+            let mut names = PosixPath::from("/src")
+                .iterdir()
+                .unwrap()
+                .map(|path| path.unwrap().as_str().to_owned())
+                .collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(names, ["/src/new.rs", "/src/sub"]);
+
+            let sub = PosixPath::from("/src/sub");
+            let err = sub.rmdir().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::DirectoryNotEmpty);
+            let moved = sub.rename(&PosixPath::from("/moved")).unwrap();
+            assert_eq!(
+                PosixPath::from("/moved/mod.rs").read_text().unwrap(),
+                "changed"
+            );
+            assert!(!sub.try_exists().unwrap());
+            assert_eq!(
+                PosixPath::from("/link").metadata().unwrap_err().kind(),
+                ErrorKind::NotFound
+            );
+            moved.mkdir(0o755, false, true).unwrap();
+        });
+
+        let lower = overlay.lower();
+        assert_eq!(lower.read_link("/link").unwrap(), "src/sub");
+        assert!(lower.metadata("/src/lib.rs").is_ok());
+        assert!(lower.metadata("/moved").is_err());
+        assert_eq!(
+            overlay.changes().unwrap(),
+            [
+                change("/moved", ChangeKind::Added),
+                change("/moved/mod.rs", ChangeKind::Added),
+                change("/src/lib.rs", ChangeKind::Removed),
+                change("/src/new.rs", ChangeKind::Added),
+                change("/src/sub", ChangeKind::Removed),
+            ]
+        );
+
+        overlay.commit().unwrap();
+        assert!(overlay.changes().unwrap().is_empty());
+        with_file_system(lower.clone(), || {
+            assert_eq!(
+                PosixPath::from("/moved/mod.rs").read_text().unwrap(),
+                "changed"
+            );
+            assert!(PosixPath::from("/src/new.rs").is_file());
+            assert!(!PosixPath::from("/src/lib.rs").try_exists().unwrap());
+            assert!(!PosixPath::from("/src/sub").try_exists().unwrap());
+        });
+    }
+
+    #[test]
+    fn opaque() {
+        let overlay = OverlayFileSystem::new(lower());
+        overlay.remove_file("/src/sub/mod.rs").unwrap();
+        overlay.remove_dir("/src/sub").unwrap();
+        overlay.create_dir("/src/sub", 0o700).unwrap();
+        assert_eq!(overlay.read_dir("/src/sub").unwrap().count(), 0);
+        assert_eq!(overlay.metadata("/link").unwrap().mode, 0o700);
+        assert_eq!(overlay.canonicalize("/link/..").unwrap(), "/src");
+        assert_eq!(
+            overlay.changes().unwrap(),
+            [
+                change("/src/sub", ChangeKind::Removed),
+                change("/src/sub", ChangeKind::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_only_lower() {
+        let lower = lower();
+        lower.set_permissions("/src", 0o555).unwrap();
+        let overlay = Rc::new(OverlayFileSystem::new(lower));
+        with_file_system(overlay.clone(), || {
+            PosixPath::from("/src/sub/new.rs")
+                .write_text("new")
+                .unwrap();
+            PosixPath::from("/src/lib.rs")
+                .write_text("changed")
+                .unwrap();
+            let err = PosixPath::from("/src/new.rs")
+                .touch(0o644, false)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+            assert_eq!(PosixPath::from("/src").metadata().unwrap().mode, 0o555);
+        });
+        assert_eq!(
+            overlay.changes().unwrap(),
+            [
+                change("/src/lib.rs", ChangeKind::Modified),
+                change("/src/sub/new.rs", ChangeKind::Added),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn disk() {
+        let overlay = Rc::new(OverlayFileSystem::new(StdFileSystem));
+        let cwd = StdFileSystem.current_dir().unwrap();
+        with_file_system(overlay.clone(), || {
+            let path = PosixPath::from("./Cargo.toml");
+            path.append(b"\n# overlay\n").unwrap();
+            assert!(path.read_text().unwrap().ends_with("# overlay\n"));
+            PosixPath::from("./tmp-overlay/a")
+                .mkdir(0o755, true, false)
+                .unwrap();
+        });
+        assert!(!std::fs::read_to_string("Cargo.toml")
+            .unwrap()
+            .contains("# overlay"));
+        assert!(!std::path::Path::new("tmp-overlay").exists());
+        assert_eq!(
+            overlay.changes().unwrap(),
+            [
+                change(&format!("{cwd}/Cargo.toml"), ChangeKind::Modified),
+                change(&format!("{cwd}/tmp-overlay"), ChangeKind::Added),
+                change(&format!("{cwd}/tmp-overlay/a"), ChangeKind::Added),
+            ]
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+mod case_insensitive {
+    use std::io::ErrorKind;
+
+    use pathlib::{
+        with_file_system, CaseInsensitiveFileSystem, FileSystem, MemoryFileSystem, Path, PosixPath,
+    };
+
+    #[test]
+    fn lookups() {
+        let fs = CaseInsensitiveFileSystem::new(MemoryFileSystem::new());
+        with_file_system(fs.clone(), || {
+            PosixPath::from("/Src/Main.RS")
+                .touch(0o644, false)
+                .unwrap_err();
+            PosixPath::from("/Src").mkdir(0o755, false, false).unwrap();
+            PosixPath::from("/src/Main.RS").write_text("main").unwrap();
+            assert_eq!(PosixPath::from("/SRC/main.rs").read_text().unwrap(), "main");
+            assert_eq!(
+                PosixPath::from("/sRc/MAIN.rs")
+                    .resolve(true)
+                    .unwrap()
+                    .as_str(),
+                "/Src/Main.RS"
+            );
+            let err = PosixPath::from("/SRC")
+                .mkdir(0o755, false, false)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+            let names = PosixPath::from("/src")
+                .iterdir()
+                .unwrap()
+                .map(|path| path.unwrap().as_str().to_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["/src/Main.RS"]);
+
+            // Renaming to another spelling of the same name changes the spelling.
+            let renamed = PosixPath::from("/src/main.rs")
+                .rename(&PosixPath::from("/SRC/main.rs"))
+                .unwrap();
+            assert!(renamed.is_file());
+            assert_eq!(
+                PosixPath::from("/src/MAIN.RS")
+                    .resolve(true)
+                    .unwrap()
+                    .as_str(),
+                "/Src/main.rs"
+            );
+            // Names are folded to their simple uppercase as on NTFS, which keeps `ß`.
+            PosixPath::from("/src/ß").touch(0o644, false).unwrap();
+            assert!(!PosixPath::from("/src/SS").try_exists().unwrap());
+            assert!(!PosixPath::from("/src/ẞ").try_exists().unwrap());
+            PosixPath::from("/src/ǆ").touch(0o644, false).unwrap();
+            assert!(PosixPath::from("/src/Ǆ").is_file());
+        });
+        assert!(fs.inner().metadata("/Src/main.rs").unwrap().is_file());
+        assert!(fs.inner().metadata("/src").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "normalization")]
+    fn normalization() {
+        let fs = CaseInsensitiveFileSystem::new(MemoryFileSystem::new());
+        drop(fs.open(
+            "/caf\u{E9}",
+            pathlib::OpenOptions::new().write(true).create(true),
+        ));
+        assert!(fs.metadata("/CAFE\u{301}").is_err());
+        let fs = fs.normalization_insensitive(true);
+        assert_eq!(fs.canonicalize("/CAFE\u{301}").unwrap(), "/caf\u{E9}");
+    }
+}
+
+#[cfg(feature = "std")]
+mod fault {
+    use std::io::{ErrorKind, Write};
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use pathlib::{
+        with_file_system, Fault, FaultRule, FaultyFileSystem, FileSystem, MemoryFileSystem,
+        OpenOptions, Operation, Path, PathError, PosixPath,
+    };
+
+    #[test]
+    fn rules() {
+        let fs = Rc::new(FaultyFileSystem::new(MemoryFileSystem::new()));
+        fs.inject(
+            FaultRule::new(Fault::Error(ErrorKind::StorageFull))
+                .operation(Operation::Write)
+                .path("*.log")
+                .skip(1)
+                .times(1),
+        );
+        fs.inject(
+            FaultRule::new(Fault::Error(ErrorKind::NotFound))
+                .operation(Operation::Metadata)
+                .path("/gone"),
+        );
+        with_file_system(fs.clone(), || {
+            let log = PosixPath::from("/app.log");
+            log.write_text("first").unwrap();
+            let err = log.append(b"second").unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::StorageFull);
+            log.append(b", third").unwrap();
+            assert_eq!(log.read_text().unwrap(), "first, third");
+            PosixPath::from("/data.txt").write_text("data").unwrap();
+
+            let gone = PosixPath::from("/gone");
+            gone.touch(0o644, false).unwrap();
+            assert!(gone.symlink_metadata().is_ok());
+            assert_eq!(gone.metadata().unwrap_err().kind(), ErrorKind::NotFound);
+            assert!(!gone.is_file());
+        });
+
+        fs.clear();
+        assert!(fs.metadata("/gone").is_ok());
+        fs.inject(FaultRule::new(Fault::Error(ErrorKind::PermissionDenied)).path("/locked/*"));
+        fs.create_dir("/locked", 0o755).unwrap();
+        let err = fs.rename("/gone", "/locked/gone").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert!(fs.inner().metadata("/gone").is_ok());
+
+        fs.clear();
+        for kind in [ErrorKind::NotFound, ErrorKind::TimedOut] {
+            fs.inject(
+                FaultRule::new(Fault::Error(kind))
+                    .operation(Operation::Metadata)
+                    .times(1),
+            );
+        }
+        assert_eq!(fs.metadata("/").unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(fs.metadata("/").unwrap_err().kind(), ErrorKind::TimedOut);
+        assert!(fs.metadata("/").is_ok());
+        #[cfg(unix)]
+        {
+            fs.inject(FaultRule::new(Fault::Os(5)).operation(Operation::ReadDir));
+            let err = fs.read_dir("/").err().unwrap();
+            assert_eq!(err.raw_os_error(), Some(5));
+            with_file_system(fs.clone(), || {
+                let err = PosixPath::from("/").iterdir().err().unwrap();
+                let err = err.get_ref().unwrap().downcast_ref::<PathError>().unwrap();
+                assert_eq!(err.raw_os_error(), Some(5));
+            });
+        }
+    }
+
+    #[test]
+    fn partial_writes() {
+        let fs = FaultyFileSystem::new(MemoryFileSystem::new());
+        fs.inject(FaultRule::new(Fault::PartialWrite(2)));
+        let options = OpenOptions::new().write(true).create(true).to_owned();
+        let mut file = fs.open("/file", &options).unwrap();
+        assert_eq!(file.write(b"Hello").unwrap(), 2);
+        file.write_all(b"llo").unwrap();
+        assert_eq!(file.metadata().unwrap().size, 5);
+
+        fs.clear();
+        fs.inject(FaultRule::new(Fault::PartialWrite(0)).times(1));
+        let mut file = fs.open("/file", OpenOptions::new().append(true)).unwrap();
+        let err = file.write_all(b"!").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        file.write_all(b"!").unwrap();
+
+        fs.inject(
+            FaultRule::new(Fault::Delay(Duration::from_millis(5))).operation(Operation::Metadata),
+        );
+        let start = Instant::now();
+        assert_eq!(fs.metadata("/file").unwrap().size, 6);
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn seeded() {
+        let outcomes = |seed| {
+            let fs = FaultyFileSystem::new(MemoryFileSystem::new()).seed(seed);
+            fs.inject(FaultRule::new(Fault::Error(ErrorKind::Other)).probability(0.5));
+            (0..64)
+                .map(|_| fs.metadata("/").is_ok())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(outcomes(7), outcomes(7));
+        assert_ne!(outcomes(7), outcomes(8));
+        let successes = outcomes(7).into_iter().filter(|ok| *ok).count();
+        assert!((16..48).contains(&successes), "{successes}");
+    }
+}
+
+#[cfg(feature = "std")]
+mod audit {
+    use std::cell::RefCell;
+    use std::io::ErrorKind;
+    use std::rc::Rc;
+
+    use pathlib::{
+        with_file_system, AuditEvent, AuditFileSystem, FileSystem, MemoryFileSystem, Path,
+        PosixPath, PurePath,
+    };
+
+    fn run() {
+        let dir = PosixPath::from("/app/conf");
+        dir.mkdir(0o755, true, true).unwrap();
+        let file = &dir / &PosixPath::from("app.toml");
+        file.write_text("debug = true").unwrap();
+        file.chmod(0o600).unwrap();
+        let _ = file.rename(&PosixPath::from("/app/app.toml")).unwrap();
+        PosixPath::from("/app/app.toml").unlink(true).unwrap();
+    }
+
+    #[test]
+    fn events() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let fs = Rc::new(AuditFileSystem::new(
+            MemoryFileSystem::new(),
+            move |event| sink.borrow_mut().push(event.clone()),
+        ));
+        with_file_system(fs.clone(), run);
+        assert!(fs.inner().metadata("/app/conf").unwrap().is_dir());
+        assert!(fs.inner().metadata("/app/app.toml").is_err());
+        let events = events.borrow();
+        assert_eq!(
+            events[0],
+            AuditEvent::CreateDir {
+                path: "/app".to_owned(),
+                mode: 0o777
+            }
+        );
+        let lines = events.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "mkdir -m 777 /app",
+                "mkdir -m 755 /app/conf",
+                "write /app/conf/app.toml",
+                "chmod 600 /app/conf/app.toml",
+                "mv /app/conf/app.toml /app/app.toml",
+                "rm /app/app.toml",
+            ]
+        );
+    }
+
+    #[test]
+    fn dry_run() {
+        let lines = Rc::new(RefCell::new(Vec::new()));
+        let sink = lines.clone();
+        let fs = Rc::new(
+            AuditFileSystem::new(MemoryFileSystem::new(), move |event| {
+                sink.borrow_mut().push(event.to_string())
+            })
+            .dry_run(true),
+        );
+        fs.inner().create_dir("/app", 0o755).unwrap();
+        with_file_system(fs.clone(), || {
+            let file = PosixPath::from("/app/app.toml");
+            file.write_text("debug = true").unwrap();
+            assert!(file.try_exists().unwrap());
+            assert!(fs.inner().metadata("/app/app.toml").is_err());
+            let err = PosixPath::from("/app").rmdir().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::DirectoryNotEmpty);
+            file.unlink(false).unwrap();
+            file.unlink(true).unwrap();
+            PosixPath::from("/app").rmdir().unwrap();
+            assert!(!PosixPath::from("/app").is_dir());
+            assert!(fs.inner().metadata("/app").unwrap().is_dir());
+
+            PosixPath::from("/a/b/c").mkdir(0o700, true, false).unwrap();
+            let err = PosixPath::from("/a/b").mkdir(0o700, false, false);
+            assert_eq!(err.unwrap_err().kind(), ErrorKind::AlreadyExists);
+            let err = PosixPath::from("/x/y").write_text("");
+            assert_eq!(err.unwrap_err().kind(), ErrorKind::NotFound);
+            PosixPath::from("/a/it's here").write_text("").unwrap();
+        });
+        assert_eq!(
+            *lines.borrow(),
+            [
+                "write /app/app.toml",
+                "rm /app/app.toml",
+                "rmdir /app",
+                "mkdir -m 777 /a",
+                "mkdir -m 777 /a/b",
+                "mkdir -m 700 /a/b/c",
+                "write '/a/it'\"'\"'s here'",
+            ]
+        );
+    }
+
+    fn deploy() {
+        for dir in ["out/conf/", "./out/conf"] {
+            PosixPath::from(dir).mkdir(0o755, true, true).unwrap();
+        }
+        let conf = PosixPath::from("/out/conf/app.toml");
+        if !conf.try_exists().unwrap() {
+            conf.write_text("debug = true").unwrap();
+        }
+        PosixPath::from("/out/conf/old.toml")
+            .hardlink_to(&PosixPath::from("/etc/app.toml"))
+            .unwrap();
+        PosixPath::from("/out/current")
+            .symlink_to(&PosixPath::from("conf"))
+            .unwrap();
+        assert!(PosixPath::from("/out/current/old.toml").is_file());
+        let etc = PosixPath::from("/out/conf")
+            .rename(&PosixPath::from("/out/etc"))
+            .unwrap();
+        assert!(!PosixPath::from("/out/current").try_exists().unwrap());
+        let mut names = etc
+            .iterdir()
+            .unwrap()
+            .map(|path| path.unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        for path in &names {
+            path.chmod(0o600).unwrap();
+            if path.file_name() == Some("old.toml") {
+                path.unlink(false).unwrap();
+            }
+        }
+        let err = etc.rmdir().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DirectoryNotEmpty);
+        PosixPath::from("/etc/app.toml").unlink(false).unwrap();
+        PosixPath::from("/etc").rmdir().unwrap();
+        assert!(!PosixPath::from("/etc").try_exists().unwrap());
+        etc.rename(&PosixPath::from("/etc")).unwrap();
+        assert_eq!(
+            PosixPath::from("/etc/app.toml").metadata().unwrap().mode,
+            0o600
+        );
+        PosixPath::from("/out/current").unlink(false).unwrap();
+        PosixPath::from("/out").rmdir().unwrap();
+    }
+
+    #[test]
+    fn dry_run_matches_run() {
+        let events = |dry_run| {
+            let lines = Rc::new(RefCell::new(Vec::new()));
+            let sink = lines.clone();
+            let inner = MemoryFileSystem::new();
+            inner.create_dir("/etc", 0o755).unwrap();
+            with_file_system(inner.clone(), || {
+                PosixPath::from("/etc/app.toml").write_text("").unwrap();
+            });
+            let fs = AuditFileSystem::new(inner, move |event| {
+                sink.borrow_mut().push(event.to_string())
+            })
+            .dry_run(dry_run);
+            with_file_system(fs, deploy);
+            lines.take()
+        };
+        let run = events(false);
+        assert_eq!(
+            run,
+            [
+                "mkdir -m 777 out",
+                "mkdir -m 755 out/conf/",
+                "write /out/conf/app.toml",
+                "ln /etc/app.toml /out/conf/old.toml",
+                "ln -s conf /out/current",
+                "mv /out/conf /out/etc",
+                "chmod 600 /out/etc/app.toml",
+                "chmod 600 /out/etc/old.toml",
+                "rm /out/etc/old.toml",
+                "rm /etc/app.toml",
+                "rmdir /etc",
+                "mv /out/etc /etc",
+                "rm /out/current",
+                "rmdir /out",
+            ]
+        );
+        assert_eq!(events(true), run);
+    }
+}
+
+#[cfg(feature = "std")]
+mod transaction {
+    use std::io::ErrorKind;
+    use std::rc::Rc;
+
+    use pathlib::{
+        with_file_system, Fault, FaultRule, FaultyFileSystem, MemoryFileSystem, Operation, Path,
+        PosixPath, RollbackError, Transaction,
+    };
+
+    fn path(path: &str) -> PosixPath {
+        PosixPath::from(path)
+    }
+
+    fn setup() {
+        path("/data/old").mkdir(0o755, true, false).unwrap();
+        path("/data/old/file").write_text("old").unwrap();
+        path("/data/a").write_text("a").unwrap();
+        path("/data/b").write_text("b").unwrap();
+        path("/data/b").chmod(0o600).unwrap();
+    }
+
+    #[test]
+    fn commit() {
+        with_file_system(MemoryFileSystem::new(), || {
+            setup();
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction
+                .rename(&path("/data/a"), &path("/data/c"))
+                .write(&path("/data/b"), "new")
+                .remove(&path("/data/old"))
+                .mkdir(&path("/data/new"), 0o755);
+            assert_eq!(transaction.len(), 4);
+            transaction.commit().unwrap();
+
+            assert!(!path("/data/a").try_exists().unwrap());
+            assert_eq!(path("/data/c").read_text().unwrap(), "a");
+            assert_eq!(path("/data/b").read_text().unwrap(), "new");
+            assert_eq!(path("/data/b").metadata().unwrap().mode, 0o600);
+            assert!(!path("/data/old").try_exists().unwrap());
+            assert!(path("/data/new").is_dir());
+            assert!(!path("/staging").try_exists().unwrap());
+        });
+    }
+
+    #[test]
+    fn rollback() {
+        with_file_system(MemoryFileSystem::new(), || {
+            setup();
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction
+                .rename(&path("/data/a"), &path("/data/b"))
+                .write(&path("/data/c"), "c")
+                .remove(&path("/data/old"))
+                .mkdir(&path("/data/new"), 0o755)
+                .rename(&path("/data/missing"), &path("/data/d"));
+            let err = transaction.commit().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+
+            assert_eq!(path("/data/a").read_text().unwrap(), "a");
+            assert_eq!(path("/data/b").read_text().unwrap(), "b");
+            assert!(!path("/data/c").try_exists().unwrap());
+            assert_eq!(path("/data/old/file").read_text().unwrap(), "old");
+            assert!(!path("/data/new").try_exists().unwrap());
+            assert!(!path("/staging").try_exists().unwrap());
+        });
+    }
+
+    #[test]
+    fn failed_rollback() {
+        let fs = Rc::new(FaultyFileSystem::new(MemoryFileSystem::new()));
+        with_file_system(fs.clone(), || {
+            setup();
+            fs.inject(
+                FaultRule::new(Fault::Error(ErrorKind::PermissionDenied))
+                    .operation(Operation::Rename)
+                    .path("/data/c"),
+            );
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction
+                .remove(&path("/data/a"))
+                .mkdir(&path("/data/new"), 0o755)
+                .rename(&path("/data/b"), &path("/data/c"));
+            let err = transaction.commit().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+            let inner = err.get_ref().unwrap();
+            assert!(inner.downcast_ref::<RollbackError>().is_none());
+            assert_eq!(path("/data/a").read_text().unwrap(), "a");
+
+            fs.inject(
+                FaultRule::new(Fault::Error(ErrorKind::StorageFull))
+                    .operation(Operation::Rename)
+                    .path("/staging/0/0"),
+            );
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction
+                .remove(&path("/data/old"))
+                .mkdir(&path("/data/new"), 0o755);
+            let err = transaction.commit().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::StorageFull);
+        });
+
+        fs.clear();
+        with_file_system(fs.clone(), || {
+            fs.inject(
+                FaultRule::new(Fault::Error(ErrorKind::PermissionDenied))
+                    .operation(Operation::RemoveDir)
+                    .path("/data/new"),
+            );
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction
+                .write(&path("/data/a"), "changed")
+                .mkdir(&path("/data/new"), 0o755)
+                .remove(&path("/data/missing"));
+            let err = transaction.commit().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+            let err = err
+                .get_ref()
+                .unwrap()
+                .downcast_ref::<RollbackError>()
+                .unwrap();
+            let kinds = err.rollback_errors().iter().map(|err| err.kind());
+            assert!(kinds.eq([ErrorKind::PermissionDenied]));
+            // The rollback goes on past the directory it cannot remove.
+            assert_eq!(path("/data/a").read_text().unwrap(), "a");
+            assert!(path("/data/new").is_dir());
+            assert!(path("/staging/0").is_dir());
+
+            // A later commit stages its files elsewhere, and leaves those kept alone.
+            path("/staging/0/kept").write_text("kept").unwrap();
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction.write(&path("/data/a"), "changed");
+            transaction.commit().unwrap();
+            assert_eq!(path("/data/a").read_text().unwrap(), "changed");
+            assert_eq!(path("/staging/0/kept").read_text().unwrap(), "kept");
+            assert!(!path("/staging/1").try_exists().unwrap());
+        });
+    }
+
+    #[test]
+    fn write_non_regular() {
+        with_file_system(MemoryFileSystem::new(), || {
+            setup();
+            path("/data/link").symlink_to(&path("/data/a")).unwrap();
+            path("/data/old/sub").mkdir(0o755, false, false).unwrap();
+            path("/data/old/sub/keep").write_text("keep").unwrap();
+
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction.write(&path("/data/link"), "new");
+            let err = transaction.commit().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert!(path("/data/link").is_symlink());
+            assert_eq!(path("/data/a").read_text().unwrap(), "a");
+
+            let mut transaction = Transaction::new(path("/staging"));
+            let _ = transaction.write(&path("/data/old"), "new");
+            let err = transaction.commit().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::IsADirectory);
+            assert_eq!(path("/data/old/sub/keep").read_text().unwrap(), "keep");
+            assert!(!path("/staging").try_exists().unwrap());
+        });
+    }
+}
+
+#[cfg(feature = "std")]
+mod metadata {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pathlib::{
+        with_file_system, FileType, MemoryFileSystem, Metadata, Path, PosixPath, Timestamp,
+    };
+
+    #[test]
+    fn timestamps() {
+        let before = UNIX_EPOCH - Duration::new(1, 500_000_000);
+        let timestamp = Timestamp::from(before);
+        assert_eq!(timestamp, Timestamp::new(-2, 500_000_000));
+        assert_eq!(timestamp.to_system_time(), Some(before));
+        let after = UNIX_EPOCH + Duration::new(1, 500_000_000);
+        assert_eq!(Timestamp::from(after), Timestamp::new(0, 1_500_000_000));
+        assert!(timestamp < Timestamp::from(UNIX_EPOCH));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn std() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = PosixPath::from("./tmp-metadata");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        dir.mkdir(0o755, false, false).unwrap();
+        let file = &dir / &PosixPath::from("file");
+        file.write_text("Hello").unwrap();
+        (&dir / &PosixPath::from("link"))
+            .hardlink_to(&file)
+            .unwrap();
+
+        let expected = std::fs::metadata(file.as_str()).unwrap();
+        let metadata = file.metadata().unwrap();
+        assert_eq!(metadata, Metadata::from(expected.clone()));
+        assert_eq!(
+            (metadata.uid, metadata.gid),
+            (expected.uid(), expected.gid())
+        );
+        assert_eq!(metadata.nlink, 2);
+        assert_eq!(
+            metadata.atime,
+            expected.accessed().ok().map(Timestamp::from)
+        );
+        let ctime = Timestamp::new(expected.ctime(), expected.ctime_nsec() as u32);
+        assert_eq!(metadata.ctime, Some(ctime));
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    fn memory() {
+        with_file_system(MemoryFileSystem::new(), || {
+            let file = PosixPath::from("/file");
+            file.write_text("Hello").unwrap();
+            let metadata = file.metadata().unwrap();
+            assert_eq!((metadata.uid, metadata.gid, metadata.nlink), (0, 0, 1));
+            assert!(metadata.btime.is_some() && metadata.btime <= metadata.ctime);
+
+            PosixPath::from("/link").hardlink_to(&file).unwrap();
+            let linked = file.metadata().unwrap();
+            assert_eq!(linked.nlink, 2);
+            assert!(linked.ctime >= metadata.ctime);
+            assert_eq!(linked.btime, metadata.btime);
+        });
+
+        let metadata = Metadata {
+            file_type: FileType::File,
+            size: 5,
+            nlink: 1,
+            ..Metadata::default()
+        };
+        assert!(metadata.is_file() && metadata.atime.is_none());
+    }
+}
+
+#[cfg(feature = "std")]
+mod file_types {
+    use pathlib::{with_file_system, MemoryFileSystem, Path, PosixPath};
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn std() {
+        let null = PosixPath::from("/dev/null");
+        assert!(null.is_char_device());
+        assert!(!null.is_block_device() && !null.is_fifo() && !null.is_file());
+        assert!(!null.is_mount() && !null.is_junction());
+        assert!(PosixPath::from("/").is_mount());
+
+        let dir = PosixPath::from("./tmp-file-types");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        dir.mkdir(0o755, false, false).unwrap();
+        assert!(!dir.is_mount());
+        let socket = &dir / &PosixPath::from("socket");
+        let _listener = std::os::unix::net::UnixListener::bind(socket.as_str()).unwrap();
+        assert!(socket.is_socket() && !socket.is_fifo());
+
+        let file = &dir / &PosixPath::from("file");
+        file.touch(0o644, false).unwrap();
+        let link = &dir / &PosixPath::from("link");
+        link.symlink_to(&PosixPath::from("file")).unwrap();
+        assert!(link.samefile(&file).unwrap());
+        assert!(!link.samefile(&dir).unwrap());
+        assert!(file.samefile(&dir).is_ok());
+        assert!(file.samefile(&PosixPath::from("missing")).is_err());
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    #[cfg(windows)]
+    #[cfg_attr(miri, ignore)]
+    fn junction() {
+        use pathlib::{FileSystem, StdFileSystem, WindowsPath};
+
+        let dir = WindowsPath::from(".\\tmp-junction");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        let target = &dir / &WindowsPath::from("target");
+        target.mkdir(0o755, true, false).unwrap();
+        let junction = &dir / &WindowsPath::from("junction");
+        let status = std::process::Command::new("cmd")
+            .args(["/C", "mklink", "/J", junction.as_str(), target.as_str()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(junction.is_junction() && junction.is_dir());
+        assert!(!target.is_junction() && !dir.is_junction());
+        let entries = StdFileSystem.read_dir(dir.as_str()).unwrap();
+        let junctions = entries.filter(|entry| entry.as_ref().unwrap().file_type.is_junction());
+        assert_eq!(junctions.count(), 1);
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    fn memory() {
+        with_file_system(MemoryFileSystem::new(), || {
+            let dir = PosixPath::from("/tmp");
+            dir.mkdir(0o755, false, false).unwrap();
+            let file = &dir / &PosixPath::from("file");
+            file.write_text("Hello").unwrap();
+            assert!(PosixPath::from("/").is_mount());
+            assert!(!dir.is_mount() && !file.is_mount());
+            assert!(!file.is_fifo() && !file.is_socket() && !file.is_char_device());
+
+            let hardlink = PosixPath::from("/hardlink");
+            hardlink.hardlink_to(&file).unwrap();
+            assert!(hardlink.samefile(&file).unwrap());
+            assert!(PosixPath::from("/tmp/..")
+                .samefile(&PosixPath::from("/"))
+                .unwrap());
+            let copy = PosixPath::from("/copy");
+            copy.write_text("Hello").unwrap();
+            assert!(!copy.samefile(&file).unwrap());
+        });
+    }
+}
+
+#[cfg(feature = "std")]
+mod mount {
+    use std::io::ErrorKind;
+
+    use pathlib::{parse_mountinfo, MountInfo};
+
+    #[test]
+    fn parse() {
+        let mounts = parse_mountinfo(include_str!("fixtures/mountinfo")).unwrap();
+        assert_eq!(mounts.len(), 9);
+        assert_eq!(
+            mounts[0],
+            MountInfo {
+                mount_id: 22,
+                parent_id: 1,
+                major: 8,
+                minor: 2,
+                root: "/".into(),
+                mount_point: "/".into(),
+                mount_options: vec!["rw".into(), "relatime".into()],
+                optional_fields: vec!["shared:1".into()],
+                fs_type: "ext4".into(),
+                source: "/dev/sda2".into(),
+                super_options: vec!["rw".into(), "errors=remount-ro".into()],
+            }
+        );
+        assert!(!mounts[0].is_read_only());
+        assert!(mounts[5].is_read_only());
+        assert_eq!(
+            (mounts[6].root.as_str(), mounts[6].fs_type.as_str()),
+            ("/exports/home", "nfs4")
+        );
+        assert!(mounts[6].optional_fields.is_empty());
+        assert_eq!(mounts[7].mount_point, "/mnt/my disk");
+        assert!(mounts[7].is_read_only());
+        assert_eq!(mounts[8].optional_fields, ["shared:23", "master:5"]);
+    }
+
+    #[test]
+    fn find() {
+        let mounts = parse_mountinfo(include_str!("fixtures/mountinfo")).unwrap();
+        let find = |path| MountInfo::find(&mounts, path).map(|mount| mount.mount_id);
+        assert_eq!(find("/"), Some(22));
+        assert_eq!(find("/usr/bin"), Some(22));
+        assert_eq!(find("/dev/shm/file"), Some(26));
+        assert_eq!(find("/devices"), Some(22));
+        assert_eq!(find("/tmp"), Some(44));
+        assert_eq!(find("/home/user/file"), Some(42));
+        assert_eq!(find("/mnt/my disk/file"), Some(43));
+        assert_eq!(find("relative"), None);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(parse_mountinfo("").unwrap().is_empty());
+        for text in [
+            "22 1 8:2 / / rw shared:1 ext4 /dev/sda2 rw",
+            "22 1 8 / / rw - ext4 /dev/sda2 rw",
+            "22 1 8:2 / / rw - ext4 /dev/sda2 rw extra",
+        ] {
+            let err = parse_mountinfo(text).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(miri, ignore)]
+    fn host() {
+        use pathlib::{Path, PosixPath};
+
+        let root = PosixPath::from("/").mount_info().unwrap();
+        assert_eq!(root.mount_point, "/");
+        let proc = PosixPath::from("/proc/self").mount_info().unwrap();
+        assert_eq!(
+            (proc.mount_point.as_str(), proc.fs_type.as_str()),
+            ("/proc", "proc")
+        );
+        assert_eq!(
+            PosixPath::from("/missing").mount_info().unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+mod usage {
+    use std::io::Result;
+
+    use pathlib::{
+        with_file_system, FileSystem, MemoryFileSystem, Metadata, OpenFile, OpenOptions, Path,
+        PosixPath, ReadDir, SizeOptions, TreeSize,
+    };
+
+    /// A backend that reports the files below `/cache/mnt` on another device.
+    struct Mounted(MemoryFileSystem);
+
+    impl FileSystem for Mounted {
+        fn current_dir(&self) -> Result<String> {
+            self.0.current_dir()
+        }
+
+        fn metadata(&self, path: &str) -> Result<Metadata> {
+            self.symlink_metadata(path)
+        }
+
+        fn symlink_metadata(&self, path: &str) -> Result<Metadata> {
+            let mut metadata = self.0.symlink_metadata(path)?;
+            if path.starts_with("/cache/mnt") {
+                metadata.dev += 1;
+            }
+            Ok(metadata)
+        }
+
+        fn canonicalize(&self, path: &str) -> Result<String> {
+            self.0.canonicalize(path)
+        }
+
+        fn read_dir(&self, path: &str) -> Result<ReadDir> {
+            self.0.read_dir(path)
+        }
+
+        fn read_link(&self, path: &str) -> Result<String> {
+            self.0.read_link(path)
+        }
+
+        fn open(&self, path: &str, options: &OpenOptions) -> Result<Box<dyn OpenFile>> {
+            self.0.open(path, options)
+        }
+
+        fn create_dir(&self, path: &str, mode: u32) -> Result<()> {
+            self.0.create_dir(path, mode)
+        }
+
+        fn remove_file(&self, path: &str) -> Result<()> {
+            self.0.remove_file(path)
+        }
+
+        fn remove_dir(&self, path: &str) -> Result<()> {
+            self.0.remove_dir(path)
+        }
+
+        fn rename(&self, from: &str, to: &str) -> Result<()> {
+            self.0.rename(from, to)
+        }
+
+        fn symlink(&self, target: &str, link: &str) -> Result<()> {
+            self.0.symlink(target, link)
+        }
+
+        fn hard_link(&self, original: &str, link: &str) -> Result<()> {
+            self.0.hard_link(original, link)
+        }
+
+        fn set_permissions(&self, path: &str, mode: u32) -> Result<()> {
+            self.0.set_permissions(path, mode)
+        }
+    }
+
+    fn path(path: &str) -> PosixPath {
+        PosixPath::from(path)
+    }
+
+    #[test]
+    fn tree_size() {
+        with_file_system(Mounted(MemoryFileSystem::new()), || {
+            path("/cache/a/b").mkdir(0o755, true, false).unwrap();
+            path("/cache/mnt").mkdir(0o755, false, false).unwrap();
+            path("/cache/a/one").write_bytes(&[0; 100]).unwrap();
+            path("/cache/a/b/two").write_bytes(&[0; 5000]).unwrap();
+            path("/cache/a/b/link")
+                .hardlink_to(&path("/cache/a/one"))
+                .unwrap();
+            path("/cache/symlink").symlink_to(&path("a")).unwrap();
+            path("/cache/mnt/three").write_bytes(&[0; 10]).unwrap();
+
+            let options = SizeOptions::default();
+            let size = path("/cache").tree_size(&options).unwrap();
+            assert_eq!(size.size, 4 * 4096 + 100 + 5000 + 1 + 10);
+            assert_eq!(size.files, 4 + 4);
+            assert!(size.dirs.is_empty());
+
+            let options = SizeOptions {
+                allocated: true,
+                one_file_system: true,
+                breakdown: true,
+            };
+            let size = path("/cache").tree_size(&options).unwrap();
+            let dirs = |dirs: &[(&str, u64)]| -> Vec<_> {
+                dirs.iter().map(|(dir, size)| (path(dir), *size)).collect()
+            };
+            assert_eq!(
+                size,
+                TreeSize {
+                    size: 6 * 4096,
+                    files: 3 + 3,
+                    dirs: dirs(&[
+                        ("/cache/a/b", 3 * 4096),
+                        ("/cache/a", 5 * 4096),
+                        ("/cache", 6 * 4096),
+                    ]),
+                }
+            );
+
+            let file = path("/cache/a/b/two").tree_size(&options).unwrap();
+            assert_eq!((file.size, file.files), (2 * 4096, 1));
+            assert!(path("/missing").tree_size(&options).is_err());
+        });
+    }
+
+    #[test]
+    fn disk_usage() {
+        with_file_system(MemoryFileSystem::new(), || {
+            let err = path("/").disk_usage().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        });
+        #[cfg(unix)]
+        {
+            let usage = path(".").disk_usage().unwrap();
+            assert!(usage.total > 0 && usage.used <= usage.total && usage.free <= usage.total);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod mode {
+    use std::io::ErrorKind;
+
+    use pathlib::{
+        with_file_system, MemoryFileSystem, ModeChange, ParseModeError, Path, PosixPath,
+    };
+
+    #[test]
+    fn apply() {
+        // The mode, the old bits, whether it is a directory, the umask and the new bits.
+        let cases = [
+            ("u+x", 0o644, false, 0o022, 0o744),
+            ("go-w", 0o666, false, 0o022, 0o644),
+            ("a=r,u+w", 0o777, false, 0o022, 0o644),
+            ("+x", 0o644, false, 0o022, 0o755),
+            ("+x", 0o644, false, 0o077, 0o744),
+            ("-w", 0o666, false, 0o022, 0o466),
+            ("=r", 0o777, false, 0o022, 0o444),
+            ("u=g", 0o654, false, 0o022, 0o554),
+            ("go=u-w", 0o750, false, 0o022, 0o755),
+            ("a+X", 0o644, false, 0o022, 0o644),
+            ("a+X", 0o744, false, 0o022, 0o755),
+            ("a+X", 0o644, true, 0o022, 0o755),
+            ("u+s,g+s", 0o755, false, 0o022, 0o6755),
+            ("o+t", 0o777, true, 0o022, 0o1777),
+            ("u=", 0o4755, false, 0o022, 0o055),
+            ("ug=rw,o=", 0o777, false, 0o022, 0o660),
+            ("g+s", 0o755, true, 0o022, 0o2755),
+            ("755", 0o2775, true, 0o022, 0o2755),
+            ("755", 0o2775, false, 0o022, 0o755),
+            ("00755", 0o2775, true, 0o022, 0o755),
+            ("2755", 0o755, true, 0o022, 0o2755),
+            ("+020", 0o755, false, 0o022, 0o775),
+            ("-7", 0o777, false, 0o022, 0o770),
+            ("a=rwx", 0o4000, true, 0o022, 0o4777),
+        ];
+        for (mode, old, is_dir, umask, new) in cases {
+            let change = mode.parse::<ModeChange>().unwrap();
+            assert_eq!(change.apply(old, is_dir, umask), new, "{mode} on {old:o}");
+        }
+        assert_eq!(ModeChange::from(0o600).apply(0o2775, true, 0o022), 0o600);
+        assert!(ModeChange::parse("+x").unwrap().uses_umask());
+        assert!(!ModeChange::parse("a+x,755").is_ok_and(|change| change.uses_umask()));
+    }
+
+    #[test]
+    fn invalid() {
+        for mode in [
+            "", "8", "10000", "u", "u+q", "x", "u+x,", ",u+x", "u+755", "755 ", "u+x g+x",
+        ] {
+            assert_eq!(
+                ModeChange::parse(mode),
+                Err(ParseModeError::new(mode)),
+                "{mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn memory() {
+        let fs = MemoryFileSystem::new();
+        let _ = fs.set_umask(0o077);
+        with_file_system(fs.clone(), || {
+            let dir = PosixPath::from("/a/b");
+            dir.mkdir_with(&"u=rwx,go=rx".parse().unwrap(), true, false)
+                .unwrap();
+            assert_eq!(dir.metadata().unwrap().mode, 0o755);
+            assert_eq!(PosixPath::from("/a").metadata().unwrap().mode, 0o700);
+            dir.mkdir_with(&ModeChange::from(0o700), false, true)
+                .unwrap();
+            assert_eq!(dir.metadata().unwrap().mode, 0o755);
+
+            let file = &dir / &PosixPath::from("file");
+            file.touch_with(&ModeChange::from(0o640), false).unwrap();
+            assert_eq!(file.metadata().unwrap().mode, 0o640);
+            let err = file
+                .touch_with(&ModeChange::from(0o600), false)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+            file.touch_with(&ModeChange::from(0o600), true).unwrap();
+            assert_eq!(file.metadata().unwrap().mode, 0o640);
+            let new = &dir / &PosixPath::from("new");
+            new.touch_with(&"=rw".parse().unwrap(), false).unwrap();
+            assert_eq!(new.metadata().unwrap().mode, 0o600);
+
+            file.chmod_symbolic("+x").unwrap();
+            assert_eq!(file.metadata().unwrap().mode, 0o740);
+            file.chmod_symbolic("g-r,o=u").unwrap();
+            assert_eq!(file.metadata().unwrap().mode, 0o707);
+            let err = file.chmod_symbolic("u+z").unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert!(err.get_ref().unwrap().is::<ParseModeError>());
+
+            let _ = fs.set_umask(0o022);
+            let private = PosixPath::from("/c/private");
+            private.mkdir(0o700, true, false).unwrap();
+            assert_eq!(private.metadata().unwrap().mode, 0o700);
+            assert_eq!(PosixPath::from("/c").metadata().unwrap().mode, 0o755);
+        });
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn std() {
+        let dir = PosixPath::from("./tmp-mode");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        dir.mkdir_with(&ModeChange::from(0o777), false, false)
+            .unwrap();
+        assert_eq!(dir.metadata().unwrap().mode, 0o777);
+        let file = &dir / &PosixPath::from("file");
+        file.touch_with(&"a=rw".parse().unwrap(), false).unwrap();
+        assert_eq!(file.metadata().unwrap().mode, 0o666);
+        file.chmod_symbolic("go-w,u+x").unwrap();
+        assert_eq!(file.metadata().unwrap().mode, 0o744);
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod ownership {
+    use std::cell::RefCell;
+    use std::io::ErrorKind;
+    use std::rc::Rc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pathlib::{
+        with_file_system, AuditFileSystem, MemoryFileSystem, Path, PosixPath, UnknownOwnerError,
+    };
+
+    #[test]
+    fn memory() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let fs = AuditFileSystem::new(MemoryFileSystem::new(), move |event| {
+            sink.borrow_mut().push(event.to_string())
+        });
+        with_file_system(fs, || {
+            let file = PosixPath::from("/file");
+            file.touch(0o644, false).unwrap();
+            let link = PosixPath::from("/link");
+            link.symlink_to(&file).unwrap();
+
+            file.chown(Some("1000"), Some("100")).unwrap();
+            let metadata = file.metadata().unwrap();
+            assert_eq!((metadata.uid, metadata.gid), (1000, 100));
+            link.lchown(Some("7"), None).unwrap();
+            assert_eq!(link.symlink_metadata().unwrap().uid, 7);
+            assert_eq!(file.metadata().unwrap().uid, 1000);
+            link.chown(None, Some("5")).unwrap();
+            assert_eq!(file.metadata().unwrap().gid, 5);
+
+            let err = file.chown(Some("no-such-user"), None).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+            let err = err.get_ref().unwrap().downcast_ref::<UnknownOwnerError>();
+            assert_eq!(err, Some(&UnknownOwnerError::user("no-such-user")));
+
+            let atime = UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789);
+            let mtime = UNIX_EPOCH + Duration::new(1_500_000_000, 1);
+            link.set_times(Some(atime), Some(mtime), true).unwrap();
+            let metadata = file.metadata().unwrap();
+            assert_eq!(
+                (metadata.atime, metadata.mtime),
+                (Some(atime.into()), Some(mtime.into()))
+            );
+            link.set_times(None, Some(atime), false).unwrap();
+            assert_eq!(link.symlink_metadata().unwrap().mtime, Some(atime.into()));
+            assert_eq!(file.metadata().unwrap().mtime, Some(mtime.into()));
+            let before = UNIX_EPOCH - Duration::new(1, 500_000_000);
+            file.set_times(Some(before), Some(before), true).unwrap();
+            assert_eq!(file.metadata().unwrap().atime, Some(before.into()));
+        });
+        assert_eq!(
+            events.borrow()[2..],
+            [
+                "chown 1000:100 /file",
+                "chown -h 7: /link",
+                "chown :5 /link",
+                "touch -a -d @1000000000.123456789 /link; touch -m -d @1500000000.000000001 /link",
+                "touch -h -m -d @1000000000.123456789 /link",
+                "touch -d @-1.500000000 /file",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn std() {
+        let dir = PosixPath::from("./tmp-ownership");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        dir.mkdir(0o755, false, false).unwrap();
+        let file = &dir / &PosixPath::from("file");
+        file.touch(0o644, false).unwrap();
+        let link = &dir / &PosixPath::from("link");
+        link.symlink_to(&PosixPath::from("file")).unwrap();
+
+        let metadata = file.metadata().unwrap();
+        let (uid, gid) = (metadata.uid.to_string(), metadata.gid.to_string());
+        file.chown(Some(&uid), Some(&gid)).unwrap();
+        link.lchown(Some(&file.owner().unwrap()), Some(&file.group().unwrap()))
+            .unwrap();
+        let err = file.chown(None, Some("no-such-group")).unwrap_err();
+        let err = err.get_ref().unwrap().downcast_ref::<UnknownOwnerError>();
+        assert!(err.is_some_and(UnknownOwnerError::is_group));
+
+        let atime = UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789);
+        let mtime = UNIX_EPOCH + Duration::new(1_500_000_000, 987_654_321);
+        file.set_times(Some(atime), Some(mtime), true).unwrap();
+        let metadata = file.metadata().unwrap();
+        assert_eq!(
+            (metadata.atime, metadata.mtime),
+            (Some(atime.into()), Some(mtime.into()))
+        );
+        link.set_times(None, Some(atime), false).unwrap();
+        assert_eq!(link.symlink_metadata().unwrap().mtime, Some(atime.into()));
+        assert_eq!(file.metadata().unwrap().mtime, Some(mtime.into()));
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+
+    #[test]
+    #[cfg(windows)]
+    #[cfg_attr(miri, ignore)]
+    fn windows() {
+        use pathlib::WindowsPath;
+
+        let dir = WindowsPath::from(".\\tmp-times");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        dir.mkdir(0o755, false, false).unwrap();
+        let file = &dir / &WindowsPath::from("file");
+        file.touch(0o644, false).unwrap();
+        file.chmod(0o444).unwrap();
+        let mtime = UNIX_EPOCH + Duration::new(1_500_000_000, 0);
+        for path in [&dir, &file] {
+            path.set_times(Some(mtime), Some(mtime), true).unwrap();
+            assert_eq!(path.metadata().unwrap().mtime, Some(mtime.into()));
+        }
+        file.chmod(0o644).unwrap();
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod xattrs {
+    use std::cell::RefCell;
+    use std::io::ErrorKind;
+    use std::rc::Rc;
+
+    use pathlib::{
+        with_file_system, AuditFileSystem, FileSystem, MemoryFileSystem, OpenOptions,
+        OverlayFileSystem, Path, PathError, PosixPath, UnsupportedXattrError,
+    };
+
+    /// Returns the [UnsupportedXattrError] that `err` reports, if any.
+    fn unsupported(err: &std::io::Error) -> Option<&UnsupportedXattrError> {
+        let err = err.get_ref()?.downcast_ref::<PathError>()?;
+        err.io_error().get_ref()?.downcast_ref()
+    }
+
+    #[test]
+    fn memory() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let fs = AuditFileSystem::new(MemoryFileSystem::new(), move |event| {
+            sink.borrow_mut().push(event.to_string())
+        });
+        with_file_system(fs, || {
+            let file = PosixPath::from("/file");
+            file.touch(0o644, false).unwrap();
+            let link = PosixPath::from("/link");
+            link.symlink_to(&file).unwrap();
+            assert_eq!(file.xattrs(true).unwrap(), Vec::<String>::new());
+
+            file.set_xattr("user.comment", b"hi", true).unwrap();
+            link.set_xattr("security.capability", &[1, 0xff], true)
+                .unwrap();
+            link.set_xattr("trusted.link", b"", false).unwrap();
+            assert_eq!(
+                link.xattrs(true).unwrap(),
+                ["security.capability", "user.comment"]
+            );
+            assert_eq!(link.xattrs(false).unwrap(), ["trusted.link"]);
+            assert_eq!(
+                file.get_xattr("user.comment", true).unwrap().as_deref(),
+                Some(&b"hi"[..])
+            );
+            assert_eq!(file.get_xattr("user.missing", true).unwrap(), None);
+            assert_eq!(link.get_xattr("user.comment", false).unwrap(), None);
+
+            link.remove_xattr("user.comment", true).unwrap();
+            let err = file.remove_xattr("user.comment", true).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+
+            let err = file.set_xattr("comment", b"hi", true).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Unsupported);
+            let err = unsupported(&err).unwrap();
+            assert_eq!(err.name(), Some("comment"));
+            assert_eq!(
+                err.to_string(),
+                "extended attribute comment is not supported"
+            );
+        });
+        assert_eq!(
+            events.borrow()[2..],
+            [
+                "setfattr -n user.comment -v 0x6869 /file",
+                "setfattr -n security.capability -v 0x01ff /link",
+                "setfattr -h -n trusted.link -v 0x /link",
+                "setfattr -x user.comment /link",
+            ]
+        );
+    }
+
+    #[test]
+    fn overlay() {
+        let lower = MemoryFileSystem::new();
+        let mut options = OpenOptions::new();
+        let _ = options.write(true).create(true);
+        let _ = lower.open("/file", &options).unwrap();
+        lower
+            .set_xattr("/file", "user.origin", b"lower", true)
+            .unwrap();
+        with_file_system(OverlayFileSystem::new(lower.clone()), || {
+            let file = PosixPath::from("/file");
+            assert_eq!(file.xattrs(true).unwrap(), ["user.origin"]);
+            file.set_xattr("user.comment", b"upper", true).unwrap();
+            assert_eq!(file.xattrs(true).unwrap(), ["user.comment", "user.origin"]);
+        });
+        assert_eq!(lower.list_xattrs("/file", true).unwrap(), ["user.origin"]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(miri, ignore)]
+    fn std() {
+        let dir = PosixPath::from("./tmp-xattrs");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        dir.mkdir(0o755, false, false).unwrap();
+        let file = &dir / &PosixPath::from("file");
+        file.touch(0o644, false).unwrap();
+        let link = &dir / &PosixPath::from("link");
+        link.symlink_to(&PosixPath::from("file")).unwrap();
+
+        match file.set_xattr("user.comment", b"hi", true) {
+            Ok(()) => {
+                assert!(file
+                    .xattrs(true)
+                    .unwrap()
+                    .contains(&"user.comment".to_owned()));
+                assert_eq!(
+                    link.get_xattr("user.comment", true).unwrap().as_deref(),
+                    Some(&b"hi"[..])
+                );
+                assert_eq!(file.get_xattr("user.missing", true).unwrap(), None);
+                link.remove_xattr("user.comment", true).unwrap();
+                let err = file.remove_xattr("user.comment", true).unwrap_err();
+                assert_eq!(err.kind(), ErrorKind::NotFound);
+            }
+            Err(err) => {
+                assert_eq!(err.kind(), ErrorKind::Unsupported);
+                assert_eq!(unsupported(&err).unwrap().name(), Some("user.comment"));
+            }
+        }
+        // Linux does not allow user attributes on symlinks.
+        assert!(link.set_xattr("user.comment", b"hi", false).is_err());
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+}
+
+#[cfg(feature = "std")]
+mod copy {
+    use std::cell::RefCell;
+    use std::io::ErrorKind;
+    use std::rc::Rc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pathlib::{
+        with_file_system, AuditFileSystem, CopyOptions, Fault, FaultRule, FaultyFileSystem,
+        MemoryFileSystem, Operation, OverwritePolicy, Path, PosixPath,
+    };
+
+    #[test]
+    fn file() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let fs = AuditFileSystem::new(MemoryFileSystem::new(), move |event| {
+            sink.borrow_mut().push(event.to_string())
+        });
+        with_file_system(fs, || {
+            let src = PosixPath::from("/src");
+            src.write_bytes(b"hello").unwrap();
+            src.chmod(0o640).unwrap();
+            let mtime = UNIX_EPOCH + Duration::new(1_000_000_000, 5);
+            src.set_times(Some(mtime), Some(mtime), true).unwrap();
+            src.set_xattr("user.origin", b"src", true).unwrap();
+            PosixPath::from("/dir").mkdir(0o755, false, false).unwrap();
+
+            let progress = Rc::new(RefCell::new(Vec::new()));
+            let sink = progress.clone();
+            let mut options = CopyOptions::new().progress(move |progress| {
+                sink.borrow_mut()
+                    .push((progress.to.to_owned(), progress.copied, progress.size))
+            });
+            let dest = PosixPath::from("/dir");
+            assert_eq!(src.copy_to(&dest, &mut options).unwrap(), 5);
+            let copy = PosixPath::from("/dir/src");
+            assert_eq!(copy.read_bytes().unwrap(), b"hello");
+            let metadata = copy.metadata().unwrap();
+            assert_eq!(metadata.mode, 0o640);
+            assert_ne!(metadata.mtime, Some(mtime.into()));
+            assert_eq!(copy.xattrs(true).unwrap(), Vec::<String>::new());
+            assert_eq!(*progress.borrow(), [("/dir/src".to_owned(), 5, 5)]);
+
+            let copy2 = PosixPath::from("/copy2");
+            let mut options = CopyOptions::new().times(true).xattrs(true);
+            let _ = src.copy_to(&copy2, &mut options).unwrap();
+            assert_eq!(copy2.metadata().unwrap().mtime, Some(mtime.into()));
+            assert_eq!(
+                copy2.get_xattr("user.origin", true).unwrap().as_deref(),
+                Some(&b"src"[..])
+            );
+
+            src.write_bytes(b"changed").unwrap();
+            let mut options = CopyOptions::new().overwrite(OverwritePolicy::Error);
+            let err = src.copy_to(&copy2, &mut options).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+            let mut options = CopyOptions::new().overwrite(OverwritePolicy::Skip);
+            assert_eq!(src.copy_to(&copy2, &mut options).unwrap(), 0);
+            assert_eq!(copy2.read_bytes().unwrap(), b"hello");
+            let mut options = CopyOptions::new().overwrite(OverwritePolicy::Update);
+            assert_eq!(src.copy_to(&copy2, &mut options).unwrap(), 7);
+            assert_eq!(copy2.read_bytes().unwrap(), b"changed");
+            assert_eq!(src.copy_to(&copy2, &mut options).unwrap(), 0);
+
+            let err = src.copy_to(&src, &mut CopyOptions::new()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            let err = dest.copy_to(&copy2, &mut CopyOptions::new()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::IsADirectory);
+        });
+        let events = events.borrow();
+        let copies: Vec<_> = events
+            .iter()
+            .filter(|event| event.starts_with("cp "))
+            .collect();
+        assert_eq!(
+            copies,
+            ["cp /src /dir/src", "cp /src /copy2", "cp /src /copy2"]
+        );
+    }
+
+    #[test]
+    fn symlinks() {
+        with_file_system(MemoryFileSystem::new(), || {
+            let file = PosixPath::from("/file");
+            file.write_bytes(b"data").unwrap();
+            let link = PosixPath::from("/link");
+            link.symlink_to(&PosixPath::from("file")).unwrap();
+            let target = PosixPath::from("/target");
+            target.write_bytes(b"old").unwrap();
+            let dest = PosixPath::from("/dest");
+            dest.symlink_to(&target).unwrap();
+
+            let mut options = CopyOptions::new().follow_symlinks(false);
+            assert_eq!(link.copy_to(&dest, &mut options).unwrap(), 0);
+            assert_eq!(dest.read_link().unwrap(), PosixPath::from("file"));
+            assert_eq!(target.read_bytes().unwrap(), b"old");
+
+            let copy = PosixPath::from("/copy");
+            assert_eq!(link.copy_to(&copy, &mut CopyOptions::new()).unwrap(), 4);
+            assert!(!copy.is_symlink());
+            assert_eq!(copy.read_bytes().unwrap(), b"data");
+        });
+    }
+
+    #[test]
+    fn tree() {
+        with_file_system(MemoryFileSystem::new(), || {
+            let src = PosixPath::from("/src");
+            (&src / &PosixPath::from("sub"))
+                .mkdir(0o755, true, false)
+                .unwrap();
+            (&src / &PosixPath::from("a.txt"))
+                .write_bytes(b"abc")
+                .unwrap();
+            (&src / &PosixPath::from("sub/b.txt"))
+                .write_bytes(b"de")
+                .unwrap();
+            (&src / &PosixPath::from("sub/c.pyc"))
+                .write_bytes(b"x")
+                .unwrap();
+            (&src / &PosixPath::from("sub/link"))
+                .symlink_to(&PosixPath::from("b.txt"))
+                .unwrap();
+            let sub = &src / &PosixPath::from("sub");
+            sub.chmod(0o700).unwrap();
+            let mtime = UNIX_EPOCH + Duration::from_secs(1_234_567_890);
+            sub.set_times(Some(mtime), Some(mtime), true).unwrap();
+
+            let totals = Rc::new(RefCell::new(Vec::new()));
+            let sink = totals.clone();
+            let mut options = CopyOptions::new()
+                .times(true)
+                .follow_symlinks(false)
+                .ignore("*.pyc")
+                .progress(move |progress| sink.borrow_mut().push(progress.total));
+            let dest = PosixPath::from("/dest");
+            assert_eq!(src.copy_tree(&dest, &mut options).unwrap(), 5);
+            assert_eq!(totals.borrow().last(), Some(&5));
+            let copied = |name: &str| &dest / &PosixPath::from(name);
+            assert_eq!(copied("a.txt").read_bytes().unwrap(), b"abc");
+            assert_eq!(copied("sub/b.txt").read_bytes().unwrap(), b"de");
+            assert!(!copied("sub/c.pyc").try_exists().unwrap());
+            assert_eq!(
+                copied("sub/link").read_link().unwrap(),
+                PosixPath::from("b.txt")
+            );
+            let metadata = copied("sub").metadata().unwrap();
+            assert_eq!((metadata.mode, metadata.mtime), (0o700, Some(mtime.into())));
+
+            let err = src.copy_tree(&dest, &mut CopyOptions::new()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+            copied("a.txt").write_bytes(b"new").unwrap();
+            let mut options = CopyOptions::new()
+                .dirs_exist_ok(true)
+                .overwrite(OverwritePolicy::Skip);
+            assert_eq!(src.copy_tree(&dest, &mut options).unwrap(), 1);
+            assert_eq!(copied("a.txt").read_bytes().unwrap(), b"new");
+            assert_eq!(copied("sub/c.pyc").read_bytes().unwrap(), b"x");
+
+            // A target inside the tree is not copied into itself.
+            let backup = &src / &PosixPath::from("backup");
+            let mut options = CopyOptions::new().follow_symlinks(false);
+            assert_eq!(src.copy_tree(&backup, &mut options).unwrap(), 6);
+            let copied = |name: &str| &backup / &PosixPath::from(name);
+            assert_eq!(copied("sub/b.txt").read_bytes().unwrap(), b"de");
+            assert!(!copied("backup").try_exists().unwrap());
+        });
+    }
+
+    #[test]
+    fn skipped_xattrs() {
+        let fs = Rc::new(FaultyFileSystem::new(MemoryFileSystem::new()));
+        with_file_system(fs.clone(), || {
+            let src = PosixPath::from("/src");
+            src.write_bytes(b"data").unwrap();
+            src.set_xattr("user.a", b"a", true).unwrap();
+            src.set_xattr("user.b", b"b", true).unwrap();
+            let mut options = CopyOptions::new().xattrs(true);
+
+            fs.inject(
+                FaultRule::new(Fault::Error(ErrorKind::PermissionDenied))
+                    .operation(Operation::SetXattr)
+                    .times(1),
+            );
+            let copy = PosixPath::from("/copy");
+            assert_eq!(src.copy_to(&copy, &mut options).unwrap(), 4);
+            assert_eq!(copy.xattrs(true).unwrap().len(), 1);
+
+            fs.inject(
+                FaultRule::new(Fault::Error(ErrorKind::StorageFull)).operation(Operation::SetXattr),
+            );
+            let err = src
+                .copy_to(&PosixPath::from("/copy2"), &mut options)
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::StorageFull);
+        });
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn std() {
+        let dir = PosixPath::from("./tmp-copy");
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir.as_str()).unwrap();
+        }
+        let src = &dir / &PosixPath::from("src");
+        src.mkdir(0o755, true, false).unwrap();
+        let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        let big = &src / &PosixPath::from("big");
+        big.write_bytes(&data).unwrap();
+        let mtime = UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789);
+        big.set_times(Some(mtime), Some(mtime), true).unwrap();
+        (&src / &PosixPath::from("empty")).write_bytes(b"").unwrap();
+
+        let calls = Rc::new(RefCell::new(0));
+        let sink = calls.clone();
+        let mut options = CopyOptions::new()
+            .times(true)
+            .xattrs(true)
+            .progress(move |_| *sink.borrow_mut() += 1);
+        let dest = &dir / &PosixPath::from("dest");
+        let copied = src.copy_tree(&dest, &mut options).unwrap();
+        assert_eq!(copied, data.len() as u64);
+        assert!(*calls.borrow() > 0);
+        let copy = &dest / &PosixPath::from("big");
+        assert_eq!(copy.read_bytes().unwrap(), data);
+        assert_eq!(copy.metadata().unwrap().mtime, Some(mtime.into()));
+        assert_eq!(
+            (&dest / &PosixPath::from("empty")).read_bytes().unwrap(),
+            b""
+        );
+        std::fs::remove_dir_all(dir.as_str()).unwrap();
+    }
+}
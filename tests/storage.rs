@@ -0,0 +1,80 @@
+#![cfg(feature = "alloc")]
+
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use pathlib::{
+    ArcPath, BoxPath, CowPath, PathStorage, Posix, PosixPath, PurePath, RcPath, SmallPath,
+    SmallString, Windows,
+};
+
+const JOIN: &[(&str, &str, &str)] = &[
+    ("/foo", "bar", "/foo/bar"),
+    ("/foo", "/bar", "/bar"),
+    ("/foo/", "bar/baz/", "/foo/bar/baz/"),
+    ("", "bar", "bar"),
+];
+
+fn check_join<S: PathStorage<Error = Infallible>>(a: &str, b: &str, c: &str) {
+    let expected = PosixPath::from(c);
+    let Ok(a) = PosixPath::from(a).to_storage::<S>();
+    let Ok(b) = PosixPath::from(b).to_storage::<S>();
+    assert_eq!(a.join(&b), expected, "{a:?}.join({b:?})");
+    let mut joined = a;
+    joined.join_in_place(&b);
+    assert_eq!(joined, expected, "join_in_place({b:?})");
+}
+
+#[test]
+fn join() {
+    for &(a, b, c) in JOIN {
+        check_join::<String>(a, b, c);
+        check_join::<Box<str>>(a, b, c);
+        check_join::<Rc<str>>(a, b, c);
+        check_join::<Arc<str>>(a, b, c);
+        check_join::<Cow<'_, str>>(a, b, c);
+    }
+}
+
+#[test]
+fn cow_borrows_until_modified() {
+    let source = String::from("/foo/bar.txt");
+    let mut path = CowPath::<Posix>::from(source.as_str());
+    assert!(matches!(path.storage(), Cow::Borrowed(_)));
+    assert_eq!(path.file_name(), Some("bar.txt"));
+    path.join_in_place(&CowPath::from("baz"));
+    assert!(matches!(path.storage(), Cow::Owned(_)));
+    assert_eq!(path.as_str(), "/foo/bar.txt/baz");
+}
+
+#[test]
+fn arc_clones_share_storage() {
+    let path = ArcPath::<Posix>::from("/foo/bar");
+    let clone = path.clone();
+    assert!(Arc::ptr_eq(path.storage(), clone.storage()));
+    assert_eq!(clone.parent().unwrap().as_str(), "/foo");
+}
+
+#[test]
+fn small_spills_to_heap() {
+    let mut path = SmallPath::<Windows, 8>::new();
+    path.try_push("foo").unwrap();
+    assert!(matches!(path.storage(), SmallString::Inline(_)));
+    assert_eq!(path.as_str(), "foo");
+    assert_eq!(format!("{:?}", path.storage()), "Inline(\"foo\")");
+    path.try_push("barbaz").unwrap();
+    assert!(matches!(path.storage(), SmallString::Heap(_)));
+    assert_eq!(path.as_str(), "foo\\barbaz");
+}
+
+#[test]
+fn conversion() {
+    let path = PosixPath::from("/foo/bar");
+    let boxed: BoxPath<Posix> = path.to_storage().unwrap();
+    let rc: RcPath<Posix> = boxed.to_storage().unwrap();
+    assert_eq!(boxed, path);
+    assert_eq!(rc, path);
+    assert_eq!(format!("{rc:?}"), "\"/foo/bar\"");
+}